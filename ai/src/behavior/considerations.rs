@@ -1,23 +1,293 @@
-use core::amethyst::ecs::World;
-use iaus::{curves, ConsiderationFn, Description};
+use super::planner::PlanningWorld;
+use core::{
+    amethyst::{core::math::Point3, ecs::Entity},
+    components::{DigestionComponent, PropertiesComponent},
+    defs::{
+        behavior::{ConsiderationDefinition, ConsiderationInput, CurveDefinition},
+        psyche::NeedsContainer,
+    },
+};
+use derivative::Derivative;
+use iaus::{curves, ConsiderationFn, Curve};
+use psyche::components::MoodComponent;
 
-use std::sync::Arc;
+use std::{ops::RangeInclusive, sync::Arc, time::Duration};
+
+/// Everything a consideration can read about the entity it is scoring for.
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+pub struct ConsiderationContext {
+    pub entity: Entity,
+    pub properties: Option<PropertiesComponent>,
+    pub needs: Option<NeedsContainer>,
+    pub digestion: Option<DigestionComponent>,
+    pub mood: Option<MoodComponent>,
+    pub position: Option<Point3<u32>>,
+    pub idle: Option<Duration>,
+
+    /// The surroundings distances are measured against, shared by every entity scored this frame.
+    #[derivative(Debug = "ignore")]
+    pub world: Arc<PlanningWorld>,
+}
+impl ConsiderationContext {
+    pub fn new(entity: Entity, properties: Option<PropertiesComponent>) -> Self {
+        Self {
+            entity,
+            properties,
+            needs: None,
+            digestion: None,
+            mood: None,
+            position: None,
+            idle: None,
+            world: Arc::new(PlanningWorld::default()),
+        }
+    }
+
+    pub fn with_needs(mut self, needs: Option<NeedsContainer>) -> Self {
+        self.needs = needs;
+        self
+    }
+
+    pub fn with_digestion(mut self, digestion: Option<DigestionComponent>) -> Self {
+        self.digestion = digestion;
+        self
+    }
+
+    pub fn with_mood(mut self, mood: Option<MoodComponent>) -> Self {
+        self.mood = mood;
+        self
+    }
+
+    pub fn with_position(mut self, position: Option<Point3<u32>>) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_idle(mut self, idle: Option<Duration>) -> Self {
+        self.idle = idle;
+        self
+    }
+
+    pub fn with_world(mut self, world: Arc<PlanningWorld>) -> Self {
+        self.world = world;
+        self
+    }
+
+    /// The raw value of an input, before it is shaped by a curve. Inputs the entity has no state
+    /// for read as 0.0, except distance, which reads as unreachable.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn input(&self, input: &ConsiderationInput) -> f32 {
+        match input {
+            ConsiderationInput::Constant(value) => *value,
+            ConsiderationInput::HasProperty(property) => {
+                if self
+                    .properties
+                    .as_ref()
+                    .map_or(false, |properties| properties.contains_value(property))
+                {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ConsiderationInput::Need(kind) => self
+                .needs
+                .as_ref()
+                .map_or(0.0, |needs| f32::from(needs.need(*kind).value)),
+            ConsiderationInput::Hunger => self
+                .digestion
+                .as_ref()
+                .map_or(0.0, |digestion| -f32::from(digestion.calories().value)),
+            ConsiderationInput::Thirst => self
+                .digestion
+                .as_ref()
+                .map_or(0.0, |digestion| -f32::from(digestion.hydration().value)),
+            ConsiderationInput::Mood => self.mood.as_ref().map_or(0.0, |mood| f32::from(mood.mood)),
+            ConsiderationInput::Breakdown => self
+                .mood
+                .as_ref()
+                .and_then(|mood| mood.breakdown)
+                .map_or(0.0, |breakdown| f32::from(breakdown as u8)),
+            ConsiderationInput::IdleTime => self.idle.map_or(0.0, |idle| idle.as_secs_f32()),
+            ConsiderationInput::Distance(property) => self
+                .position
+                .and_then(|position| self.world.nearest(self.entity, position, property))
+                .map_or(std::f32::MAX, |distance| distance as f32),
+        }
+    }
+}
+
+pub fn curve(def: &CurveDefinition) -> Box<dyn Curve<RangeInclusive<f32>>> {
+    match def {
+        CurveDefinition::Linear {
+            range,
+            slope,
+            intercept,
+        } => Box::new(curves::Linear {
+            range: range.0..=range.1,
+            slope: *slope,
+            intercept: *intercept,
+        }),
+        CurveDefinition::Exponential { range, power } => Box::new(curves::Exponential {
+            range: range.0..=range.1,
+            power: *power,
+        }),
+        CurveDefinition::ExponentialDecay { range, magnitude } => {
+            Box::new(curves::ExponentialDecay {
+                range: range.0..=range.1,
+                magnitude: *magnitude,
+            })
+        }
+        CurveDefinition::Logistic {
+            range,
+            steepness,
+            midpoint,
+        } => Box::new(curves::Logistic {
+            range: range.0..=range.1,
+            steepness: *steepness,
+            midpoint: *midpoint,
+        }),
+        CurveDefinition::Logit { range, steepness } => Box::new(curves::Logit {
+            range: range.0..=range.1,
+            steepness: *steepness,
+        }),
+        CurveDefinition::SmoothStep { range } => Box::new(curves::SmoothStep {
+            range: range.0..=range.1,
+        }),
+        CurveDefinition::Inverse { range, magnitude } => Box::new(curves::Inverse {
+            range: range.0..=range.1,
+            magnitude: *magnitude,
+        }),
+        CurveDefinition::Step {
+            range,
+            threshold,
+            low,
+            high,
+        } => Box::new(curves::Step {
+            range: range.0..=range.1,
+            threshold: *threshold,
+            low: *low,
+            high: *high,
+        }),
+        CurveDefinition::PiecewiseLinear { range, points } => Box::new(
+            curves::PiecewiseLinear::new(range.0..=range.1, points.clone()),
+        ),
+    }
+}
+
+pub fn consideration(
+    def: &ConsiderationDefinition,
+) -> Arc<ConsiderationFn<ConsiderationContext, RangeInclusive<f32>>> {
+    let input = def.input.clone();
 
-pub fn hunger() -> Arc<ConsiderationFn<World>> {
     Arc::new(ConsiderationFn::new(
-        Some("consideration 1 (Exp), 2.0exp"),
-        None,
-        Some(Box::new(curves::Exponential {
-            range: std::ops::Range {
-                start: 0.0,
-                end: 256.0,
-            },
-            power: 2.0,
-        })),
-        Box::new(|consideration, _| {
-            let result = consideration.curve.as_ref().unwrap().transform(155.0);
-            log::trace!("{} = {}", consideration.name().unwrap(), result);
+        Some(def.name.as_str()),
+        def.description.as_ref().map(String::as_str),
+        Some(curve(&def.curve)),
+        Box::new(move |consideration, context| {
+            let value = context.input(&input);
+            let result = consideration.curve.as_ref().unwrap().transform(value);
+            log::trace!("{} = {}", consideration.name.as_ref().unwrap(), result);
             result
         }),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{
+        amethyst::ecs::{Builder, World, WorldExt},
+        defs::{
+            behavior::BehaviorDefinition, foliage::FoliageCategory, property::Property,
+            psyche::NeedKind, DefinitionStorage,
+        },
+    };
+    use iaus::Consideration;
+
+    #[test]
+    fn defined_considerations() {
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+
+        let storage =
+            DefinitionStorage::<BehaviorDefinition>::from_folder("../resources/defs/behaviors")
+                .unwrap();
+        let wander = &storage.find("Cow Root").unwrap().buckets[1].decisions[0];
+        assert_eq!(wander.name, "Wander");
+
+        let context = ConsiderationContext::new(entity, None);
+        let always = consideration(&wander.considerations[0]);
+        assert!((always.score(&context) - 1.0).abs() < std::f32::EPSILON);
+
+        let has_pickup = consideration(&ConsiderationDefinition {
+            name: "Can pick up".to_string(),
+            description: None,
+            input: ConsiderationInput::HasProperty(Property::CanPickup),
+            curve: CurveDefinition::Linear {
+                range: (0.0, 1.0),
+                slope: 1.0,
+                intercept: 0.0,
+            },
+        });
+        assert!(has_pickup.score(&context).abs() < std::f32::EPSILON);
+
+        let context = ConsiderationContext::new(
+            entity,
+            Some(PropertiesComponent::from_iter_ref(
+                [Property::CanPickup].iter(),
+            )),
+        );
+        assert!((has_pickup.score(&context) - 1.0).abs() < std::f32::EPSILON);
+    }
+
+    #[test]
+    fn pawn_state_inputs() {
+        let mut world = World::new();
+        let (pawn, near, far) = (
+            world.create_entity().build(),
+            world.create_entity().build(),
+            world.create_entity().build(),
+        );
+
+        let brush = Property::Foliage(FoliageCategory::Brush);
+        let mut surroundings = PlanningWorld::default();
+        surroundings.insert(
+            near,
+            PropertiesComponent::from_iter_ref([brush.clone()].iter()),
+            Some(Point3::new(3, 4, 0)),
+        );
+        surroundings.insert(
+            far,
+            PropertiesComponent::from_iter_ref([brush.clone()].iter()),
+            Some(Point3::new(10, 1, 0)),
+        );
+
+        let mut needs = NeedsContainer::default();
+        needs.need_mut(NeedKind::Social).value = 12;
+
+        let mood = MoodComponent {
+            mood: -40,
+            breakdown: psyche::mood::Breakdown::from_mood(-40),
+            ..MoodComponent::default()
+        };
+
+        let mut context = ConsiderationContext::new(pawn, None)
+            .with_needs(Some(needs))
+            .with_mood(Some(mood))
+            .with_position(Some(Point3::new(1, 1, 0)))
+            .with_idle(Some(Duration::from_millis(2500)))
+            .with_world(Arc::new(surroundings));
+
+        let input = |context: &ConsiderationContext, input| context.input(&input);
+        assert!((input(&context, ConsiderationInput::Need(NeedKind::Social)) - 12.0).abs() < 0.01);
+        assert!((input(&context, ConsiderationInput::IdleTime) - 2.5).abs() < 0.01);
+        assert!((input(&context, ConsiderationInput::Distance(brush.clone())) - 3.0).abs() < 0.01);
+        assert!(input(&context, ConsiderationInput::Hunger).abs() < std::f32::EPSILON);
+        assert!((input(&context, ConsiderationInput::Mood) + 40.0).abs() < 0.01);
+        assert!((input(&context, ConsiderationInput::Breakdown) - 1.0).abs() < 0.01);
+
+        context.position = None;
+        assert!(input(&context, ConsiderationInput::Distance(brush)) >= std::f32::MAX);
+    }
+}
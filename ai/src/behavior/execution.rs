@@ -0,0 +1,413 @@
+use super::{
+    planner::{step_valid, GoapPlannerComponent, GoapState, PlanStep, PlanningWorld},
+    CurrentGoalComponent,
+};
+use crate::components::CurrentActionComponent;
+use core::{
+    amethyst::{
+        core::{SystemDesc, Time},
+        derive::SystemDesc,
+        ecs::{
+            Component, Entities, Entity, Join, Read, ReadStorage, System, SystemData, VecStorage,
+            World, Write, WriteStorage,
+        },
+        shrev::EventChannel,
+    },
+    defs::{action::ActionDefinition, DefinitionStorage},
+    fsm::{ActionEvent, ActionStatus, ActionTarget},
+};
+use std::{collections::VecDeque, sync::Arc};
+
+/// The steps of a resolved plan, performed one at a time through `CurrentActionComponent`.
+#[derive(Debug)]
+pub struct PlanExecutionComponent {
+    steps: VecDeque<PlanStep>,
+    completed: Vec<PlanStep>,
+    current: Option<PlanStep>,
+    needs_plan: bool,
+    last_failure: Option<goap::PlanFailure>,
+}
+impl Default for PlanExecutionComponent {
+    fn default() -> Self {
+        Self {
+            steps: VecDeque::new(),
+            completed: Vec::new(),
+            current: None,
+            needs_plan: true,
+            last_failure: None,
+        }
+    }
+}
+impl PlanExecutionComponent {
+    pub fn needs_plan(&self) -> bool { self.needs_plan }
+
+    pub fn current(&self) -> Option<PlanStep> { self.current }
+
+    pub fn steps(&self) -> impl Iterator<Item = &PlanStep> { self.steps.iter() }
+
+    pub fn last_failure(&self) -> Option<goap::PlanFailure> { self.last_failure }
+
+    pub fn set_plan(&mut self, steps: Vec<PlanStep>) {
+        self.steps = steps.into();
+        self.completed.clear();
+        self.current = None;
+        self.needs_plan = false;
+        self.last_failure = None;
+    }
+
+    /// Records why planning failed. The plan is still needed, so planning is retried.
+    pub fn fail(&mut self, failure: goap::PlanFailure) { self.last_failure = Some(failure); }
+
+    /// Drops the remaining steps so the planner resolves a new plan from the current world.
+    pub fn replan(&mut self) {
+        self.steps.clear();
+        self.completed.clear();
+        self.current = None;
+        self.needs_plan = true;
+    }
+
+    fn advance(&mut self) -> Option<PlanStep> {
+        if let Some(step) = self.current.take() {
+            self.completed.push(step);
+        }
+        self.current = self.steps.pop_front();
+        self.current
+    }
+}
+impl Component for PlanExecutionComponent {
+    type Storage = VecStorage<Self>;
+}
+
+fn action_event(
+    entity: Entity,
+    (action, target): PlanStep,
+    action_defs: &DefinitionStorage<ActionDefinition>,
+) -> Option<ActionEvent> {
+    action_defs.get(action).map(|action| {
+        ActionEvent::new(
+            Some(entity),
+            target.map(ActionTarget::Entity).into_iter().collect(),
+            action.event.clone(),
+        )
+    })
+}
+
+/// Performs planned steps in order, firing each as an `ActionEvent` and tracking it on
+/// `CurrentActionComponent`. Steps advance on `ActionStatus::Success`. A failed or cancelled
+/// step, or one whose preconditions no longer hold, sends the entity back to the planner.
+#[derive(Default, SystemDesc)]
+pub struct PlanExecutionSystem;
+impl<'s> System<'s> for PlanExecutionSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, Time>,
+        Read<'s, DefinitionStorage<ActionDefinition>>,
+        Write<'s, EventChannel<ActionEvent>>,
+        Read<'s, Arc<PlanningWorld>>,
+        ReadStorage<'s, GoapPlannerComponent>,
+        WriteStorage<'s, PlanExecutionComponent>,
+        WriteStorage<'s, CurrentActionComponent>,
+        WriteStorage<'s, CurrentGoalComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            time,
+            action_defs,
+            mut action_channel,
+            planning_world,
+            planner_storage,
+            mut execution_storage,
+            mut action_storage,
+            mut goal_storage,
+        ): Self::SystemData,
+    ) {
+        let planning_world: &PlanningWorld = &planning_world;
+
+        let mut finished = Vec::new();
+        for (entity, planner_data, execution) in
+            (&entities, &planner_storage, &mut execution_storage).join()
+        {
+            if execution.needs_plan() {
+                continue;
+            }
+
+            let state = GoapState::new(
+                entity,
+                time.frame_number(),
+                planner_data.condition_cache(),
+                planning_world,
+            );
+
+            let status = action_storage.get(entity).map(|action| action.status);
+            let next = match (execution.current(), status) {
+                (None, _) | (Some(_), Some(ActionStatus::Success)) => execution.advance(),
+                // A missing action was cancelled by removing it
+                (Some(_), None)
+                | (Some(_), Some(ActionStatus::Failure))
+                | (Some(_), Some(ActionStatus::Cancelled)) => {
+                    log::debug!("Plan step failed for {:?}, replanning", entity);
+                    action_storage.remove(entity);
+                    execution.replan();
+                    continue;
+                }
+                (Some(step), Some(_)) => {
+                    if !step_valid(&state, &action_defs, &execution.completed, step) {
+                        log::debug!("Plan step invalidated for {:?}, replanning", entity);
+                        action_storage.remove(entity);
+                        execution.replan();
+                    }
+                    continue;
+                }
+            };
+
+            let step = if let Some(step) = next {
+                step
+            } else {
+                // Every step succeeded, so the goal is done
+                log::trace!("Plan complete for {:?}", entity);
+                finished.push(entity);
+                continue;
+            };
+
+            let event = match action_event(entity, step, &action_defs) {
+                Some(event) if step_valid(&state, &action_defs, &execution.completed, step) => {
+                    event
+                }
+                _ => {
+                    log::debug!(
+                        "Plan step {:?} is invalid for {:?}, replanning",
+                        step,
+                        entity
+                    );
+                    action_storage.remove(entity);
+                    execution.replan();
+                    continue;
+                }
+            };
+
+            log::trace!("Starting plan step {:?} for {:?}", step, entity);
+            action_channel.single_write(event.clone());
+            action_storage
+                .insert(entity, CurrentActionComponent::new(event))
+                .unwrap();
+        }
+
+        for entity in finished {
+            action_storage.remove(entity);
+            goal_storage.remove(entity);
+            execution_storage.remove(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behavior::planner::{
+        ConditionCache, GoapPlannerSystemDesc, PlanningWorldSystemDesc,
+    };
+    use core::{
+        amethyst::{
+            core::math::Point3,
+            ecs::{Builder, RunNow, WorldExt},
+        },
+        components::{
+            ItemParentComponent, ItemParentRelationship, PropertiesComponent, TilePosition,
+        },
+        defs::{
+            foliage::FoliageCategory,
+            property::{MovementFlags, Property},
+            Named,
+        },
+        fsm::ActionTarget,
+    };
+
+    #[test]
+    fn advance_tracks_completed_steps() {
+        let mut world = World::new();
+        let entities = [world.create_entity().build(), world.create_entity().build()];
+
+        let mut execution = PlanExecutionComponent::default();
+        assert!(execution.needs_plan());
+
+        execution.set_plan(vec![(0, Some(entities[0])), (1, Some(entities[1]))]);
+        assert!(!execution.needs_plan());
+        assert_eq!(execution.current(), None);
+
+        assert_eq!(execution.advance(), Some((0, Some(entities[0]))));
+        assert_eq!(execution.advance(), Some((1, Some(entities[1]))));
+        assert_eq!(execution.completed, vec![(0, Some(entities[0]))]);
+        assert_eq!(execution.advance(), None);
+        assert_eq!(execution.completed.len(), 2);
+
+        execution.replan();
+        assert!(execution.needs_plan());
+        assert!(execution.completed.is_empty());
+    }
+
+    #[test]
+    fn plan_fires_events_in_order() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let action_defs =
+            DefinitionStorage::<ActionDefinition>::from_folder("../resources/defs/actions")
+                .unwrap();
+        let action = |name: &str| action_defs.find(name).unwrap().id().unwrap();
+        let (move_to, pickup, fell_tree) =
+            (action("Move To"), action("Pickup"), action("Fell Tree"));
+
+        let mut world = World::new();
+        world.insert(Time::default());
+        world.insert(action_defs.clone());
+        let mut snapshot = PlanningWorldSystemDesc::default().build(&mut world);
+        let mut planner = GoapPlannerSystemDesc::default().build(&mut world);
+        let mut executor = PlanExecutionSystemDesc::default().build(&mut world);
+        let mut reader = world
+            .fetch_mut::<EventChannel<ActionEvent>>()
+            .register_reader();
+
+        let cache = world.fetch::<ConditionCache>().clone();
+        let pawn = world
+            .create_entity()
+            .with(PropertiesComponent::from_iter_ref(
+                [Property::Movement(MovementFlags::Walk)].iter(),
+            ))
+            .with(TilePosition(Point3::new(0, 0, 0)))
+            .with(CurrentGoalComponent::new(fell_tree))
+            .with(GoapPlannerComponent::new(cache))
+            .with(PlanExecutionComponent::default())
+            .build();
+        let axe = world
+            .create_entity()
+            .with(PropertiesComponent::from_iter_ref(
+                [Property::CanPickup, Property::Chopping(1)].iter(),
+            ))
+            .with(TilePosition(Point3::new(5, 0, 0)))
+            .build();
+        let tree = world
+            .create_entity()
+            .with(PropertiesComponent::from_iter_ref(
+                [Property::Foliage(FoliageCategory::Tree)].iter(),
+            ))
+            .with(TilePosition(Point3::new(0, 5, 0)))
+            .build();
+
+        // Each step is fired once the one before it succeeds. Picking up the axe takes it off the
+        // map and onto the pawn, which still lets the pawn chop with it.
+        let mut fired = Vec::new();
+        for _ in 0..5 {
+            snapshot.run_now(&world);
+            planner.run_now(&world);
+            executor.run_now(&world);
+
+            fired.extend(
+                world
+                    .fetch::<EventChannel<ActionEvent>>()
+                    .read(&mut reader)
+                    .cloned(),
+            );
+            let picked_up = world
+                .read_storage::<PlanExecutionComponent>()
+                .get(pawn)
+                .and_then(PlanExecutionComponent::current)
+                == Some((pickup, Some(axe)));
+            if let Some(current) = world
+                .write_storage::<CurrentActionComponent>()
+                .get_mut(pawn)
+            {
+                current.status = ActionStatus::Success;
+            }
+            if picked_up {
+                world.write_storage::<TilePosition>().remove(axe);
+                world
+                    .write_storage::<ItemParentComponent>()
+                    .insert(
+                        axe,
+                        ItemParentComponent::new(pawn, ItemParentRelationship::On),
+                    )
+                    .unwrap();
+            }
+        }
+
+        let expected = [
+            (move_to, axe),
+            (pickup, axe),
+            (move_to, tree),
+            (fell_tree, tree),
+        ]
+        .iter()
+        .map(|(action, target)| {
+            ActionEvent::new(
+                Some(pawn),
+                vec![ActionTarget::Entity(*target)],
+                action_defs.get(*action).unwrap().event.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(fired, expected);
+
+        // The goal is done once the last step succeeds
+        assert!(!world.read_storage::<CurrentGoalComponent>().contains(pawn));
+        assert!(!world
+            .read_storage::<PlanExecutionComponent>()
+            .contains(pawn));
+        assert!(!world
+            .read_storage::<CurrentActionComponent>()
+            .contains(pawn));
+    }
+
+    #[test]
+    fn failed_step_replans() {
+        let mut world = World::new();
+        world.insert(Time::default());
+        world.insert(
+            DefinitionStorage::<ActionDefinition>::from_folder("../resources/defs/actions")
+                .unwrap(),
+        );
+        let mut executor = PlanExecutionSystemDesc::default().build(&mut world);
+
+        let target = world
+            .create_entity()
+            .with(PropertiesComponent::default())
+            .with(TilePosition::default())
+            .build();
+        let move_to = world
+            .fetch::<DefinitionStorage<ActionDefinition>>()
+            .find("Move To")
+            .unwrap()
+            .id()
+            .unwrap();
+        let mut execution = PlanExecutionComponent::default();
+        execution.set_plan(vec![(move_to, Some(target))]);
+        execution.advance();
+        let pawn = world
+            .create_entity()
+            .with(GoapPlannerComponent::new(ConditionCache::default()))
+            .with(execution)
+            .with(CurrentActionComponent::new(ActionEvent::new(
+                None,
+                Vec::new(),
+                core::fsm::Event::default(),
+            )))
+            .build();
+        world
+            .write_storage::<CurrentActionComponent>()
+            .get_mut(pawn)
+            .unwrap()
+            .status = ActionStatus::Failure;
+
+        executor.run_now(&world);
+
+        assert!(world
+            .read_storage::<PlanExecutionComponent>()
+            .get(pawn)
+            .unwrap()
+            .needs_plan());
+        assert!(!world
+            .read_storage::<CurrentActionComponent>()
+            .contains(pawn));
+    }
+}
@@ -1,26 +1,190 @@
-use core::amethyst::core::legion::World;
-use iaus::NamedDecision;
-use std::{collections::HashSet, hash::Hash, sync::Arc, time::Duration};
+use considerations::ConsiderationContext;
+use core::{
+    amethyst::ecs::{Component, Entity, VecStorage},
+    defs::{
+        behavior::{BehaviorDefinition, DecisionDefinition},
+        Named,
+    },
+};
+use derivative::Derivative;
+use iaus::{Consideration, Decision, Description, NamedDecision};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    sync::Arc,
+    time::Duration,
+};
 
 pub mod considerations;
+pub mod execution;
 pub mod planner;
 pub mod utility;
 
 pub struct CurrentGoalComponent {
     action_id: u32,
 }
+impl CurrentGoalComponent {
+    pub fn new(action_id: u32) -> Self { Self { action_id } }
+}
+impl Component for CurrentGoalComponent {
+    type Storage = VecStorage<Self>;
+}
+
+/// A decision built from a `DecisionDefinition`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct DefinedDecision {
+    name: String,
+    description: Option<String>,
+    action: Option<String>,
+    base: f32,
+    cooldown: Duration,
+    considerations: Vec<Arc<dyn Consideration<ConsiderationContext>>>,
+}
+impl DefinedDecision {
+    pub fn new(def: &DecisionDefinition) -> Self {
+        Self {
+            name: def.name.clone(),
+            description: def.description.clone(),
+            action: def.action.clone(),
+            base: def.base,
+            cooldown: Duration::from_secs_f32(def.cooldown.max(0.0)),
+            considerations: def
+                .considerations
+                .iter()
+                .map(|c| considerations::consideration(c) as Arc<dyn Consideration<_>>)
+                .collect(),
+        }
+    }
+
+    /// The action to make the entity's goal when this decision is chosen.
+    pub fn action(&self) -> Option<&str> { self.action.as_ref().map(String::as_str) }
+
+    pub fn cooldown(&self) -> Duration { self.cooldown }
+}
+impl Description for DefinedDecision {
+    fn name(&self) -> Option<&str> { Some(self.name.as_ref()) }
+    fn description(&self) -> Option<&str> { self.description.as_ref().map(String::as_str) }
+}
+impl Decision<ConsiderationContext> for DefinedDecision {
+    fn considerations(&self) -> &[Arc<dyn Consideration<ConsiderationContext>>] {
+        self.considerations.as_slice()
+    }
+
+    fn base(&self) -> f32 { self.base }
+}
+impl NamedDecision<ConsiderationContext> for DefinedDecision {}
+
+/// Sent whenever an entity commits to a different decision.
+#[derive(Debug, Clone)]
+pub struct DecisionChosenEvent {
+    pub entity: Entity,
+    pub decision: Arc<DefinedDecision>,
+    pub priority: u32,
+    pub score: f32,
+}
 
 #[derive(Debug)]
 pub struct UtilityStateComponent {
+    /// Name of the `BehaviorDefinition` the decisions were built from.
+    behavior: String,
     pub available_decisions: HashSet<DecisionEntry>,
+    momentum: f32,
+    chosen: Option<Arc<DefinedDecision>>,
+    /// When each decision comes off cooldown, by name.
+    cooldowns: HashMap<String, Duration>,
+}
+impl UtilityStateComponent {
+    pub fn from_definition(def: &BehaviorDefinition) -> Self {
+        Self {
+            behavior: def.name().to_string(),
+            available_decisions: def
+                .buckets
+                .iter()
+                .flat_map(|bucket| {
+                    bucket.decisions.iter().map(move |decision| DecisionEntry {
+                        decision: Arc::new(DefinedDecision::new(decision)),
+                        priority: bucket.priority,
+                        last_score: 0.0,
+                        last_tick: Duration::default(),
+                    })
+                })
+                .collect(),
+            momentum: def.momentum,
+            chosen: None,
+            cooldowns: HashMap::new(),
+        }
+    }
+
+    pub fn behavior(&self) -> &str { &self.behavior }
+
+    /// The decision the entity is currently committed to.
+    pub fn chosen(&self) -> Option<&Arc<DefinedDecision>> { self.chosen.as_ref() }
+
+    /// Commits to the best of the last scored decisions: the highest priority bucket with any
+    /// decision scoring above zero, then the highest score within it. The current decision's
+    /// score is boosted by the behavior's momentum, and decisions on cooldown are skipped.
+    /// Returns the new entry only when the choice changes.
+    pub fn resolve(&mut self, now: Duration) -> Option<DecisionEntry> {
+        self.cooldowns.retain(|_, ready| *ready > now);
+
+        let current = self.chosen.as_ref().map(|decision| decision.name.as_str());
+        let best = self
+            .available_decisions
+            .iter()
+            .filter(|entry| entry.last_score > 0.0)
+            .filter_map(|entry| {
+                let name = entry.decision.name.as_str();
+                if current == Some(name) {
+                    Some((entry, entry.last_score * (1.0 + self.momentum)))
+                } else if self.cooldowns.contains_key(name) {
+                    None
+                } else {
+                    Some((entry, entry.last_score))
+                }
+            })
+            .max_by(|(a, a_score), (b, b_score)| {
+                a.priority.cmp(&b.priority).then(
+                    a_score
+                        .partial_cmp(b_score)
+                        .unwrap_or(std::cmp::Ordering::Equal),
+                )
+            })
+            .map(|(entry, _)| entry.clone());
+
+        if best.as_ref().map(|entry| entry.decision.name.as_str()) == current {
+            return None;
+        }
+
+        if let Some(previous) = self.chosen.take() {
+            if previous.cooldown > Duration::default() {
+                self.cooldowns
+                    .insert(previous.name.clone(), now + previous.cooldown);
+            }
+        }
+        self.chosen = best.as_ref().map(|entry| entry.decision.clone());
+
+        best
+    }
+}
+impl Component for UtilityStateComponent {
+    type Storage = VecStorage<Self>;
 }
 
 #[derive(Debug, Clone)]
 pub struct DecisionEntry {
-    decision: Arc<dyn NamedDecision<World>>,
+    decision: Arc<DefinedDecision>,
+    priority: u32,
     last_score: f32,
     last_tick: Duration,
 }
+impl DecisionEntry {
+    pub fn decision(&self) -> &Arc<DefinedDecision> { &self.decision }
+
+    pub fn priority(&self) -> u32 { self.priority }
+
+    pub fn last_score(&self) -> f32 { self.last_score }
+}
 impl Hash for DecisionEntry {
     fn hash<H: std::hash::Hasher>(&self, hasher: &mut H) { self.decision.name().hash(hasher) }
 }
@@ -28,3 +192,69 @@ impl PartialEq for DecisionEntry {
     fn eq(&self, other: &Self) -> bool { self.decision.name().eq(&other.decision.name()) }
 }
 impl Eq for DecisionEntry {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::defs::DefinitionStorage;
+
+    fn set_score(state: &mut UtilityStateComponent, name: &str, score: f32) {
+        state.available_decisions = state
+            .available_decisions
+            .drain()
+            .map(|mut entry| {
+                if entry.decision.name == name {
+                    entry.last_score = score;
+                }
+                entry
+            })
+            .collect();
+    }
+
+    fn chosen(state: &UtilityStateComponent) -> Option<&str> {
+        state.chosen().map(|decision| decision.name.as_str())
+    }
+
+    #[test]
+    fn resolve_commits_to_decisions() {
+        let storage =
+            DefinitionStorage::<BehaviorDefinition>::from_folder("../resources/defs/behaviors")
+                .unwrap();
+        let mut state = UtilityStateComponent::from_definition(storage.find("Cow Root").unwrap());
+        let secs = Duration::from_secs;
+
+        assert!(state.resolve(secs(0)).is_none());
+
+        set_score(&mut state, "Wander", 0.5);
+        assert_eq!(
+            state
+                .resolve(secs(0))
+                .map(|entry| entry.decision.name.clone()),
+            Some("Wander".to_string())
+        );
+        assert!(state.resolve(secs(1)).is_none());
+
+        // Momentum keeps the current decision against a close challenger
+        set_score(&mut state, "Rest", 0.55);
+        assert!(state.resolve(secs(2)).is_none());
+        assert_eq!(chosen(&state), Some("Wander"));
+
+        set_score(&mut state, "Rest", 0.7);
+        assert!(state.resolve(secs(3)).is_some());
+        assert_eq!(chosen(&state), Some("Rest"));
+
+        // Replaced decisions wait out their cooldown
+        set_score(&mut state, "Rest", 0.0);
+        state.resolve(secs(4));
+        assert_eq!(chosen(&state), Some("Wander"));
+        set_score(&mut state, "Rest", 0.9);
+        assert!(state.resolve(secs(20)).is_none());
+        state.resolve(secs(40));
+        assert_eq!(chosen(&state), Some("Rest"));
+
+        // Higher priority buckets win regardless of score
+        set_score(&mut state, "Graze", 0.1);
+        state.resolve(secs(41));
+        assert_eq!(chosen(&state), Some("Graze"));
+    }
+}
@@ -1,26 +1,38 @@
-use rayon::prelude::*;
-
-use super::CurrentGoalComponent;
+use super::{execution::PlanExecutionComponent, CurrentGoalComponent, UtilityStateComponent};
 use core::{
-    amethyst::core::{
-        legion::{system::PreparedWorld, *},
-        Time,
+    amethyst::{
+        core::{math::Point3, SystemDesc, Time},
+        derive::SystemDesc,
+        ecs::{
+            Component, Entities, Entity, Join, ParJoin, Read, ReadStorage, System, SystemData,
+            VecStorage, World, Write, WriteStorage,
+        },
     },
-    components::PropertiesComponent,
+    components::{ItemParentComponent, PropertiesComponent, TilePosition},
     defs::{
         action::{ActionConditionValue, ActionDefinition},
+        property::Property,
         DefinitionStorage,
     },
-    fsm,
+    fsm::{self, ConditionEquality, ConditionKind, ConditionTarget},
 };
 use derivative::Derivative;
+use rayon::prelude::*;
 use std::{
     collections::HashMap,
+    hash::{Hash, Hasher},
     sync::{Arc, Mutex},
 };
 
+/// Nearest candidate targets bound to each action, keeping the planner's branching factor bounded.
+const MAX_TARGETS: usize = 8;
+
 pub type ConditionCachePtr = Arc<Mutex<ConditionCache>>;
-pub type ConditionCache = HashMap<fsm::Condition<ActionConditionValue>, (u64, bool)>;
+pub type ConditionCache =
+    HashMap<(fsm::Condition<ActionConditionValue>, Option<Entity>), (u64, bool)>;
+
+/// A single step of a resolved plan: the action to perform and the entity it targets.
+pub type PlanStep = (u32, Option<Entity>);
 
 pub struct GoapPlannerComponent {
     condition_cache: ConditionCachePtr,
@@ -31,149 +43,658 @@ impl GoapPlannerComponent {
             condition_cache: Arc::new(Mutex::new(base_conditions)),
         }
     }
+    pub fn condition_cache(&self) -> ConditionCachePtr { self.condition_cache.clone() }
+}
+impl Component for GoapPlannerComponent {
+    type Storage = VecStorage<Self>;
+}
+
+/// Snapshot of the entities a plan can be resolved against, taken from the world once a frame so
+/// planning never touches component storage.
+#[derive(Default)]
+pub struct PlanningWorld {
+    entities: HashMap<Entity, (PropertiesComponent, Option<Point3<u32>>)>,
+    /// Items each entity is carrying, which are in `entities` without a position.
+    carried: HashMap<Entity, Vec<Entity>>,
+}
+impl PlanningWorld {
+    pub fn insert(
+        &mut self,
+        entity: Entity,
+        properties: PropertiesComponent,
+        position: Option<Point3<u32>>,
+    ) {
+        self.entities.insert(entity, (properties, position));
+    }
+
+    /// Adds an item carried by `holder`, which lends it its properties.
+    pub fn insert_carried(
+        &mut self,
+        item: Entity,
+        properties: PropertiesComponent,
+        holder: Entity,
+    ) {
+        self.insert(item, properties, None);
+        self.carried.entry(holder).or_default().push(item);
+    }
+
+    fn properties(&self, entity: Entity) -> Option<&PropertiesComponent> {
+        self.entities.get(&entity).map(|(properties, _)| properties)
+    }
+
+    fn position(&self, entity: Entity) -> Option<Point3<u32>> {
+        self.entities
+            .get(&entity)
+            .and_then(|(_, position)| *position)
+    }
+
+    fn near(&self, source: Entity, target: Entity, distance: u32) -> bool {
+        use pathfinding::prelude::absdiff;
+
+        if let (Some(a), Some(b)) = (self.position(source), self.position(target)) {
+            absdiff(a.x, b.x)
+                .max(absdiff(a.y, b.y))
+                .max(absdiff(a.z, b.z))
+                <= distance
+        } else {
+            false
+        }
+    }
+
+    /// Distance in tiles from `from` to the closest entity other than `source` which has
+    /// `property`.
+    pub fn nearest(&self, source: Entity, from: Point3<u32>, property: &Property) -> Option<u32> {
+        use pathfinding::prelude::absdiff;
+
+        self.entities
+            .iter()
+            .filter(|(entity, (properties, _))| {
+                **entity != source && properties.contains_value(property)
+            })
+            .filter_map(|(_, (_, position))| *position)
+            .map(|b| {
+                absdiff(from.x, b.x)
+                    .max(absdiff(from.y, b.y))
+                    .max(absdiff(from.z, b.z))
+            })
+            .min()
+    }
+
+    /// Resolves a condition purely against the world, ignoring anything simulated by a plan.
+    fn check(
+        &self,
+        source: Entity,
+        condition: &fsm::Condition<ActionConditionValue>,
+        target: Option<Entity>,
+    ) -> bool {
+        let result = self.resolve(source, condition, target);
+        match condition.equality {
+            ConditionEquality::Is => result,
+            ConditionEquality::Not => !result,
+        }
+    }
+
+    fn resolve(
+        &self,
+        source: Entity,
+        condition: &fsm::Condition<ActionConditionValue>,
+        target: Option<Entity>,
+    ) -> bool {
+        let subject = match condition.target {
+            ConditionTarget::Me => Some(source),
+            ConditionTarget::Entity => target,
+        };
+
+        match (&condition.kind, &condition.value, subject, target) {
+            (ConditionKind::Has, ActionConditionValue::Property(property), Some(subject), _) => {
+                let has = |entity| {
+                    self.properties(entity)
+                        .map_or(false, |properties| properties.contains_value(property))
+                };
+                has(subject)
+                    || self
+                        .carried
+                        .get(&subject)
+                        .map_or(false, |items| items.iter().any(|item| has(*item)))
+            }
+            (ConditionKind::Near(distance), _, Some(subject), Some(target)) => {
+                let other = if subject == target { source } else { target };
+                self.near(subject, other, *distance)
+            }
+            _ => false,
+        }
+    }
+
+    /// The entities an action can be performed on, nearest first. Actions which never refer to a
+    /// target are bound once, without one.
+    fn bind(&self, source: Entity, action: &ActionDefinition) -> Vec<Option<Entity>> {
+        use pathfinding::prelude::absdiff;
+
+        let targeted = !action.targets.is_empty()
+            || action
+                .conditions
+                .iter()
+                .chain(action.post_conditions.iter().map(|(c, _)| c))
+                .any(|c| c.value == ActionConditionValue::Target);
+        if !targeted {
+            return vec![None];
+        }
+
+        let origin = self.position(source);
+        let mut candidates = self
+            .entities
+            .iter()
+            .filter(|(entity, (_, position))| **entity != source && position.is_some())
+            .filter(|(entity, _)| {
+                action
+                    .targets
+                    .iter()
+                    .all(|c| self.check(source, c, Some(**entity)))
+            })
+            .map(|(entity, (_, position))| {
+                let distance = match (origin, position) {
+                    (Some(a), Some(b)) => absdiff(a.x, b.x) + absdiff(a.y, b.y) + absdiff(a.z, b.z),
+                    _ => std::u32::MAX,
+                };
+                (*entity, distance)
+            })
+            .collect::<Vec<_>>();
+
+        candidates.sort_by_key(|(_, distance)| *distance);
+        candidates
+            .into_iter()
+            .take(MAX_TARGETS)
+            .map(|(entity, _)| Some(entity))
+            .collect()
+    }
+}
+
+/// A condition as it holds after simulating some actions, bound to the entity it refers to.
+/// Stored normalized to `ConditionEquality::Is`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Fact {
+    condition: fsm::Condition<ActionConditionValue>,
+    target: Option<Entity>,
+    value: bool,
+}
+
+fn normalize(
+    condition: &fsm::Condition<ActionConditionValue>,
+    value: bool,
+) -> (fsm::Condition<ActionConditionValue>, bool) {
+    let mut condition = condition.clone();
+    let value = match condition.equality {
+        ConditionEquality::Is => value,
+        ConditionEquality::Not => !value,
+    };
+    condition.equality = ConditionEquality::Is;
+    (condition, value)
+}
+
+/// A condition of an action, bound to the target that action was planned against.
+#[derive(Debug, Clone, Copy)]
+struct BoundCondition<'a> {
+    condition: &'a fsm::Condition<ActionConditionValue>,
+    target: Option<Entity>,
+}
+impl<'a, 'state> goap::Condition<GoapState<'state>> for BoundCondition<'a> {
+    fn unique_id(&self) -> u64 { calculate_hash(&(self.condition, self.target)) }
+
+    fn check(&self, state: &GoapState<'state>) -> bool { state.check(self.condition, self.target) }
+}
+
+/// The goal of a plan, satisfied once the goal action itself has been performed.
+#[derive(Debug)]
+struct GoalCondition(u32);
+impl<'state> goap::Condition<GoapState<'state>> for GoalCondition {
+    fn unique_id(&self) -> u64 { calculate_hash(&("goal", self.0)) }
+
+    fn check(&self, state: &GoapState<'state>) -> bool { state.performed == Some(self.0) }
 }
 
 #[derive(Derivative)]
 #[derivative(Debug(bound = ""))]
-struct GoapAction<'a> {
+struct GoapAction<'a, 'state> {
     action: &'a ActionDefinition,
-    conditions: Vec<&'a fsm::Condition<ActionConditionValue>>,
-    conditions_map: Vec<&'a dyn goap::Condition<GoapState<'a>>>,
+    target: Option<Entity>,
+    conditions_map: Vec<&'a dyn goap::Condition<GoapState<'state>>>,
 }
-impl<'a> GoapAction<'a> {
-    pub fn new(action: &'a ActionDefinition) -> Self {
-        let conditions_map = action
-            .conditions
+impl<'a, 'state> GoapAction<'a, 'state> {
+    pub fn new(
+        action: &'a ActionDefinition,
+        target: Option<Entity>,
+        conditions: &'a [BoundCondition<'a>],
+    ) -> Self {
+        let conditions_map = conditions
             .iter()
             .map(|c| c as &dyn goap::Condition<GoapState>)
             .collect();
         Self {
             action,
+            target,
             conditions_map,
-            conditions: action.conditions.iter().collect(),
         }
     }
 }
-impl<'a, 'state> goap::Action<GoapState<'a>> for GoapAction<'a> {
-    fn unique_id(&self) -> u64 { u64::from(self.action.id.unwrap()) }
+impl<'a, 'state> goap::Action<GoapState<'state>> for GoapAction<'a, 'state> {
+    fn unique_id(&self) -> u64 { calculate_hash(&(self.action.id.unwrap(), self.target)) }
 
-    fn conditions(&self) -> &[&dyn goap::Condition<GoapState<'a>>] {
+    fn conditions(&self) -> &[&dyn goap::Condition<GoapState<'state>>] {
         self.conditions_map.as_slice()
     }
 
-    fn apply(&self, _: &mut GoapState<'a>) {}
+    fn apply(&self, state: &mut GoapState<'state>) { state.apply(self.action, self.target); }
+
+    /// Actions cost their base time in milliseconds, and at least one so shorter plans win ties.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn cost(&self) -> u32 { ((self.action.base_time * 1000.0) as u32).max(1) }
 }
 
-fn check_condition(condition: &fsm::Condition<ActionConditionValue>, state: &GoapState) -> bool {
-    use core::fsm::{ConditionKind, ConditionTarget};
-    log::trace!("Checking condition: {:?}", condition);
+#[derive(Derivative, Clone)]
+#[derivative(Debug(bound = ""))]
+pub struct GoapState<'a> {
+    current_frame: u64,
+    source_entity: Entity,
+    condition_cache: ConditionCachePtr,
+    facts: Vec<Fact>,
+    location: Option<Entity>,
+    performed: Option<u32>,
 
-    let result = match condition.target {
-        ConditionTarget::Me => match condition.kind {
-            ConditionKind::Has => true,
-            ConditionKind::Near(_distance) => unimplemented!(),
-        },
-        ConditionTarget::Entity => {
-            // Search to see if an entity matches
-            false
+    #[derivative(Debug = "ignore")]
+    world: &'a PlanningWorld,
+}
+impl<'a> GoapState<'a> {
+    pub fn new(
+        source_entity: Entity,
+        current_frame: u64,
+        condition_cache: ConditionCachePtr,
+        world: &'a PlanningWorld,
+    ) -> Self {
+        Self {
+            current_frame,
+            source_entity,
+            condition_cache,
+            facts: Vec::new(),
+            location: None,
+            performed: None,
+            world,
         }
-    };
+    }
+
+    fn apply(&mut self, action: &ActionDefinition, target: Option<Entity>) {
+        for (condition, value) in &action.post_conditions {
+            self.set(condition, target, *value);
+        }
+        self.performed = action.id;
+    }
 
-    log::trace!("returning: {}", result);
+    fn set(
+        &mut self,
+        condition: &fsm::Condition<ActionConditionValue>,
+        target: Option<Entity>,
+        value: bool,
+    ) {
+        let (condition, value) = normalize(condition, value);
+
+        // Moving next to something takes the source away from wherever it was before
+        if let (ConditionKind::Near(_), true) = (condition.kind, value) {
+            self.location = target;
+            return;
+        }
 
-    if let Ok(mut condition_cache) = state.condition_cache.lock() {
-        condition_cache.insert(condition.clone(), (state.current_frame, result));
+        let fact = Fact {
+            condition,
+            target,
+            value,
+        };
+
+        if let Some(existing) = self
+            .facts
+            .iter_mut()
+            .find(|f| f.condition == fact.condition && f.target == fact.target)
+        {
+            *existing = fact;
+        } else {
+            self.facts.push(fact);
+            // Keep facts in a canonical order so equal states hash equally
+            self.facts.sort_by_key(calculate_hash);
+        }
     }
 
-    result
-}
+    fn check(
+        &self,
+        condition: &fsm::Condition<ActionConditionValue>,
+        target: Option<Entity>,
+    ) -> bool {
+        log::trace!("Checking condition: {:?} @ {:?}", condition, target);
+
+        let (normalized, _) = normalize(condition, true);
+        let fact = self
+            .facts
+            .iter()
+            .find(|f| f.condition == normalized && f.target == target);
+
+        let result = match (normalized.kind, self.location, fact) {
+            (ConditionKind::Near(_), Some(location), _) => target == Some(location),
+            (_, _, Some(fact)) => fact.value,
+            _ => self.held(&normalized) || self.check_world(&normalized, target),
+        };
+
+        let result = match condition.equality {
+            ConditionEquality::Is => result,
+            ConditionEquality::Not => !result,
+        };
+
+        log::trace!("returning: {}", result);
 
-impl<'a> goap::Condition<GoapState<'a>> for fsm::Condition<ActionConditionValue> {
-    fn unique_id(&self) -> u64 { calculate_hash(self) }
-    fn check(&self, state: &GoapState<'a>) -> bool {
-        if let Some(result) = state.condition_cache.lock().unwrap().get(self) {
-            if result.0 == state.current_frame {
-                log::trace!("{:?} - Returning cached result: {}", self, result.1);
+        result
+    }
+
+    /// Anything the source has picked up during the plan lends it its properties.
+    fn held(&self, condition: &fsm::Condition<ActionConditionValue>) -> bool {
+        let property = match (&condition.target, &condition.kind, &condition.value) {
+            (ConditionTarget::Me, ConditionKind::Has, ActionConditionValue::Property(property)) => {
+                property
+            }
+            _ => return false,
+        };
+
+        self.facts
+            .iter()
+            .filter(|f| {
+                f.value
+                    && f.condition.target == ConditionTarget::Me
+                    && f.condition.kind == ConditionKind::Has
+                    && (f.condition.value == ActionConditionValue::Target
+                        || f.condition.value == ActionConditionValue::Item)
+            })
+            .filter_map(|f| f.target)
+            .any(|entity| {
+                self.world
+                    .properties(entity)
+                    .map_or(false, |properties| properties.contains_value(property))
+            })
+    }
+
+    /// World lookups are cached per frame, and only ever made for normalized conditions.
+    fn check_world(
+        &self,
+        condition: &fsm::Condition<ActionConditionValue>,
+        target: Option<Entity>,
+    ) -> bool {
+        let key = (condition.clone(), target);
+
+        if let Some(result) = self.condition_cache.lock().unwrap().get(&key) {
+            if result.0 == self.current_frame {
+                log::trace!("{:?} - Returning cached result: {}", key, result.1);
                 return result.1;
             }
         }
 
-        check_condition(self, state)
+        let result = self.world.check(self.source_entity, &key.0, target);
+
+        if let Ok(mut condition_cache) = self.condition_cache.lock() {
+            condition_cache.insert(key, (self.current_frame, result));
+        }
+
+        result
+    }
+}
+impl<'a> Hash for GoapState<'a> {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.source_entity.hash(hasher);
+        self.facts.hash(hasher);
+        self.location.hash(hasher);
+        self.performed.hash(hasher);
+    }
+}
+impl<'a> PartialEq for GoapState<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.source_entity == other.source_entity
+            && self.facts == other.facts
+            && self.location == other.location
+            && self.performed == other.performed
     }
 }
+impl<'a> Eq for GoapState<'a> {}
+
+/// Plans the steps needed for the state's source entity to perform the goal action, binding each
+/// action to the targets available in the world.
+pub fn plan(
+    state: &GoapState,
+    action_defs: &DefinitionStorage<ActionDefinition>,
+    goal: u32,
+) -> Result<Vec<PlanStep>, goap::PlanFailure> {
+    let bindings = action_defs
+        .iter()
+        .flat_map(|action| {
+            state
+                .world
+                .bind(state.source_entity, action)
+                .into_iter()
+                .map(move |target| (action, target))
+        })
+        .collect::<Vec<_>>();
+
+    let conditions = bindings
+        .iter()
+        .map(|(action, target)| {
+            action
+                .conditions
+                .iter()
+                .chain(action.targets.iter())
+                .map(|condition| BoundCondition {
+                    condition,
+                    target: *target,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let actions = bindings
+        .iter()
+        .zip(conditions.iter())
+        .map(|((action, target), conditions)| GoapAction::new(action, *target, conditions))
+        .collect::<Vec<_>>();
+    let action_refs = actions
+        .iter()
+        .map(|a| a as &dyn goap::Action<GoapState>)
+        .collect::<Vec<_>>();
+
+    let steps = actions
+        .iter()
+        .map(|a| {
+            (
+                goap::Action::<GoapState>::unique_id(a),
+                (a.action.id.unwrap(), a.target),
+            )
+        })
+        .collect::<HashMap<_, _>>();
 
-#[derive(Derivative, Clone)]
-#[derivative(Debug(bound = ""))]
-pub struct GoapState<'a> {
-    current_frame: u64,
-    source_entity: Entity,
-    condition_cache: ConditionCachePtr,
+    let goal = GoalCondition(goal);
+    let goal_refs = [&goal as &dyn goap::Condition<GoapState>];
 
-    #[derivative(Debug = "ignore")]
-    storage: &'a PreparedWorld,
+    goap::Planner::plan(state, &goal_refs, &action_refs)
+        .map(|plan| plan.iter().map(|a| steps[&a.unique_id()]).collect())
 }
 
-#[derive(Default)]
-pub struct GoapPlannerSystemDesc {}
-impl SystemDesc for GoapPlannerSystemDesc {
-    fn build(mut self, world: &mut World) -> Box<dyn Schedulable> {
-        let frame = world.resources.get::<Time>().unwrap().frame_number();
-
-        let action_defs = world
-            .resources
-            .get::<DefinitionStorage<ActionDefinition>>()
-            .unwrap();
-        let mut condition_cache = HashMap::with_capacity(action_defs.len());
-        for action in action_defs.iter() {
-            for condition in &action.conditions {
-                condition_cache.insert(condition.clone(), (frame, false));
+/// Whether `step` can still be performed, given the world and the effects of the steps of its plan
+/// which have already completed.
+pub fn step_valid(
+    state: &GoapState,
+    action_defs: &DefinitionStorage<ActionDefinition>,
+    completed: &[PlanStep],
+    step: PlanStep,
+) -> bool {
+    let mut state = state.clone();
+    for (action, target) in completed {
+        if let Some(action) = action_defs.get(*action) {
+            state.apply(action, *target);
+        }
+    }
+
+    let (action, target) = step;
+    action_defs.get(action).map_or(false, |action| {
+        action
+            .conditions
+            .iter()
+            .chain(action.targets.iter())
+            .all(|condition| state.check(condition, target))
+    })
+}
+
+/// Takes the frame's `PlanningWorld`, shared by the utility, planner and execution systems.
+/// Entities are snapshotted where they stand, and carried items along with whoever carries them.
+/// Nothing is copied while no entity is deciding or planning.
+#[derive(Default, SystemDesc)]
+pub struct PlanningWorldSystem;
+impl<'s> System<'s> for PlanningWorldSystem {
+    type SystemData = (
+        Entities<'s>,
+        Write<'s, Arc<PlanningWorld>>,
+        ReadStorage<'s, PropertiesComponent>,
+        ReadStorage<'s, TilePosition>,
+        ReadStorage<'s, ItemParentComponent>,
+        ReadStorage<'s, UtilityStateComponent>,
+        ReadStorage<'s, PlanExecutionComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut planning_world,
+            properties_storage,
+            position_storage,
+            parent_storage,
+            utility_storage,
+            execution_storage,
+        ): Self::SystemData,
+    ) {
+        if (&utility_storage).join().next().is_none()
+            && (&execution_storage).join().next().is_none()
+        {
+            if !planning_world.entities.is_empty() {
+                *planning_world = Arc::default();
             }
+            return;
         }
-        world.resources.insert(condition_cache);
 
-        SystemBuilder::<()>::new("GoapPlannerSystem")
-            .read_resource::<Time>()
-            .read_resource::<DefinitionStorage<ActionDefinition>>()
-            .read_component::<PropertiesComponent>()
-            .with_query(<(Read<CurrentGoalComponent>, Write<GoapPlannerComponent>)>::query())
-            .build(move |_, world, (time, action_defs), query| {
-                let actions = action_defs
-                    .raw_storage()
-                    .iter()
-                    .map(|action| GoapAction::new(action))
-                    .collect::<Vec<_>>();
-                let action_refs = actions
-                    .iter()
-                    .map(|a| a as &dyn goap::Action<GoapState>)
-                    .collect::<Vec<_>>();
-
-                log::trace!("GoapPlannerSystem::run");
-                query.par_entities_for_each(|(source_entity, (mut goal_data, planner_data))| {
-                    log::trace!("GoapPlannerSystem::iter");
-                    let state = GoapState {
-                        source_entity,
-                        current_frame: time.frame_number(),
-                        condition_cache: planner_data.condition_cache.clone(),
-                        storage: world,
-                    };
-
-                    let goal_action = action_defs.get(goal_data.action_id).unwrap();
-                    let goal_conditions_refs = goal_action
-                        .conditions
-                        .iter()
-                        .map(|a| a as &dyn goap::Condition<GoapState>)
-                        .collect::<Vec<_>>();
-
-                    let plan = goap::Planner::plan(&state, &goal_conditions_refs, &action_refs);
-
-                    log::trace!("Resolved plan: {:?}", plan);
-                });
-            })
+        let mut snapshot = PlanningWorld::default();
+        for (entity, properties, position, parent) in (
+            &entities,
+            &properties_storage,
+            position_storage.maybe(),
+            parent_storage.maybe(),
+        )
+            .join()
+        {
+            match (position, parent) {
+                (_, Some(parent)) => {
+                    snapshot.insert_carried(entity, properties.clone(), parent.parent)
+                }
+                (Some(position), None) => {
+                    snapshot.insert(entity, properties.clone(), Some(position.0))
+                }
+                (None, None) => {}
+            }
+        }
+        *planning_world = Arc::new(snapshot);
+    }
+}
+
+/// Resolves a plan for every entity with a `CurrentGoalComponent` whose `PlanExecutionComponent`
+/// needs one.
+pub struct GoapPlannerSystem;
+impl<'s> System<'s> for GoapPlannerSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, Time>,
+        Read<'s, DefinitionStorage<ActionDefinition>>,
+        Read<'s, Arc<PlanningWorld>>,
+        ReadStorage<'s, CurrentGoalComponent>,
+        ReadStorage<'s, GoapPlannerComponent>,
+        WriteStorage<'s, PlanExecutionComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            time,
+            action_defs,
+            planning_world,
+            goal_storage,
+            planner_storage,
+            mut execution_storage,
+        ): Self::SystemData,
+    ) {
+        if !(&goal_storage, &execution_storage)
+            .join()
+            .any(|(_, execution)| execution.needs_plan())
+        {
+            return;
+        }
+        let planning_world: &PlanningWorld = &planning_world;
+
+        log::trace!("GoapPlannerSystem::run");
+        (
+            &entities,
+            &goal_storage,
+            &planner_storage,
+            &mut execution_storage,
+        )
+            .par_join()
+            .for_each(|(source_entity, goal_data, planner_data, execution)| {
+                if !execution.needs_plan() {
+                    return;
+                }
+
+                log::trace!("GoapPlannerSystem::iter");
+                let state = GoapState::new(
+                    source_entity,
+                    time.frame_number(),
+                    planner_data.condition_cache.clone(),
+                    planning_world,
+                );
+
+                let plan = plan(&state, &action_defs, goal_data.action_id);
+
+                log::trace!("Resolved plan: {:?}", plan);
+
+                match plan {
+                    Ok(steps) => execution.set_plan(steps),
+                    Err(failure) => execution.fail(failure),
+                }
+            });
+    }
+}
+
+/// Seeds the `ConditionCache` resource with every action's conditions, unresolved.
+#[derive(Default)]
+pub struct GoapPlannerSystemDesc;
+impl<'a, 'b> SystemDesc<'a, 'b, GoapPlannerSystem> for GoapPlannerSystemDesc {
+    fn build(self, world: &mut World) -> GoapPlannerSystem {
+        <GoapPlannerSystem as System<'_>>::SystemData::setup(world);
+
+        let frame = world.fetch::<Time>().frame_number();
+        let condition_cache = {
+            let action_defs = world.fetch::<DefinitionStorage<ActionDefinition>>();
+            let mut condition_cache = HashMap::with_capacity(action_defs.len());
+            for action in action_defs.iter() {
+                for condition in &action.conditions {
+                    condition_cache.insert((normalize(condition, true).0, None), (frame, false));
+                }
+            }
+            condition_cache
+        };
+        world.insert(condition_cache);
+
+        GoapPlannerSystem
     }
 }
 
 fn calculate_hash<T: std::hash::Hash>(t: &T) -> u64 {
-    use std::hash::Hasher;
     let mut s = std::collections::hash_map::DefaultHasher::new();
     t.hash(&mut s);
     s.finish()
@@ -183,57 +704,150 @@ fn calculate_hash<T: std::hash::Hash>(t: &T) -> u64 {
 mod tests {
     use super::*;
     use core::{
-        amethyst::core::legion::{Universe, World},
-        defs::Named,
+        amethyst::ecs::{Builder, RunNow, WorldExt},
+        defs::{
+            foliage::FoliageCategory,
+            property::{MovementFlags, Property},
+            Named,
+        },
     };
 
-    fn add_test_goap_stuff(world: &mut World) {
-        let action_id = world
-            .resources
-            .get::<DefinitionStorage<ActionDefinition>>()
-            .unwrap()
-            .find("Fell Tree")
-            .unwrap()
-            .id()
-            .unwrap();
+    fn action_defs() -> DefinitionStorage<ActionDefinition> {
+        DefinitionStorage::<ActionDefinition>::from_folder("../resources/defs/actions").unwrap()
+    }
 
-        let cache = world.resources.get::<ConditionCache>().unwrap().clone();
+    fn action_id(defs: &DefinitionStorage<ActionDefinition>, name: &str) -> u32 {
+        defs.find(name).unwrap().id().unwrap()
+    }
 
-        world.insert(
-            (),
-            vec![(
-                CurrentGoalComponent { action_id },
-                GoapPlannerComponent::new(cache),
-            )],
+    fn add_test_goap_stuff(world: &mut World) -> Entity {
+        let action_id = action_id(
+            &world.fetch::<DefinitionStorage<ActionDefinition>>(),
+            "Fell Tree",
         );
 
-        world
-            .resources
-            .get_mut::<Time>()
-            .unwrap()
-            .increment_frame_number();
+        let cache = world.fetch::<ConditionCache>().clone();
+
+        let entity = world
+            .create_entity()
+            .with(CurrentGoalComponent { action_id })
+            .with(GoapPlannerComponent::new(cache))
+            .with(PlanExecutionComponent::default())
+            .build();
+
+        world.fetch_mut::<Time>().increment_frame_number();
+
+        entity
     }
 
     #[test]
     fn simple_planner_test() {
         let _ = env_logger::builder().is_test(true).try_init();
 
-        let universe = Universe::new();
-        let mut world = universe.create_world();
+        let mut world = World::new();
 
-        world.resources.insert(Time::default());
+        world.insert(Time::default());
 
-        world.resources.insert(
-            DefinitionStorage::<ActionDefinition>::from_folder(
-                "/home/jaynus/dev/survival/resources/defs/actions",
-            )
-            .unwrap(),
-        );
+        world.insert(action_defs());
 
         let mut system = GoapPlannerSystemDesc::default().build(&mut world);
 
-        add_test_goap_stuff(&mut world);
+        let entity = add_test_goap_stuff(&mut world);
+
+        system.run_now(&world);
+
+        // Nothing in the world has an axe or a tree, so planning fails and is retried
+        let executions = world.read_storage::<PlanExecutionComponent>();
+        let execution = executions.get(entity).unwrap();
+        assert!(execution.needs_plan());
+        assert!(execution.last_failure().is_some());
+    }
+
+    #[test]
+    fn plan_fell_tree() {
+        let _ = env_logger::builder().is_test(true).try_init();
 
-        system.run(&world);
+        let mut specs = World::new();
+        let (pawn, axe, tree) = (
+            specs.create_entity().build(),
+            specs.create_entity().build(),
+            specs.create_entity().build(),
+        );
+
+        let mut world = PlanningWorld::default();
+        world.insert(
+            pawn,
+            PropertiesComponent::from_iter_ref([Property::Movement(MovementFlags::Walk)].iter()),
+            Some(Point3::new(0, 0, 0)),
+        );
+        world.insert(
+            axe,
+            PropertiesComponent::from_iter_ref([Property::CanPickup, Property::Chopping(1)].iter()),
+            Some(Point3::new(5, 0, 0)),
+        );
+        world.insert(
+            tree,
+            PropertiesComponent::from_iter_ref([Property::Foliage(FoliageCategory::Tree)].iter()),
+            Some(Point3::new(0, 5, 0)),
+        );
+
+        let defs = action_defs();
+        let state = GoapState::new(pawn, 0, Arc::new(Mutex::new(HashMap::new())), &world);
+        let plan = plan(&state, &defs, action_id(&defs, "Fell Tree")).unwrap();
+
+        assert_eq!(
+            plan,
+            vec![
+                (action_id(&defs, "Move To"), Some(axe)),
+                (action_id(&defs, "Pickup"), Some(axe)),
+                (action_id(&defs, "Move To"), Some(tree)),
+                (action_id(&defs, "Fell Tree"), Some(tree)),
+            ]
+        );
+
+        // Each step stays valid given the steps before it, but skipping the axe does not work
+        for i in 0..plan.len() {
+            assert!(step_valid(&state, &defs, &plan[..i], plan[i]));
+        }
+        assert!(!step_valid(&state, &defs, &plan[2..3], plan[3]));
+
+        // Once the axe is carried, the pawn only has to go to the tree
+        let mut carrying = PlanningWorld::default();
+        carrying.insert(
+            pawn,
+            PropertiesComponent::from_iter_ref([Property::Movement(MovementFlags::Walk)].iter()),
+            Some(Point3::new(5, 0, 0)),
+        );
+        carrying.insert_carried(
+            axe,
+            PropertiesComponent::from_iter_ref([Property::CanPickup, Property::Chopping(1)].iter()),
+            pawn,
+        );
+        carrying.insert(
+            tree,
+            PropertiesComponent::from_iter_ref([Property::Foliage(FoliageCategory::Tree)].iter()),
+            Some(Point3::new(0, 5, 0)),
+        );
+        let state = GoapState::new(pawn, 0, Arc::new(Mutex::new(HashMap::new())), &carrying);
+        assert_eq!(
+            plan(&state, &defs, action_id(&defs, "Fell Tree")).unwrap(),
+            vec![
+                (action_id(&defs, "Move To"), Some(tree)),
+                (action_id(&defs, "Fell Tree"), Some(tree)),
+            ]
+        );
+
+        // Without anything to chop with, there is no plan
+        let mut world = PlanningWorld::default();
+        world.insert(
+            pawn,
+            PropertiesComponent::from_iter_ref([Property::Movement(MovementFlags::Walk)].iter()),
+            Some(Point3::new(0, 0, 0)),
+        );
+        let state = GoapState::new(pawn, 0, Arc::new(Mutex::new(HashMap::new())), &world);
+        assert_eq!(
+            plan(&state, &defs, action_id(&defs, "Fell Tree")).unwrap_err(),
+            goap::PlanFailure::NoPlan
+        );
     }
 }
@@ -1,51 +1,321 @@
-use super::*;
-use core::amethyst::core::{legion::*, Time};
-use crossbeam::queue::SegQueue;
+use super::{execution::PlanExecutionComponent, planner::PlanningWorld, *};
+use crate::components::CurrentActionComponent;
+use core::{
+    amethyst::{
+        core::{SystemDesc, Time},
+        derive::SystemDesc,
+        ecs::{
+            Entities, ParJoin, Read, ReadStorage, System, SystemData, World, Write, WriteStorage,
+        },
+        shrev::{EventChannel, ReaderId},
+    },
+    components::{DigestionComponent, IdleComponent, PropertiesComponent, TilePosition},
+    defs::{action::ActionDefinition, DefinitionStorage},
+};
+use psyche::components::{MoodComponent, PyscheNeedsComponent};
 use rayon::prelude::*;
+use std::sync::Arc;
 
-pub struct UtilitySystem<R> {
-    runner: R,
-}
-impl<R> UtilitySystem<R>
-where
-    R: FnMut(&mut World),
-{
-    fn with(runner: R) -> Self { Self { runner } }
-}
-impl<R> ThreadLocal for UtilitySystem<R>
-where
-    R: FnMut(&mut World),
-{
-    fn run(&mut self, world: &mut World) { (self.runner)(world) }
-    fn dispose(self, world: &mut World) {}
-}
-pub struct UtilitySystemDesc;
-impl UtilitySystemDesc {
-    fn build(mut self, world: &mut World) -> Box<dyn ThreadLocal> {
-        let mut query = <(Write<UtilityStateComponent>)>::query();
+/// Scores every decision available to each entity, from its `UtilityStateComponent`. Entities are
+/// scored against their own needs, digestion, mood, position and idle time, and the frame's
+/// `PlanningWorld` snapshot of their surroundings. Each entity then commits to its best
+/// decision, and a `DecisionChosenEvent` is written whenever that changes.
+#[derive(Default, SystemDesc)]
+pub struct UtilitySystem;
+impl<'s> System<'s> for UtilitySystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, Time>,
+        Write<'s, EventChannel<DecisionChosenEvent>>,
+        Read<'s, Arc<PlanningWorld>>,
+        WriteStorage<'s, UtilityStateComponent>,
+        ReadStorage<'s, PropertiesComponent>,
+        ReadStorage<'s, PyscheNeedsComponent>,
+        ReadStorage<'s, DigestionComponent>,
+        ReadStorage<'s, MoodComponent>,
+        ReadStorage<'s, TilePosition>,
+        ReadStorage<'s, IdleComponent>,
+    );
 
-        let runner = move |world: &mut World| {
-            let mut updated = {
-                let time = world.resources.get::<Time>().unwrap();
+    fn run(
+        &mut self,
+        (
+            entities,
+            time,
+            mut chosen_channel,
+            surroundings,
+            mut utility_storage,
+            properties_storage,
+            needs_storage,
+            digestion_storage,
+            mood_storage,
+            position_storage,
+            idle_storage,
+        ): Self::SystemData,
+    ) {
+        let timestamp = time.absolute_time();
 
-                let timestamp = time.absolute_time();
+        let chosen = (
+            &entities,
+            &mut utility_storage,
+            properties_storage.maybe(),
+            needs_storage.maybe(),
+            digestion_storage.maybe(),
+            mood_storage.maybe(),
+            position_storage.maybe(),
+            idle_storage.maybe(),
+        )
+            .par_join()
+            .filter_map(
+                |(entity, utility, properties, needs, digestion, mood, position, idle)| {
+                    let context = ConsiderationContext::new(entity, properties.cloned())
+                        .with_needs(needs.map(|needs| needs.0.clone()))
+                        .with_digestion(digestion.cloned())
+                        .with_mood(mood.cloned())
+                        .with_position(position.map(|position| position.0))
+                        .with_idle(idle.map(|idle| idle.duration_since(timestamp)))
+                        .with_world(surroundings.clone());
 
-                query.par_for_each(world, |mut utility| {
                     utility.available_decisions = utility
                         .available_decisions
                         .par_iter()
                         .map(|entry| {
                             let mut entry = entry.clone();
                             entry.last_tick = timestamp;
-                            entry.last_score = entry.decision.score(world);
+                            entry.last_score = entry.decision.score(&context);
 
                             entry
                         })
                         .collect();
+
+                    utility.resolve(timestamp).map(|entry| {
+                        log::trace!("{:?} chose {:?}", entity, entry.decision().name());
+                        DecisionChosenEvent {
+                            entity,
+                            decision: entry.decision().clone(),
+                            priority: entry.priority(),
+                            score: entry.last_score(),
+                        }
+                    })
+                },
+            )
+            .collect::<Vec<_>>();
+
+        chosen_channel.iter_write(chosen);
+    }
+}
+
+/// Makes the action of each newly chosen decision the entity's goal, dropping whatever plan was
+/// running for the last one. Decisions without an action leave the entity without a goal.
+pub struct DecisionGoalSystem {
+    reader: ReaderId<DecisionChosenEvent>,
+}
+impl<'s> System<'s> for DecisionGoalSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<DecisionChosenEvent>>,
+        Read<'s, DefinitionStorage<ActionDefinition>>,
+        WriteStorage<'s, CurrentGoalComponent>,
+        WriteStorage<'s, PlanExecutionComponent>,
+        WriteStorage<'s, CurrentActionComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            chosen_channel,
+            action_defs,
+            mut goal_storage,
+            mut execution_storage,
+            mut action_storage,
+        ): Self::SystemData,
+    ) {
+        for event in chosen_channel.read(&mut self.reader) {
+            let entity = event.entity;
+            if !entities.is_alive(entity) {
+                continue;
+            }
+
+            // Only steps of the old plan are cancelled, not actions given some other way
+            if execution_storage.remove(entity).is_some() {
+                action_storage.remove(entity);
+            }
+
+            match event
+                .decision
+                .action()
+                .and_then(|action| action_defs.get_id(action))
+            {
+                Some(action_id) => {
+                    log::trace!("{:?} now has goal {}", entity, action_id);
+                    goal_storage
+                        .insert(entity, CurrentGoalComponent::new(action_id))
+                        .unwrap();
+                    execution_storage
+                        .insert(entity, PlanExecutionComponent::default())
+                        .unwrap();
+                }
+                None => {
+                    goal_storage.remove(entity);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DecisionGoalSystemDesc;
+impl<'a, 'b> SystemDesc<'a, 'b, DecisionGoalSystem> for DecisionGoalSystemDesc {
+    fn build(self, world: &mut World) -> DecisionGoalSystem {
+        <DecisionGoalSystem as System<'_>>::SystemData::setup(world);
+
+        let reader = world
+            .fetch_mut::<EventChannel<DecisionChosenEvent>>()
+            .register_reader();
+
+        DecisionGoalSystem { reader }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behavior::planner::PlanningWorldSystemDesc;
+    use core::{
+        amethyst::{
+            core::math::Point3,
+            ecs::{Builder, RunNow, WorldExt},
+        },
+        defs::{foliage::FoliageCategory, property::Property},
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn scores_each_pawn_from_its_own_state() -> Result<(), core::failure::Error> {
+        let def: BehaviorDefinition = core::ron::de::from_str(
+            r#"(
+                name: "Test",
+                buckets: [(
+                    name: "Idle",
+                    priority: 0,
+                    decisions: [
+                        (
+                            name: "Browse",
+                            considerations: [(
+                                name: "Nearby brush",
+                                input: Distance(Foliage(Brush)),
+                                curve: Linear(range: (0.0, 10.0), slope: -1.0, intercept: 1.0),
+                            )],
+                        ),
+                        (
+                            name: "Loaf",
+                            base: 0.5,
+                            considerations: [(
+                                name: "Always",
+                                input: Constant(1.0),
+                                curve: Linear(range: (0.0, 1.0), slope: 1.0, intercept: 0.0),
+                            )],
+                        ),
+                    ],
+                )],
+            )"#,
+        )?;
+
+        let mut world = World::new();
+        let mut snapshot = PlanningWorldSystemDesc::default().build(&mut world);
+        let mut system = UtilitySystemDesc::default().build(&mut world);
+        let mut reader = world
+            .fetch_mut::<EventChannel<DecisionChosenEvent>>()
+            .register_reader();
+
+        world
+            .create_entity()
+            .with(PropertiesComponent::from_iter_ref(
+                [Property::Foliage(FoliageCategory::Brush)].iter(),
+            ))
+            .with(TilePosition(Point3::new(1, 0, 0)))
+            .build();
+        let near = world
+            .create_entity()
+            .with(UtilityStateComponent::from_definition(&def))
+            .with(TilePosition(Point3::new(0, 0, 0)))
+            .build();
+        let lost = world
+            .create_entity()
+            .with(UtilityStateComponent::from_definition(&def))
+            .build();
+
+        snapshot.run_now(&world);
+        system.run_now(&world);
+
+        let chosen = world
+            .fetch::<EventChannel<DecisionChosenEvent>>()
+            .read(&mut reader)
+            .map(|event| (event.entity, event.decision.name().unwrap().to_string()))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(chosen[&near], "Browse");
+        assert_eq!(chosen[&lost], "Loaf");
+
+        Ok(())
+    }
+
+    #[test]
+    fn chosen_decision_becomes_goal() -> Result<(), core::failure::Error> {
+        let decision = |action: Option<&str>| {
+            Arc::new(DefinedDecision::new(&DecisionDefinition {
+                name: "Test".to_string(),
+                description: None,
+                action: action.map(str::to_string),
+                base: 1.0,
+                cooldown: 0.0,
+                considerations: Vec::new(),
+            }))
+        };
+        let action_defs =
+            DefinitionStorage::<ActionDefinition>::from_folder("../resources/defs/actions")?;
+        let fell_tree = action_defs.get_id("Fell Tree").unwrap();
+
+        let mut world = World::new();
+        world.insert(action_defs);
+        let mut system = DecisionGoalSystemDesc::default().build(&mut world);
+
+        let entity = world.create_entity().build();
+        let choose = |world: &World, action| {
+            world
+                .fetch_mut::<EventChannel<DecisionChosenEvent>>()
+                .single_write(DecisionChosenEvent {
+                    entity,
+                    decision: decision(action),
+                    priority: 0,
+                    score: 1.0,
                 });
-            };
         };
 
-        Box::new(UtilitySystem::with(runner))
+        choose(&world, Some("Fell Tree"));
+        system.run_now(&world);
+        assert_eq!(
+            world
+                .read_storage::<CurrentGoalComponent>()
+                .get(entity)
+                .map(|goal| goal.action_id),
+            Some(fell_tree)
+        );
+        assert!(world
+            .read_storage::<PlanExecutionComponent>()
+            .get(entity)
+            .unwrap()
+            .needs_plan());
+
+        choose(&world, None);
+        system.run_now(&world);
+        assert!(!world
+            .read_storage::<CurrentGoalComponent>()
+            .contains(entity));
+        assert!(!world
+            .read_storage::<PlanExecutionComponent>()
+            .contains(entity));
+
+        Ok(())
     }
 }
@@ -1,5 +1,8 @@
 use crate::pathing::PathingResult;
-use core::fsm::{ActionEvent, ActionStatus};
+use core::{
+    amethyst::ecs::{Component, VecStorage},
+    fsm::{ActionEvent, ActionStatus},
+};
 
 pub use crate::behavior::UtilityStateComponent;
 
@@ -11,6 +14,9 @@ impl CurrentPathingComponent {
     pub fn new(current_path: Option<PathingResult>) -> Self { Self { current_path } }
     pub fn finished(&mut self) { self.current_path = None; }
 }
+impl Component for CurrentPathingComponent {
+    type Storage = VecStorage<Self>;
+}
 
 #[derive(Debug, Clone)]
 pub struct CurrentActionComponent {
@@ -25,3 +31,6 @@ impl CurrentActionComponent {
         }
     }
 }
+impl Component for CurrentActionComponent {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,541 @@
+//! Hierarchical pathfinding (HPA*) over fixed size chunks of the region map.
+//!
+//! The region is split into `CHUNK_SIZE` x `CHUNK_SIZE` chunks per z-level. Entrances are found on
+//! the borders between neighbouring chunks and on z-transitions between levels, and the cost of
+//! travelling between the entrances of each chunk is cached. A long path is first solved over this
+//! abstract graph, then only the chunks along the route are refined to tile-level paths.
+//!
+//! Chunks are built lazily the first time a search touches them, and are dropped again when one
+//! of their tiles changes, so only the parts of the map pawns actually travel through are built.
+
+use super::{filter_adjacent_tiles, octile_distance};
+use core::{
+    amethyst::tiles::{Map, MapStorage},
+    defs::property::MovementFlags,
+    tiles::{
+        region::{RegionTile, RegionTileFlags},
+        MovementCostTable,
+    },
+};
+use std::collections::HashMap;
+
+pub const CHUNK_SIZE: u32 = 16;
+
+/// Border runs at least this long get an entrance at each end instead of a single one in the
+/// middle.
+const LONG_ENTRANCE: usize = 6;
+
+type Point = (u32, u32, u32);
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ChunkId {
+    z: u32,
+    y: u32,
+    x: u32,
+}
+impl ChunkId {
+    pub fn of((x, y, z): Point) -> Self {
+        Self {
+            x: x / CHUNK_SIZE,
+            y: y / CHUNK_SIZE,
+            z,
+        }
+    }
+
+    pub fn contains(self, p: Point) -> bool { Self::of(p) == self }
+
+    fn origin(self) -> (u32, u32) { (self.x * CHUNK_SIZE, self.y * CHUNK_SIZE) }
+
+    fn neighbours<M: Map>(self, map: &M) -> impl Iterator<Item = ChunkId> {
+        let dimensions = *map.dimensions();
+        let (chunks_x, chunks_y) = (
+            (dimensions.x + CHUNK_SIZE - 1) / CHUNK_SIZE,
+            (dimensions.y + CHUNK_SIZE - 1) / CHUNK_SIZE,
+        );
+
+        let mut r = Vec::with_capacity(6);
+        if self.x > 0 {
+            r.push(Self {
+                x: self.x - 1,
+                ..self
+            });
+        }
+        if self.x + 1 < chunks_x {
+            r.push(Self {
+                x: self.x + 1,
+                ..self
+            });
+        }
+        if self.y > 0 {
+            r.push(Self {
+                y: self.y - 1,
+                ..self
+            });
+        }
+        if self.y + 1 < chunks_y {
+            r.push(Self {
+                y: self.y + 1,
+                ..self
+            });
+        }
+        if self.z > 0 {
+            r.push(Self {
+                z: self.z - 1,
+                ..self
+            });
+        }
+        if self.z + 1 < dimensions.z {
+            r.push(Self {
+                z: self.z + 1,
+                ..self
+            });
+        }
+        r.into_iter()
+    }
+}
+
+/// A crossing between two chunks. `a` lies in the first chunk of the border key, `b` in the
+/// second; the costs are for entering the tile on the other side.
+#[derive(Debug, Clone, Copy)]
+struct Entrance {
+    a: Point,
+    b: Point,
+    into_a: u32,
+    into_b: u32,
+}
+
+#[derive(Debug, Default)]
+struct Chunk {
+    /// Abstract nodes of this chunk, and the cost of reaching the other nodes from them.
+    edges: HashMap<Point, Vec<(Point, u32)>>,
+}
+
+/// The abstract entrance graph for one movement mode.
+#[derive(Debug)]
+pub struct HierarchicalGraph {
+    kind: MovementFlags,
+    borders: HashMap<(ChunkId, ChunkId), Vec<Entrance>>,
+    chunks: HashMap<ChunkId, Chunk>,
+}
+impl HierarchicalGraph {
+    pub fn new(kind: MovementFlags) -> Self {
+        Self {
+            kind,
+            borders: HashMap::new(),
+            chunks: HashMap::new(),
+        }
+    }
+
+    pub fn kind(&self) -> MovementFlags { self.kind }
+
+    /// Number of chunks currently built.
+    pub fn len(&self) -> usize { self.chunks.len() }
+
+    pub fn is_empty(&self) -> bool { self.chunks.is_empty() }
+
+    pub fn clear(&mut self) {
+        self.borders.clear();
+        self.chunks.clear();
+    }
+
+    /// Drops everything derived from the tile at `p`, to be rebuilt the next time a search
+    /// touches it.
+    pub fn invalidate(&mut self, p: Point) {
+        let chunk = ChunkId::of(p);
+        let (ox, oy) = chunk.origin();
+        let (lx, ly) = (p.0 - ox, p.1 - oy);
+
+        self.chunks.remove(&chunk);
+
+        let borders: Vec<_> = self
+            .borders
+            .keys()
+            .filter(|(a, b)| *a == chunk || *b == chunk)
+            .copied()
+            .collect();
+
+        for (a, b) in borders {
+            let other = if a == chunk { b } else { a };
+
+            // Horizontal borders only change if the tile lies on them
+            let touches = if other.z == chunk.z {
+                (other.x < chunk.x && lx == 0)
+                    || (other.x > chunk.x && lx == CHUNK_SIZE - 1)
+                    || (other.y < chunk.y && ly == 0)
+                    || (other.y > chunk.y && ly == CHUNK_SIZE - 1)
+            } else {
+                true
+            };
+
+            if touches {
+                self.borders.remove(&(a, b));
+                self.chunks.remove(&other);
+            }
+        }
+    }
+
+    /// Builds any of the given chunks which are missing, along with their borders.
+    pub fn build_chunks<M>(&mut self, chunks: &[ChunkId], map: &M, costs: &MovementCostTable)
+    where
+        M: Map + MapStorage<RegionTile>,
+    {
+        for chunk in chunks {
+            self.build_chunk(*chunk, map, costs);
+        }
+    }
+
+    /// Finds a path from `start` to `goal` over the abstract graph, refined to tiles. The path
+    /// includes both the start and goal tiles. The graph is only read, so any chunks the search
+    /// reached which are not built yet are returned as the error instead, to be built with
+    /// `build_chunks` before searching again.
+    pub fn find_path<M>(
+        &self,
+        start: Point,
+        goal: Point,
+        map: &M,
+        costs: &MovementCostTable,
+    ) -> Result<Option<(Vec<Point>, u32)>, Vec<ChunkId>>
+    where
+        M: Map + MapStorage<RegionTile>,
+    {
+        use pathfinding::prelude::astar;
+
+        let start_chunk = ChunkId::of(start);
+        let goal_chunk = ChunkId::of(goal);
+        let mut missing = [start_chunk, goal_chunk]
+            .iter()
+            .filter(|chunk| !self.chunks.contains_key(chunk))
+            .copied()
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            missing.dedup();
+            return Err(missing);
+        }
+
+        // Costs from the start to its chunk's nodes, and from the goal chunk's nodes to the goal
+        let from_start = self.chunk_costs(start, start_chunk, map, costs);
+        let mut to_goal: HashMap<Point, Option<u32>> = HashMap::new();
+
+        let kind = self.kind;
+        let abstract_path = astar(
+            &start,
+            |&node| {
+                let mut successors = if node == start {
+                    let mut successors = self.chunks[&start_chunk]
+                        .edges
+                        .keys()
+                        .filter_map(|n| from_start.get(n).map(|cost| (*n, *cost)))
+                        .collect::<Vec<_>>();
+
+                    // The start may itself be an entrance
+                    if self.chunks[&start_chunk].edges.contains_key(&start) {
+                        successors.extend(self.successors(start, map, &mut missing));
+                    }
+                    successors
+                } else {
+                    self.successors(node, map, &mut missing)
+                };
+
+                if ChunkId::of(node) == goal_chunk {
+                    let cost = if node == start {
+                        from_start.get(&goal).copied()
+                    } else {
+                        *to_goal.entry(node).or_insert_with(|| {
+                            find_path_in_chunk(node, goal, kind, map, costs).map(|(_, c)| c)
+                        })
+                    };
+                    if let Some(cost) = cost {
+                        successors.push((goal, cost));
+                    }
+                }
+
+                successors
+            },
+            |&node| octile_distance(node, goal, costs),
+            |&node| node == goal,
+        );
+
+        // Missing chunks were dead ends, so the search has to be redone once they are built
+        if !missing.is_empty() {
+            missing.sort();
+            missing.dedup();
+            return Err(missing);
+        }
+        let abstract_path = match abstract_path {
+            Some(abstract_path) => abstract_path,
+            None => return Ok(None),
+        };
+
+        // Refine each abstract step to tiles
+        let mut path = vec![start];
+        let mut total_cost = 0;
+        for step in abstract_path.0.windows(2) {
+            let (from, to) = (step[0], step[1]);
+
+            if from.2 == to.2 && ChunkId::of(from) == ChunkId::of(to) {
+                let (tiles, cost) = match find_path_in_chunk(from, to, kind, map, costs) {
+                    Some(refined) => refined,
+                    None => return Ok(None),
+                };
+                path.extend(tiles.into_iter().skip(1));
+                total_cost += cost;
+            } else {
+                total_cost += match self.crossing_cost(from, to) {
+                    Some(cost) => cost,
+                    None => return Ok(None),
+                };
+                path.push(to);
+            }
+        }
+
+        Ok(Some((path, total_cost)))
+    }
+
+    /// Abstract nodes reachable from `node`. Nodes in chunks which are not built yet are added
+    /// to `missing` and have no successors.
+    fn successors<M>(&self, node: Point, map: &M, missing: &mut Vec<ChunkId>) -> Vec<(Point, u32)>
+    where
+        M: Map,
+    {
+        let chunk = ChunkId::of(node);
+        let built = if let Some(built) = self.chunks.get(&chunk) {
+            built
+        } else {
+            missing.push(chunk);
+            return Vec::new();
+        };
+
+        let mut successors = built.edges.get(&node).cloned().unwrap_or_default();
+
+        for neighbour in chunk.neighbours(map) {
+            let key = border_key(chunk, neighbour);
+            for entrance in &self.borders[&key] {
+                if entrance.a == node {
+                    successors.push((entrance.b, entrance.into_b));
+                } else if entrance.b == node {
+                    successors.push((entrance.a, entrance.into_a));
+                }
+            }
+        }
+
+        successors
+    }
+
+    fn crossing_cost(&self, from: Point, to: Point) -> Option<u32> {
+        let key = border_key(ChunkId::of(from), ChunkId::of(to));
+        self.borders.get(&key)?.iter().find_map(|entrance| {
+            if entrance.a == from && entrance.b == to {
+                Some(entrance.into_b)
+            } else if entrance.b == from && entrance.a == to {
+                Some(entrance.into_a)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn build_chunk<M>(&mut self, chunk: ChunkId, map: &M, costs: &MovementCostTable)
+    where
+        M: Map + MapStorage<RegionTile>,
+    {
+        if self.chunks.contains_key(&chunk) {
+            return;
+        }
+
+        let mut nodes = Vec::new();
+        for neighbour in chunk.neighbours(map) {
+            let key = border_key(chunk, neighbour);
+            if !self.borders.contains_key(&key) {
+                let entrances = find_entrances(key, self.kind, map, costs);
+                self.borders.insert(key, entrances);
+            }
+
+            nodes.extend(self.borders[&key].iter().map(|entrance| {
+                if key.0 == chunk {
+                    entrance.a
+                } else {
+                    entrance.b
+                }
+            }));
+        }
+        nodes.sort();
+        nodes.dedup();
+
+        let mut edges = HashMap::with_capacity(nodes.len());
+        for node in &nodes {
+            let reachable = self.chunk_costs(*node, chunk, map, costs);
+            edges.insert(
+                *node,
+                nodes
+                    .iter()
+                    .filter(|other| *other != node)
+                    .filter_map(|other| reachable.get(other).map(|cost| (*other, *cost)))
+                    .collect(),
+            );
+        }
+
+        log::trace!("Built chunk {:?} with {} nodes", chunk, nodes.len());
+        self.chunks.insert(chunk, Chunk { edges });
+    }
+
+    /// Cost of reaching every tile of the chunk from `from`, without leaving the chunk.
+    fn chunk_costs<M>(
+        &self,
+        from: Point,
+        chunk: ChunkId,
+        map: &M,
+        costs: &MovementCostTable,
+    ) -> HashMap<Point, u32>
+    where
+        M: Map + MapStorage<RegionTile>,
+    {
+        use pathfinding::prelude::dijkstra_all;
+
+        let kind = self.kind;
+        dijkstra_all(&from, |&p| chunk_adjacent_tiles(p, chunk, kind, map, costs))
+            .into_iter()
+            .map(|(p, (_, cost))| (p, cost))
+            .collect()
+    }
+}
+
+/// Borders are keyed with the lower chunk first, so both chunks find the same entry.
+fn border_key(a: ChunkId, b: ChunkId) -> (ChunkId, ChunkId) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn chunk_adjacent_tiles<M>(
+    p: Point,
+    chunk: ChunkId,
+    kind: MovementFlags,
+    map: &M,
+    costs: &MovementCostTable,
+) -> Vec<(Point, u32)>
+where
+    M: Map + MapStorage<RegionTile>,
+{
+    let mut adjacent = filter_adjacent_tiles(p, kind, map, costs);
+    adjacent.retain(|(n, _)| n.2 == chunk.z && chunk.contains(*n));
+    adjacent
+}
+
+/// Tile-level path between two tiles of the same chunk, without leaving it.
+fn find_path_in_chunk<M>(
+    from: Point,
+    to: Point,
+    kind: MovementFlags,
+    map: &M,
+    costs: &MovementCostTable,
+) -> Option<(Vec<Point>, u32)>
+where
+    M: Map + MapStorage<RegionTile>,
+{
+    use pathfinding::prelude::astar;
+
+    let chunk = ChunkId::of(from);
+    astar(
+        &from,
+        |&p| chunk_adjacent_tiles(p, chunk, kind, map, costs),
+        |&p| octile_distance(p, to, costs),
+        |&p| p == to,
+    )
+}
+
+fn tile_cost<M>(p: Point, kind: MovementFlags, map: &M, costs: &MovementCostTable) -> u32
+where
+    M: Map + MapStorage<RegionTile>,
+{
+    map.encode_raw(&p)
+        .and_then(|coord| map.get_raw(coord))
+        .map_or(0, |tile| tile.movement_modifier(kind, costs))
+}
+
+fn find_entrances<M>(
+    (a, b): (ChunkId, ChunkId),
+    kind: MovementFlags,
+    map: &M,
+    costs: &MovementCostTable,
+) -> Vec<Entrance>
+where
+    M: Map + MapStorage<RegionTile>,
+{
+    let dimensions = *map.dimensions();
+    let (ox, oy) = a.origin();
+    let mut entrances = Vec::new();
+
+    let entrance = |pa: Point, pb: Point| {
+        let into_a = tile_cost(pa, kind, map, costs);
+        let into_b = tile_cost(pb, kind, map, costs);
+        if into_a > 0 && into_b > 0 {
+            Some(Entrance {
+                a: pa,
+                b: pb,
+                into_a,
+                into_b,
+            })
+        } else {
+            None
+        }
+    };
+
+    if a.z != b.z {
+        // Every pair of z-transitions stacked on top of each other is an entrance
+        for y in oy..(oy + CHUNK_SIZE).min(dimensions.y) {
+            for x in ox..(ox + CHUNK_SIZE).min(dimensions.x) {
+                let (pa, pb) = ((x, y, a.z), (x, y, b.z));
+                let is_transition = |p: Point| {
+                    map.encode_raw(&p)
+                        .and_then(|coord| map.get_raw(coord))
+                        .map_or(false, |tile| {
+                            tile.flags.contains(RegionTileFlags::HasZTransition)
+                        })
+                };
+                if is_transition(pa) && is_transition(pb) {
+                    if let Some(mut e) = entrance(pa, pb) {
+                        e.into_a += super::Z_TRANSITION_COST;
+                        e.into_b += super::Z_TRANSITION_COST;
+                        entrances.push(e);
+                    }
+                }
+            }
+        }
+        return entrances;
+    }
+
+    // Walk the shared edge, collecting runs of tiles passable on both sides
+    let edge: Vec<(Point, Point)> = if a.x != b.x {
+        let x = ox + CHUNK_SIZE - 1;
+        (oy..(oy + CHUNK_SIZE).min(dimensions.y))
+            .map(|y| ((x, y, a.z), (x + 1, y, a.z)))
+            .collect()
+    } else {
+        let y = oy + CHUNK_SIZE - 1;
+        (ox..(ox + CHUNK_SIZE).min(dimensions.x))
+            .map(|x| ((x, y, a.z), (x, y + 1, a.z)))
+            .collect()
+    };
+
+    let mut run: Vec<Entrance> = Vec::new();
+    for pair in edge.into_iter().map(Some).chain(std::iter::once(None)) {
+        if let Some(e) = pair.and_then(|(pa, pb)| entrance(pa, pb)) {
+            run.push(e);
+            continue;
+        }
+
+        if !run.is_empty() {
+            if run.len() >= LONG_ENTRANCE {
+                entrances.push(run[0]);
+                entrances.push(run[run.len() - 1]);
+            } else {
+                entrances.push(run[run.len() / 2]);
+            }
+            run.clear();
+        }
+    }
+
+    entrances
+}
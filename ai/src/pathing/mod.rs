@@ -0,0 +1,1003 @@
+use crate::components::CurrentPathingComponent;
+use core::{
+    amethyst::{
+        core::{math::Point3, SystemDesc},
+        ecs::{
+            Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, SystemData, World,
+            Write, WriteStorage,
+        },
+        shrev::{EventChannel, ReaderId},
+        tiles::{Map, MapStorage, TileMap},
+    },
+    defs::property::MovementFlags,
+    tiles::{
+        region::{RegionTile, RegionTileChangedEvent, RegionTileFlags},
+        MovementCostTable, BASE_MOVEMENT_COST, DIAGONAL_MOVEMENT_COST,
+    },
+};
+use crossbeam::channel::{Receiver, Sender};
+use hierarchical::HierarchicalGraph;
+use parking_lot::RwLock;
+use rayon::ThreadPool;
+use shrinkwraprs::Shrinkwrap;
+use smallvec::SmallVec;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+pub mod hierarchical;
+
+/// Each path has a max 4096 steps for it. This should be enough to traverse a Z-level.
+#[derive(Debug)]
+pub struct Path {
+    pub valid: Arc<AtomicBool>,
+    pub total_cost: u32,
+    pub path: SmallVec<[u32; 4096]>,
+}
+impl Default for Path {
+    fn default() -> Self {
+        Self {
+            valid: Arc::new(AtomicBool::new(true)),
+            path: SmallVec::default(),
+            total_cost: u32::max_value(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct PathingRequestEvent {
+    pub entity: Entity,
+    pub source: Point3<u32>,
+    pub destination: Point3<u32>,
+    pub kind: MovementFlags,
+}
+impl PathingRequestEvent {
+    pub fn new(
+        entity: Entity,
+        source: Point3<u32>,
+        destination: Point3<u32>,
+        kind: MovementFlags,
+    ) -> Self {
+        Self {
+            entity,
+            source,
+            destination,
+            kind,
+        }
+    }
+}
+
+pub type PathingResponseEvent = PathingResult;
+
+pub type PathingResult = Result<(PathingRequestEvent, Arc<Path>), PathingFailure>;
+
+#[derive(Debug, Clone)]
+pub enum PathingFailureKind {
+    NoPath,
+    /// The destination is on another z-level and no z-transition connecting them was reachable.
+    NoZTransition,
+    Error,
+}
+
+#[derive(failure::Fail, Debug, Clone)]
+#[fail(display = "Type not implemented.")]
+pub struct PathingFailure {
+    pub request: PathingRequestEvent,
+    pub kind: PathingFailureKind,
+}
+
+#[derive(Shrinkwrap, Clone)]
+struct TileMapContainer(Option<Arc<RwLock<TileMap<RegionTile>>>>);
+impl TileMapContainer {
+    pub fn new(map: TileMap<RegionTile>) -> Self { Self(Some(Arc::new(RwLock::new(map)))) }
+    pub fn empty() -> Self { Self(None) }
+}
+
+/// Paths are cached against the start, destination and movement kind of a request; the
+/// requesting entity doesn't change the path.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+struct PathCacheKey {
+    source: Point3<u32>,
+    destination: Point3<u32>,
+    kind: MovementFlags,
+}
+impl From<&PathingRequestEvent> for PathCacheKey {
+    fn from(request: &PathingRequestEvent) -> Self {
+        Self {
+            source: request.source,
+            destination: request.destination,
+            kind: request.kind,
+        }
+    }
+}
+
+/// Max number of cached paths before the cache is flushed.
+const PATH_CACHE_CAPACITY: usize = 1024;
+
+/// A pathing result tagged with the map snapshot version it was solved against.
+type PathingJobResult = (u64, PathingResult);
+
+pub struct PathingWorkSystem {
+    // We store a copy of the target TileMap to allow multithreading
+    pathing_request_reader_id: ReaderId<PathingRequestEvent>,
+    tile_changed_reader_id: ReaderId<RegionTileChangedEvent>,
+    channel: (Sender<PathingJobResult>, Receiver<PathingJobResult>),
+    map: TileMapContainer,
+    map_entity: Option<Entity>,
+    // Version of the live map, bumped on every tile change
+    map_version: u64,
+    // Version of the live map the snapshot currently reflects
+    snapshot_version: u64,
+    // Version the current map was first snapshotted at; results solved before it are stale
+    map_base_version: u64,
+    // Tiles changed by each map version, kept while results solved before them may come back
+    tile_changes: Vec<(u64, HashSet<u32>)>,
+    // Number of jobs in flight, by the snapshot version they were dispatched against
+    in_flight: HashMap<u64, usize>,
+    dirty_tiles: HashSet<Point3<u32>>,
+    path_cache: HashMap<PathCacheKey, Arc<Path>>,
+    graphs: HierarchicalGraphs,
+}
+
+impl<'s> System<'s> for PathingWorkSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadExpect<'s, Arc<ThreadPool>>,
+        Read<'s, MovementCostTable>,
+        Read<'s, EventChannel<PathingRequestEvent>>,
+        Read<'s, EventChannel<RegionTileChangedEvent>>,
+        Write<'s, EventChannel<PathingResponseEvent>>,
+        ReadStorage<'s, TileMap<RegionTile>>,
+        WriteStorage<'s, CurrentPathingComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            pool,
+            costs,
+            pathing_requests,
+            tile_changes,
+            mut pathing_response,
+            tilemap_storage,
+            mut pathing_storage,
+        ): Self::SystemData,
+    ) {
+        let (map_entity, map) =
+            if let Some((entity, map)) = (&entities, &tilemap_storage).join().next() {
+                (entity, map)
+            } else {
+                self.map = TileMapContainer::empty();
+                self.map_entity = None;
+                self.invalidate_all();
+                return;
+            };
+
+        // A new map replaces the snapshot wholesale
+        if self.map.is_none() || self.map_entity != Some(map_entity) {
+            log::info!("Re-caching map");
+            self.map = TileMapContainer::new(map.clone());
+            self.map_entity = Some(map_entity);
+            self.map_version += 1;
+            self.snapshot_version = self.map_version;
+            self.map_base_version = self.map_version;
+            self.dirty_tiles.clear();
+            self.tile_changes.clear();
+            // In-flight jobs keep the old graphs alive, so they cannot leak into the new map
+            self.graphs = HierarchicalGraphs::default();
+            self.invalidate_all();
+        }
+
+        // Invalidate cached paths crossing changed tiles
+        let mut changed = HashSet::new();
+        for event in tile_changes.read(&mut self.tile_changed_reader_id) {
+            if let Some(coord) = map.encode(&event.0) {
+                changed.insert(coord);
+            }
+            self.dirty_tiles.insert(event.0);
+        }
+        if !changed.is_empty() {
+            self.map_version += 1;
+            self.invalidate(&changed);
+            self.tile_changes.push((self.map_version, changed));
+        }
+
+        self.sync_snapshot(map);
+
+        // Fire off rayon spawns for pathing requests
+        let mut msg_queue = Vec::with_capacity(32);
+        let new_requests = pathing_requests
+            .read(&mut self.pathing_request_reader_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        for new_request in new_requests {
+            if let Some(path) = self.path_cache.get(&PathCacheKey::from(&new_request)) {
+                if path.valid.load(Ordering::Relaxed) {
+                    log::trace!("Pathing cache hit: {:?}", new_request);
+                    msg_queue.push(Ok((new_request, path.clone())));
+                    continue;
+                }
+            }
+
+            self.dispatch(&pool, &costs, new_request);
+        }
+
+        // Collect any available pathing results and fire them off
+        while let Ok((version, msg)) = self.channel.1.try_recv() {
+            self.finish(version);
+
+            // Tiles the result depends on changed while it was being solved, so try again
+            if self.is_stale(version, &msg) {
+                let request = match &msg {
+                    Ok((request, _)) => request,
+                    Err(e) => &e.request,
+                };
+                log::trace!("Stale path result, re-requesting: {:?}", request);
+                self.dispatch(&pool, &costs, request.clone());
+                continue;
+            }
+
+            if let Ok((request, path)) = &msg {
+                if self.path_cache.len() >= PATH_CACHE_CAPACITY {
+                    self.path_cache.clear();
+                }
+                self.path_cache.insert(request.into(), path.clone());
+            }
+
+            msg_queue.push(msg);
+        }
+
+        for msg in &msg_queue {
+            let entity = match msg {
+                Ok((request, _)) => request.entity,
+                Err(e) => e.request.entity,
+            };
+
+            if entities.is_alive(entity) {
+                pathing_storage
+                    .insert(entity, CurrentPathingComponent::new(Some(msg.clone())))
+                    .unwrap();
+            }
+        }
+
+        self.prune_tile_changes();
+
+        pathing_response.drain_vec_write(&mut msg_queue);
+    }
+}
+impl PathingWorkSystem {
+    fn dispatch(
+        &mut self,
+        pool: &ThreadPool,
+        costs: &MovementCostTable,
+        request: PathingRequestEvent,
+    ) {
+        log::trace!("PATHING REQUEST THREAD FIRED");
+
+        let sender = self.channel.0.clone();
+        let map = self.map.clone();
+        let costs = costs.clone();
+        let graphs = self.graphs.clone();
+        let version = self.snapshot_version;
+        *self.in_flight.entry(version).or_insert(0) += 1;
+        pool.spawn(move || find_path(request, version, &map, &costs, &graphs, &sender));
+    }
+
+    fn finish(&mut self, version: u64) {
+        if let Some(count) = self.in_flight.get_mut(&version) {
+            *count -= 1;
+            if *count == 0 {
+                self.in_flight.remove(&version);
+            }
+        }
+    }
+
+    /// Whether a result solved against the snapshot at `version` depends on any tile which has
+    /// changed since. A path depends on the tiles it crosses, while a failure may be down to any
+    /// tile. Results solved against a previous map are always stale.
+    fn is_stale(&self, version: u64, result: &PathingResult) -> bool {
+        version < self.map_base_version
+            || self
+                .tile_changes
+                .iter()
+                .filter(|(changed_in, _)| *changed_in > version)
+                .any(|(_, changed)| match result {
+                    Ok((_, path)) => path.path.iter().any(|coord| changed.contains(coord)),
+                    Err(_) => true,
+                })
+    }
+
+    /// Forgets tile changes which neither the snapshot nor any job in flight predates.
+    fn prune_tile_changes(&mut self) {
+        let oldest = self
+            .in_flight
+            .keys()
+            .copied()
+            .min()
+            .map_or(self.snapshot_version, |version| {
+                version.min(self.snapshot_version)
+            });
+        self.tile_changes.retain(|(version, _)| *version > oldest);
+    }
+
+    /// Copies any dirty tiles from the live map into the snapshot. If pathing jobs are still
+    /// holding the snapshot, the sync is retried next frame.
+    fn sync_snapshot(&mut self, map: &TileMap<RegionTile>) {
+        if self.dirty_tiles.is_empty() {
+            return;
+        }
+
+        let snapshot = if let Some(snapshot) = self.map.as_ref() {
+            snapshot
+        } else {
+            return;
+        };
+
+        // Jobs take the snapshot lock before the graphs, so holding it means no job is using them
+        if let Some(mut snapshot) = snapshot.try_write() {
+            let graphs = self.graphs.read();
+            for coord in self.dirty_tiles.drain() {
+                if let (Some(tile), Some(target)) = (map.get(&coord), snapshot.get_mut(&coord)) {
+                    *target = tile.clone();
+                }
+                graphs
+                    .values()
+                    .for_each(|graph| graph.write().invalidate((coord.x, coord.y, coord.z)));
+            }
+            self.snapshot_version = self.map_version;
+        }
+    }
+
+    fn invalidate(&mut self, changed: &HashSet<u32>) {
+        self.path_cache.retain(|_, path| {
+            if path.path.iter().any(|coord| changed.contains(coord)) {
+                path.valid.store(false, Ordering::Relaxed);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn invalidate_all(&mut self) {
+        self.path_cache
+            .drain()
+            .for_each(|(_, path)| path.valid.store(false, Ordering::Relaxed));
+    }
+}
+
+#[derive(Default)]
+pub struct PathingWorkSystemDesc;
+impl<'a, 'b> SystemDesc<'a, 'b, PathingWorkSystem> for PathingWorkSystemDesc {
+    fn build(self, world: &mut World) -> PathingWorkSystem {
+        log::trace!("Setup PathingWorkSystem");
+        <PathingWorkSystem as System<'_>>::SystemData::setup(world);
+
+        let pathing_request_reader_id =
+            Write::<EventChannel<PathingRequestEvent>>::fetch(world).register_reader();
+        let tile_changed_reader_id =
+            Write::<EventChannel<RegionTileChangedEvent>>::fetch(world).register_reader();
+
+        PathingWorkSystem {
+            pathing_request_reader_id,
+            tile_changed_reader_id,
+            channel: crossbeam::channel::bounded(2048),
+            map: TileMapContainer::empty(),
+            map_entity: None,
+            map_version: 0,
+            snapshot_version: 0,
+            map_base_version: 0,
+            tile_changes: Vec::new(),
+            in_flight: HashMap::new(),
+            dirty_tiles: HashSet::new(),
+            path_cache: HashMap::with_capacity(PATH_CACHE_CAPACITY),
+            graphs: HierarchicalGraphs::default(),
+        }
+    }
+}
+
+fn bail(request: PathingRequestEvent) -> PathingResult {
+    Err(PathingFailure {
+        request,
+        kind: PathingFailureKind::Error,
+    })
+}
+
+/// Additional cost of moving between z-levels, on top of the destination tile's own movement
+/// modifier.
+pub const Z_TRANSITION_COST: u32 = BASE_MOVEMENT_COST * 2;
+
+/// Returns true if a pawn can move vertically between the two tiles. Both ends of the move must
+/// be flagged as a z-transition (ramp, stairs) for the move to be allowed.
+fn can_transition(from: &RegionTile, to: &RegionTile) -> bool {
+    from.flags.contains(RegionTileFlags::HasZTransition)
+        && to.flags.contains(RegionTileFlags::HasZTransition)
+}
+
+/// Octile distance scaled by the cheapest tile cost, plus the z-level transitions, so it never
+/// overestimates the remaining cost.
+fn octile_distance(
+    (x, y, z): (u32, u32, u32),
+    destination: (u32, u32, u32),
+    costs: &MovementCostTable,
+) -> u32 {
+    use pathfinding::prelude::absdiff;
+
+    let dx = absdiff(x, destination.0);
+    let dy = absdiff(y, destination.1);
+    let (long, short) = (dx.max(dy), dx.min(dy));
+
+    (BASE_MOVEMENT_COST * (long - short) + DIAGONAL_MOVEMENT_COST * short) * costs.min_cost()
+        / BASE_MOVEMENT_COST
+        + absdiff(z, destination.2) * Z_TRANSITION_COST
+}
+
+fn filter_adjacent_tiles<M>(
+    (x, y, z): (u32, u32, u32),
+    kind: MovementFlags,
+    map: &M,
+    costs: &MovementCostTable,
+) -> Vec<((u32, u32, u32), u32)>
+where
+    M: Map + MapStorage<RegionTile>,
+{
+    let dimensions = *map.dimensions();
+    let step = |v: u32, d: i8, max: u32| match d {
+        -1 => v.checked_sub(1),
+        1 => Some(v + 1).filter(|v| *v < max),
+        _ => Some(v),
+    };
+
+    // Returns the neighbour at the offset and the cost of entering it, 0 if impassable
+    let neighbour = |dx: i8, dy: i8| {
+        let p = (step(x, dx, dimensions.x)?, step(y, dy, dimensions.y)?, z);
+        if let Some(tile) = map.encode_raw(&p).and_then(|coord| map.get_raw(coord)) {
+            Some((p, tile.movement_modifier(kind, costs)))
+        } else {
+            log::error!("COULDNT FETCH TILE: {:?}", p);
+            None
+        }
+    };
+
+    let mut adjacent = Vec::with_capacity(10);
+
+    for &(dx, dy) in &[
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (-1, 0),
+        (1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+    ] {
+        let (p, cost) = match neighbour(dx, dy) {
+            Some((p, cost)) if cost > 0 => (p, cost),
+            _ => continue,
+        };
+
+        if dx == 0 || dy == 0 {
+            adjacent.push((p, cost));
+            continue;
+        }
+
+        // Don't cut corners, both orthogonal neighbours of a diagonal move must be passable
+        let blocked = |n: Option<((u32, u32, u32), u32)>| n.map_or(true, |(_, cost)| cost == 0);
+        if blocked(neighbour(dx, 0)) || blocked(neighbour(0, dy)) {
+            continue;
+        }
+
+        adjacent.push((p, cost * DIAGONAL_MOVEMENT_COST / BASE_MOVEMENT_COST));
+    }
+
+    // Vertical neighbours, only reachable through a z-transition on both levels
+    if let Some(current) = map.encode_raw(&(x, y, z)).and_then(|c| map.get_raw(c)) {
+        if current.flags.contains(RegionTileFlags::HasZTransition) {
+            let mut vertical = SmallVec::<[(u32, u32, u32); 2]>::new();
+            if z > 0 {
+                vertical.push((x, y, z - 1));
+            }
+            if z + 1 < dimensions.z {
+                vertical.push((x, y, z + 1));
+            }
+
+            adjacent.extend(vertical.into_iter().filter_map(|p| {
+                let tile = map.encode_raw(&p).and_then(|c| map.get_raw(c))?;
+                let cost = tile.movement_modifier(kind, costs);
+                if can_transition(current, tile) && cost > 0 {
+                    Some((p, cost + Z_TRANSITION_COST))
+                } else {
+                    None
+                }
+            }));
+        }
+    }
+
+    adjacent
+}
+
+/// Requests horizontally further apart than this are solved over the hierarchical chunk graph
+/// instead of tile by tile.
+const HIERARCHICAL_DISTANCE: u32 = hierarchical::CHUNK_SIZE * 2;
+
+/// Hierarchical graphs for each movement mode, shared by all pathing jobs. Each mode is locked on
+/// its own, so searches for one never wait on another.
+type HierarchicalGraphs = Arc<RwLock<HashMap<MovementFlags, Arc<RwLock<HierarchicalGraph>>>>>;
+
+/// Short hops between z-levels are cheaper to search tile by tile, so only the horizontal
+/// distance counts.
+fn is_long_haul(request: &PathingRequestEvent) -> bool {
+    use pathfinding::prelude::absdiff;
+
+    absdiff(request.source.x, request.destination.x) > HIERARCHICAL_DISTANCE
+        || absdiff(request.source.y, request.destination.y) > HIERARCHICAL_DISTANCE
+}
+
+fn graph_for(graphs: &HierarchicalGraphs, kind: MovementFlags) -> Arc<RwLock<HierarchicalGraph>> {
+    if let Some(graph) = graphs.read().get(&kind) {
+        return graph.clone();
+    }
+
+    graphs
+        .write()
+        .entry(kind)
+        .or_insert_with(|| Arc::new(RwLock::new(HierarchicalGraph::new(kind))))
+        .clone()
+}
+
+fn find_path(
+    request: PathingRequestEvent,
+    version: u64,
+    map: &TileMapContainer,
+    costs: &MovementCostTable,
+    graphs: &HierarchicalGraphs,
+    result_channel: &Sender<PathingJobResult>,
+) {
+    let result = if let Some(map) = map.as_ref() {
+        let map = map.read();
+        if is_long_haul(&request) {
+            let graph = graph_for(graphs, request.kind);
+            find_path_hierarchical(request, &map, costs, &graph)
+        } else {
+            find_path_sync(request, &map, costs)
+        }
+    } else {
+        bail(request)
+    };
+
+    // The system is gone if nothing is listening any more, so the result has nowhere to go
+    let _ = result_channel.send((version, result));
+}
+
+fn find_path_sync(
+    request: PathingRequestEvent,
+    map: &TileMap<RegionTile>,
+    costs: &MovementCostTable,
+) -> PathingResult {
+    use pathfinding::prelude::astar;
+
+    let kind = request.kind;
+    search(request, map, costs, |start, destination| {
+        astar(
+            &start,
+            |&point| filter_adjacent_tiles(point, kind, &*map, costs),
+            |&point| octile_distance(point, destination, costs),
+            |&p| p == destination,
+        )
+    })
+}
+
+/// Searches under a read lock, so jobs share the graph. Chunks the search needs are built under
+/// a short write lock, and the search is then retried.
+fn find_path_hierarchical(
+    request: PathingRequestEvent,
+    map: &TileMap<RegionTile>,
+    costs: &MovementCostTable,
+    graph: &RwLock<HierarchicalGraph>,
+) -> PathingResult {
+    search(request, map, costs, |start, destination| loop {
+        let missing = match graph.read().find_path(start, destination, map, costs) {
+            Ok(path) => return path,
+            Err(missing) => missing,
+        };
+        graph.write().build_chunks(&missing, map, costs);
+    })
+}
+
+/// Validates the request against the map, runs the search and packs its steps into a `Path`.
+fn search<F>(
+    request: PathingRequestEvent,
+    map: &TileMap<RegionTile>,
+    costs: &MovementCostTable,
+    f: F,
+) -> PathingResult
+where
+    F: FnOnce((u32, u32, u32), (u32, u32, u32)) -> Option<(Vec<(u32, u32, u32)>, u32)>,
+{
+    let mut result = Path::default();
+
+    log::trace!(
+        "source= {:?}, target = {:?}",
+        request.source,
+        request.destination
+    );
+
+    let (source_tile, destination_tile) =
+        match (map.get(&request.source), map.get(&request.destination)) {
+            (Some(source), Some(destination)) => (source, destination),
+            _ => {
+                log::error!(
+                    "Pathing request out of map bounds: {:?} -> {:?}",
+                    request.source,
+                    request.destination
+                );
+                return bail(request);
+            }
+        };
+
+    if !source_tile.passable(request.kind, costs) {
+        log::error!("SOURCE TILE IS NOT PASSABLE?!");
+    }
+
+    if !destination_tile.passable(request.kind, costs) {
+        return Err(PathingFailure {
+            request,
+            kind: PathingFailureKind::NoPath,
+        });
+    }
+
+    let start = (request.source.x, request.source.y, request.source.z);
+    let destination = (
+        request.destination.x,
+        request.destination.y,
+        request.destination.z,
+    );
+
+    log::trace!(
+        "Path finding work received: {:?} -> {:?}",
+        start,
+        destination
+    );
+
+    let path_result = f(start, destination).map(|r| {
+        result.total_cost = r.1;
+        r.0.into_iter()
+            .for_each(|step| result.path.push(map.encode_raw(&step).unwrap()));
+    });
+
+    log::trace!("FIRING RESULT: {:?}", &result);
+
+    if path_result.is_none() {
+        let kind = if request.source.z == request.destination.z {
+            PathingFailureKind::NoPath
+        } else {
+            PathingFailureKind::NoZTransition
+        };
+
+        Err(PathingFailure { request, kind })
+    } else {
+        Ok((request, Arc::new(result)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{
+        amethyst::{
+            core::math::Vector3,
+            ecs::{Builder, RunNow, WorldExt},
+        },
+        defs::material::MaterialLayerRefCompact,
+        tiles::LayerBits,
+    };
+
+    fn floor() -> RegionTile {
+        RegionTile::new(LayerBits::from_material_refs_compact(&[
+            MaterialLayerRefCompact {
+                material_id: 1,
+                ..MaterialLayerRefCompact::default()
+            },
+        ]))
+    }
+
+    fn test_map() -> TileMap<RegionTile> {
+        let mut map =
+            TileMap::<RegionTile>::new(Vector3::new(8, 8, 2), Vector3::new(1, 1, 1), None);
+        for z in 0..2 {
+            for y in 0..8 {
+                for x in 0..8 {
+                    *map.get_mut(&Point3::new(x, y, z)).unwrap() = floor();
+                }
+            }
+        }
+        map
+    }
+
+    fn request(source: Point3<u32>, destination: Point3<u32>) -> PathingRequestEvent {
+        let mut world = core::amethyst::ecs::World::new();
+        let entity = world.create_entity().build();
+        PathingRequestEvent::new(entity, source, destination, MovementFlags::Walk)
+    }
+
+    #[test]
+    fn find_path_test() {
+        let map = test_map();
+        let costs = MovementCostTable::default();
+
+        // Two diagonal steps and a straight one
+        let (_, path) = find_path_sync(
+            request(Point3::new(0, 0, 0), Point3::new(3, 2, 0)),
+            &map,
+            &costs,
+        )
+        .unwrap();
+        assert_eq!(
+            path.total_cost,
+            DIAGONAL_MOVEMENT_COST * 2 + BASE_MOVEMENT_COST
+        );
+        assert_eq!(path.path.len(), 4);
+    }
+
+    #[test]
+    fn find_path_z_levels() {
+        let mut map = test_map();
+        let costs = MovementCostTable::default();
+
+        // No transition between the levels yet
+        match find_path_sync(
+            request(Point3::new(0, 0, 0), Point3::new(3, 0, 1)),
+            &map,
+            &costs,
+        ) {
+            Err(PathingFailure {
+                kind: PathingFailureKind::NoZTransition,
+                ..
+            }) => {}
+            r => panic!("Expected a z-transition failure, got: {:?}", r),
+        }
+
+        // Stairs at (2, 0)
+        for z in 0..2 {
+            map.get_mut(&Point3::new(2, 0, z))
+                .unwrap()
+                .flags
+                .insert(RegionTileFlags::HasZTransition);
+        }
+
+        let (_, path) = find_path_sync(
+            request(Point3::new(0, 0, 0), Point3::new(3, 0, 1)),
+            &map,
+            &costs,
+        )
+        .unwrap();
+        assert_eq!(path.total_cost, BASE_MOVEMENT_COST * 4 + Z_TRANSITION_COST);
+        assert_eq!(path.path.len(), 5);
+    }
+
+    #[test]
+    fn find_path_corners() {
+        let mut map = test_map();
+        let costs = MovementCostTable::default();
+
+        // Wall at (1, 0) stops the diagonal from (0, 0) to (1, 1)
+        *map.get_mut(&Point3::new(1, 0, 0)).unwrap() = RegionTile::default();
+
+        let (_, path) = find_path_sync(
+            request(Point3::new(0, 0, 0), Point3::new(1, 1, 0)),
+            &map,
+            &costs,
+        )
+        .unwrap();
+        assert_eq!(path.total_cost, BASE_MOVEMENT_COST * 2);
+        assert_eq!(path.path.len(), 3);
+    }
+
+    #[test]
+    fn find_path_hierarchical_test() {
+        let mut map =
+            TileMap::<RegionTile>::new(Vector3::new(64, 64, 1), Vector3::new(1, 1, 1), None);
+        for y in 0..64 {
+            for x in 0..64 {
+                *map.get_mut(&Point3::new(x, y, 0)).unwrap() = floor();
+            }
+        }
+        // A wall across the map with a single gap at the bottom
+        for y in 0..60 {
+            *map.get_mut(&Point3::new(40, y, 0)).unwrap() = RegionTile::default();
+        }
+
+        let costs = MovementCostTable::default();
+        let graph = RwLock::new(HierarchicalGraph::new(MovementFlags::Walk));
+        let source = Point3::new(2, 2, 0);
+        let destination = Point3::new(60, 2, 0);
+
+        let (_, flat) = find_path_sync(request(source, destination), &map, &costs).unwrap();
+        let (_, path) =
+            find_path_hierarchical(request(source, destination), &map, &costs, &graph).unwrap();
+
+        assert!(path.total_cost >= flat.total_cost);
+        assert_eq!(path.path[0], map.encode(&source).unwrap());
+        assert_eq!(
+            *path.path.last().unwrap(),
+            map.encode(&destination).unwrap()
+        );
+        // Every step moves to a neighbouring tile
+        for step in path.path.windows(2) {
+            let (a, b) = (map.decode(step[0]).unwrap(), map.decode(step[1]).unwrap());
+            assert!(pathfinding::prelude::absdiff(a.x, b.x) <= 1);
+            assert!(pathfinding::prelude::absdiff(a.y, b.y) <= 1);
+        }
+
+        // Close the gap, the cached chunks must be rebuilt to notice
+        for y in 60..64 {
+            let p = Point3::new(40, y, 0);
+            *map.get_mut(&p).unwrap() = RegionTile::default();
+            graph.write().invalidate((p.x, p.y, p.z));
+        }
+        assert!(
+            find_path_hierarchical(request(source, destination), &map, &costs, &graph).is_err()
+        );
+    }
+
+    /// A world with the test map, and a pathing system which has already snapshotted it.
+    fn pathing_world() -> (World, PathingWorkSystem, ReaderId<PathingResponseEvent>) {
+        let mut world = World::new();
+        world.insert(Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        ));
+        let mut system = PathingWorkSystemDesc::default().build(&mut world);
+        let reader = world
+            .fetch_mut::<EventChannel<PathingResponseEvent>>()
+            .register_reader();
+        world.create_entity().with(test_map()).build();
+        system.run_now(&world);
+
+        (world, system, reader)
+    }
+
+    fn request_path(world: &mut World, source: Point3<u32>, destination: Point3<u32>) {
+        let entity = world.create_entity().build();
+        world
+            .fetch_mut::<EventChannel<PathingRequestEvent>>()
+            .single_write(PathingRequestEvent::new(
+                entity,
+                source,
+                destination,
+                MovementFlags::Walk,
+            ));
+    }
+
+    fn responses(
+        world: &World,
+        system: &mut PathingWorkSystem,
+        reader: &mut ReaderId<PathingResponseEvent>,
+    ) -> Vec<PathingResponseEvent> {
+        system.run_now(world);
+        world
+            .fetch::<EventChannel<PathingResponseEvent>>()
+            .read(reader)
+            .cloned()
+            .collect()
+    }
+
+    /// Runs the system until the jobs in flight come back.
+    fn wait_for_responses(
+        world: &World,
+        system: &mut PathingWorkSystem,
+        reader: &mut ReaderId<PathingResponseEvent>,
+    ) -> Vec<PathingResponseEvent> {
+        for _ in 0..1000 {
+            let responses = responses(world, system, reader);
+            if !responses.is_empty() {
+                return responses;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        panic!("Timed out waiting for a path");
+    }
+
+    fn change_tile(world: &mut World, coord: Point3<u32>) {
+        world
+            .fetch_mut::<EventChannel<RegionTileChangedEvent>>()
+            .single_write(RegionTileChangedEvent(coord));
+    }
+
+    #[test]
+    fn cached_paths() {
+        let (mut world, mut system, mut reader) = pathing_world();
+
+        request_path(&mut world, Point3::new(0, 0, 0), Point3::new(5, 0, 0));
+        let first = wait_for_responses(&world, &mut system, &mut reader);
+        let (_, first) = first[0].as_ref().unwrap();
+
+        // The same trip is answered straight from the cache, whoever asks for it
+        request_path(&mut world, Point3::new(0, 0, 0), Point3::new(5, 0, 0));
+        let second = responses(&world, &mut system, &mut reader);
+        let (_, second) = second[0].as_ref().unwrap();
+        assert!(Arc::ptr_eq(first, second));
+
+        // Changing a tile it doesn't cross leaves it cached
+        change_tile(&mut world, Point3::new(5, 5, 0));
+        responses(&world, &mut system, &mut reader);
+        assert!(first.valid.load(Ordering::Relaxed));
+
+        // Changing a tile it crosses invalidates it
+        change_tile(&mut world, Point3::new(3, 0, 0));
+        responses(&world, &mut system, &mut reader);
+        assert!(!first.valid.load(Ordering::Relaxed));
+        assert!(system.path_cache.is_empty());
+    }
+
+    #[test]
+    fn stale_results() {
+        let (mut world, mut system, mut reader) = pathing_world();
+        let base = system.snapshot_version;
+
+        let (crossing, clear) = {
+            let tilemaps = world.read_storage::<TileMap<RegionTile>>();
+            let map = (&tilemaps).join().next().unwrap();
+            let costs = MovementCostTable::default();
+            let path = |y| {
+                find_path_sync(
+                    request(Point3::new(0, y, 0), Point3::new(5, y, 0)),
+                    map,
+                    &costs,
+                )
+                .unwrap()
+            };
+            (path(0), path(4))
+        };
+
+        let failed = PathingFailure {
+            request: request(Point3::new(0, 2, 0), Point3::new(5, 2, 0)),
+            kind: PathingFailureKind::NoPath,
+        };
+
+        // All three were being solved against the snapshot when a tile on one path changed
+        system.in_flight.insert(base, 3);
+        change_tile(&mut world, Point3::new(3, 0, 0));
+        responses(&world, &mut system, &mut reader);
+        assert!(system.snapshot_version > base);
+
+        system.channel.0.send((base, Ok(crossing.clone()))).unwrap();
+        system.channel.0.send((base, Ok(clear.clone()))).unwrap();
+        system.channel.0.send((base, Err(failed.clone()))).unwrap();
+
+        // The path crossing the change is solved again, and so is the failure
+        let delivered = responses(&world, &mut system, &mut reader);
+        assert_eq!(delivered.len(), 1);
+        let (request, path) = delivered[0].as_ref().unwrap();
+        assert_eq!(request, &clear.0);
+        assert!(Arc::ptr_eq(path, &clear.1));
+
+        let mut resolved = Vec::new();
+        while resolved.len() < 2 {
+            resolved.extend(wait_for_responses(&world, &mut system, &mut reader));
+        }
+        let resolved = resolved
+            .iter()
+            .map(|result| result.as_ref().unwrap())
+            .collect::<Vec<_>>();
+        let (_, path) = resolved
+            .iter()
+            .find(|(request, _)| request == &crossing.0)
+            .unwrap();
+        assert!(!Arc::ptr_eq(path, &crossing.1));
+        assert!(resolved
+            .iter()
+            .any(|(request, _)| request == &failed.request));
+
+        // Nothing is in flight any more, so the change is forgotten
+        assert!(system.in_flight.is_empty());
+        assert!(system.tile_changes.is_empty());
+    }
+}
@@ -1,3 +1,82 @@
-fn main() {
-    println!("Hello, world!");
+//! Builds the compiled definitions bundle the game loads instead of parsing every RON file.
+//!
+//! ```text
+//! assets bundle [--root resources/defs] [--mods resources/mods] [--out resources/defs.bundle]
+//! assets check  [--root resources/defs] [--mods resources/mods] [--out resources/defs.bundle]
+//! ```
+
+#![deny(clippy::pedantic, clippy::all)]
+
+use core::defs::{
+    bundle::{source_hash, DefinitionBundle, DEFAULT_BUNDLE_PATH},
+    mods::mod_list,
+};
+
+const USAGE: &str =
+    "usage: assets <bundle|check> [--root <definitions>] [--mods <mods folder>] [--out <bundle>]";
+
+struct Options {
+    command: String,
+    root: String,
+    mods: String,
+    out: String,
+}
+impl Options {
+    fn parse() -> Result<Self, failure::Error> {
+        let mut args = std::env::args().skip(1);
+        let mut options = Self {
+            command: args
+                .next()
+                .ok_or_else(|| failure::format_err!("{}", USAGE))?,
+            root: "resources/defs".to_string(),
+            mods: "resources/mods".to_string(),
+            out: DEFAULT_BUNDLE_PATH.to_string(),
+        };
+
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| failure::format_err!("missing value for {}\n{}", arg, USAGE))?;
+            match arg.as_str() {
+                "--root" => options.root = value,
+                "--mods" => options.mods = value,
+                "--out" => options.out = value,
+                _ => failure::bail!("unknown option {}\n{}", arg, USAGE),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn main() -> Result<(), failure::Error> {
+    env_logger::init();
+
+    let options = Options::parse()?;
+    let mods = mod_list(&options.root, &options.mods)?;
+    match options.command.as_str() {
+        "bundle" => {
+            let bundle = DefinitionBundle::build(&mods)?;
+            bundle.write(&options.out)?;
+            println!(
+                "Wrote {} (version {}, hash {:016x})",
+                options.out, bundle.version, bundle.hash
+            );
+        }
+        "check" => {
+            if DefinitionBundle::load_current(&options.out, &mods).is_some() {
+                println!("{} is up to date", options.out);
+            } else {
+                println!(
+                    "{} is missing or stale (definitions hash {:016x})",
+                    options.out,
+                    source_hash(&mods)?
+                );
+                std::process::exit(1);
+            }
+        }
+        _ => failure::bail!("unknown command {}\n{}", options.command, USAGE),
+    }
+
+    Ok(())
 }
@@ -1,6 +1,9 @@
 #![allow(clippy::module_name_repetitions)]
 
-use crate::systems::{item_sprites::ItemSpritesUpdateSystemDesc, BodyUpdatePropertiesSystemDesc};
+use crate::systems::{
+    digestion::DigestionSystem, item_sprites::ItemSpritesUpdateSystemDesc,
+    physiology::PhysiologySystem, BodyUpdatePropertiesSystemDesc,
+};
 use core::amethyst::core::{
     ecs::{prelude::DispatcherBuilder, World},
     SystemBundle, SystemDesc,
@@ -32,10 +35,12 @@ impl<'a, 'b, 'c> SystemBundle<'a, 'b> for BodyBundle<'c> {
             "item_hierarchy_system",
             self.dep,
         );
+        builder.add(PhysiologySystem::default(), "physiology_system", &[]);
+        builder.add(DigestionSystem::default(), "digestion_system", &[]);
         builder.add(
             BodyUpdatePropertiesSystemDesc::default().build(world),
             "body_update_system",
-            &[],
+            &["physiology_system", "digestion_system"],
         );
         builder.add(
             ItemSpritesUpdateSystemDesc::default().build(world),
@@ -0,0 +1,180 @@
+use crate::{physiology::Consciousness, BodyComponent};
+use core::{
+    defs::body::{BodyDefinition, Joint, JointRelation, Part, PartFlags},
+    petgraph::{graph::NodeIndex, visit::EdgeRef, Direction, Graph},
+};
+
+/// What a body can still do, worked out from the parts which still function. Each sense and
+/// `stance` run from 0.0 for lost to 1.0 for every part intact.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capabilities {
+    pub stance: f32,
+    pub fine_motor: bool,
+    pub sight: f32,
+    pub hearing: f32,
+    pub smell: f32,
+    pub conscious: bool,
+    pub alive: bool,
+}
+
+impl BodyComponent {
+    /// A body without a part graph has no parts to do anything with, but none it can die of
+    /// losing either.
+    pub fn capabilities(&self, def: &BodyDefinition) -> Capabilities {
+        let no_parts = Graph::new();
+        let graph = def.part_graph.as_ref().unwrap_or(&no_parts);
+        let vital = |flag: PartFlags| {
+            graph
+                .node_indices()
+                .filter(|index| has_flag(&graph[*index], flag))
+                .all(|index| self.part_function(&graph[index], index, flag) > 0.0)
+        };
+
+        // Shock or starvation leave a creature barely able to stagger about.
+        let mut stance = self.average_function(graph, PartFlags::Stance, true);
+        if self.physiology.consciousness == Consciousness::Shock {
+            stance *= 0.5;
+        }
+        if self.physiology.weakened() {
+            stance *= 0.5;
+        }
+
+        Capabilities {
+            stance,
+            fine_motor: self.average_function(graph, PartFlags::FineMotor, true) > 0.0,
+            sight: self.average_function(graph, PartFlags::Sight, false),
+            hearing: self.average_function(graph, PartFlags::Hear, false),
+            smell: self.average_function(graph, PartFlags::Smell, false),
+            conscious: self.physiology.conscious(),
+            alive: vital(PartFlags::Thought)
+                && vital(PartFlags::Circulation)
+                && !self.physiology.bled_out(),
+        }
+    }
+
+    /// Average function of every part with `flag`, or 0.0 if the body has none. Limbs are only as
+    /// good as the bones holding them up when `supported` is set.
+    fn average_function(
+        &self,
+        graph: &Graph<Part, Joint>,
+        flag: PartFlags,
+        supported: bool,
+    ) -> f32 {
+        let parts = graph
+            .node_indices()
+            .filter(|index| has_flag(&graph[*index], flag))
+            .collect::<Vec<_>>();
+        if parts.is_empty() {
+            return 0.0;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let count = parts.len() as f32;
+        parts
+            .iter()
+            .map(|index| {
+                let function = self.part_function(&graph[*index], *index, flag);
+                if supported {
+                    function * self.support(graph, *index)
+                } else {
+                    function
+                }
+            })
+            .sum::<f32>()
+            / count
+    }
+
+    /// How well the layers of a part carrying `flag` still work, from 0.0 to 1.0.
+    fn part_function(&self, part: &Part, index: NodeIndex, flag: PartFlags) -> f32 {
+        let state = &self.part_states[index.index()];
+        if state.severed {
+            return 0.0;
+        }
+
+        part.layers
+            .iter()
+            .zip(&state.layer_states)
+            .filter(|(layer, _)| layer.flags.contains(flag))
+            .map(|(_, layer_state)| 1.0 - layer_state.damage())
+            .fold(1.0, f32::min)
+    }
+
+    /// How well the limb bones a part hangs from still bear weight, following `Outside` joints
+    /// towards the body.
+    fn support(&self, graph: &Graph<Part, Joint>, index: NodeIndex) -> f32 {
+        let mut support = 1.0;
+        let mut next = Some(index);
+        while let Some(index) = next {
+            let part = &graph[index];
+            if !has_flag(part, PartFlags::Limb) {
+                break;
+            }
+            support *= self.part_function(part, index, PartFlags::Limb);
+
+            next = graph
+                .edges_directed(index, Direction::Outgoing)
+                .find(|edge| edge.weight().relation.contains(JointRelation::Outside))
+                .map(|edge| edge.target());
+        }
+        support
+    }
+}
+
+fn has_flag(part: &Part, flag: PartFlags) -> bool {
+    part.layers.iter().any(|layer| layer.flags.contains(flag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::damage::{AttackKind, Hit};
+    use core::{
+        defs::{material::MaterialDefinition, DefinitionStorage},
+        rand::SeedableRng,
+        rand_xorshift::XorShiftRng,
+    };
+
+    #[test]
+    fn capabilities_follow_damage() {
+        let bodies =
+            DefinitionStorage::<BodyDefinition>::from_folder("../resources/defs/bodies").unwrap();
+        let materials =
+            DefinitionStorage::<MaterialDefinition>::from_folder("../resources/defs/materials")
+                .unwrap();
+        let id = bodies.get_id("Humanoid").unwrap();
+        let def = bodies.get(id).unwrap();
+        let mut rng = XorShiftRng::seed_from_u64(0);
+
+        let mut body = BodyComponent::new(id, &bodies);
+        let healthy = body.capabilities(def);
+        assert!(healthy.alive && healthy.fine_motor);
+        assert!((healthy.stance - 1.0).abs() < std::f32::EPSILON);
+        assert!((healthy.sight - 1.0).abs() < std::f32::EPSILON);
+
+        let cut = Hit::new(AttackKind::Cutting, 200.0);
+        body.hit_part("Right Upper Leg", cut, def, &materials, &mut rng);
+        assert!((body.capabilities(def).stance - 0.5).abs() < std::f32::EPSILON);
+
+        body.hit_part("Left Upper Arm", cut, def, &materials, &mut rng);
+        body.hit_part("Right Upper Arm", cut, def, &materials, &mut rng);
+        assert!(!body.capabilities(def).fine_motor);
+
+        body.hit_part("Heart", cut, def, &materials, &mut rng);
+        assert!(!body.capabilities(def).alive);
+    }
+
+    #[test]
+    fn capabilities_without_parts() {
+        let bodies =
+            DefinitionStorage::<BodyDefinition>::from_folder("../resources/defs/bodies").unwrap();
+        let id = bodies.get_id("Humanoid").unwrap();
+        let body = BodyComponent::new(id, &bodies);
+
+        let mut def = bodies.get(id).unwrap().clone();
+        def.part_graph = None;
+        let capabilities = body.capabilities(&def);
+        assert!(capabilities.alive);
+        assert!(!capabilities.fine_motor);
+        assert!(capabilities.stance.abs() < std::f32::EPSILON);
+    }
+}
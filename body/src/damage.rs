@@ -0,0 +1,430 @@
+use crate::BodyComponent;
+use core::{
+    defs::{
+        body::{BodyDefinition, JointRelation, PartFlags, PartLayer},
+        material::{MaterialDefinition, MaterialStateDefinition},
+        property::Property,
+        DefinitionStorage, Named,
+    },
+    petgraph::{graph::NodeIndex, visit::EdgeRef, Direction},
+    rand::Rng,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttackKind {
+    Stabbing,
+    Bashing,
+    Cutting,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WoundKind {
+    Bruise,
+    Cut,
+    Fracture,
+    Sever,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wound {
+    pub kind: WoundKind,
+    /// How much of the layer the wound goes through, from 0.0 to 1.0. Shrinks as it heals.
+    pub severity: f32,
+    /// Blood lost in ml per second. Slows as the wound clots.
+    pub bleeding: f32,
+    /// Pain the wound causes at full severity.
+    pub pain: f32,
+}
+impl Wound {
+    pub fn new(kind: WoundKind, severity: f32, layer: &PartLayer) -> Self {
+        let (bleeding, pain) = match kind {
+            WoundKind::Bruise => (0.0, 5.0),
+            WoundKind::Cut => (2.0, 10.0),
+            WoundKind::Fracture => (0.5, 25.0),
+            WoundKind::Sever => (5.0, 15.0),
+        };
+        let bleeding = if layer.flags.contains(PartFlags::Circulation) {
+            bleeding * 25.0
+        } else {
+            bleeding
+        };
+        let pain = if layer.flags.contains(PartFlags::Nervous) {
+            pain * 2.0
+        } else if layer.flags.contains(PartFlags::Organ) {
+            pain * 1.5
+        } else {
+            pain
+        };
+
+        Self {
+            kind,
+            severity,
+            bleeding: bleeding * severity,
+            pain,
+        }
+    }
+
+    /// Pain the wound causes right now.
+    pub fn current_pain(&self) -> f32 { self.pain * self.severity }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    pub kind: AttackKind,
+    pub force: f32,
+}
+impl Hit {
+    pub fn new(kind: AttackKind, force: f32) -> Self { Self { kind, force } }
+
+    /// The hit dealt by an attack property, or `None` for properties which are not attacks.
+    pub fn from_property(property: &Property) -> Option<Self> {
+        match *property {
+            Property::Stabbing(value) => Some(Self::new(AttackKind::Stabbing, f32::from(value))),
+            Property::Bashing(value) => Some(Self::new(AttackKind::Bashing, f32::from(value))),
+            Property::Cutting(value) => Some(Self::new(AttackKind::Cutting, f32::from(value))),
+            _ => None,
+        }
+    }
+}
+
+/// Every wound a single hit caused, as `(part index, layer index, wound)`, and the indices of
+/// the parts it cut off.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HitReport {
+    pub wounds: Vec<(usize, usize, Wound)>,
+    pub severed: Vec<usize>,
+}
+impl HitReport {
+    pub fn is_empty(&self) -> bool { self.wounds.is_empty() && self.severed.is_empty() }
+}
+
+/// Resistance of a layer against an attack, from the stats of its material scaled by its
+/// thickness. Missing stats count as no resistance.
+#[allow(clippy::cast_precision_loss)]
+pub fn layer_resistance(
+    layer: &PartLayer,
+    kind: AttackKind,
+    materials: &DefinitionStorage<MaterialDefinition>,
+) -> f32 {
+    let material = &layer.material.material;
+    let state = match materials
+        .find(&material.name)
+        .and_then(|def| def.states.get(&material.state))
+    {
+        Some(state) => state,
+        None => return 0.0,
+    };
+
+    let thickness = 1.0 + layer.material.value as f32 / 10.0;
+    material_resistance(state, kind) * thickness
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn material_resistance(state: &MaterialStateDefinition, kind: AttackKind) -> f32 {
+    let stat = |value: Option<u32>| value.unwrap_or(0) as f32;
+
+    match kind {
+        AttackKind::Stabbing => stat(state.hardness),
+        AttackKind::Cutting => stat(state.hardness) + stat(state.shear_modulus) / 1000.0,
+        AttackKind::Bashing => state.fracture_toughness.map_or_else(
+            || stat(state.compressive_yield_strength) / 10.0,
+            |t| t as f32 * 4.0,
+        ),
+    }
+}
+
+impl BodyComponent {
+    /// Hits the part named `part`. Returns `None` if the body has no such part, or it has already
+    /// been severed.
+    pub fn hit_part<R>(
+        &mut self,
+        part: &str,
+        hit: Hit,
+        def: &BodyDefinition,
+        materials: &DefinitionStorage<MaterialDefinition>,
+        rng: &mut R,
+    ) -> Option<HitReport>
+    where
+        R: Rng + ?Sized,
+    {
+        let graph = def.part_graph.as_ref()?;
+        let index = graph
+            .node_indices()
+            .find(|index| graph[*index].name().eq_ignore_ascii_case(part))?;
+        if self.part_states[index.index()].severed {
+            return None;
+        }
+
+        let mut report = HitReport::default();
+        self.hit_node(index, hit, def, materials, rng, &mut report);
+        Some(report)
+    }
+
+    /// Hits a random outer part, weighted by `relative_size`. Parts inside other parts are only
+    /// reached by damage going through their container.
+    pub fn hit_random<R>(
+        &mut self,
+        hit: Hit,
+        def: &BodyDefinition,
+        materials: &DefinitionStorage<MaterialDefinition>,
+        rng: &mut R,
+    ) -> Option<HitReport>
+    where
+        R: Rng + ?Sized,
+    {
+        let graph = def.part_graph.as_ref()?;
+        let targets = graph
+            .node_indices()
+            .filter(|index| !self.part_states[index.index()].severed)
+            .filter(|index| {
+                !graph
+                    .edges_directed(*index, Direction::Incoming)
+                    .any(|edge| edge.weight().relation.contains(JointRelation::Inside))
+            })
+            .collect::<Vec<_>>();
+
+        let index = pick_weighted(&targets, |index| graph[*index].relative_size, rng)?;
+
+        let mut report = HitReport::default();
+        self.hit_node(index, hit, def, materials, rng, &mut report);
+        Some(report)
+    }
+
+    /// Applies `hit` to every layer of the part from the outside in, then carries whatever force
+    /// is left into one of the parts inside it.
+    fn hit_node<R>(
+        &mut self,
+        index: NodeIndex,
+        hit: Hit,
+        def: &BodyDefinition,
+        materials: &DefinitionStorage<MaterialDefinition>,
+        rng: &mut R,
+        report: &mut HitReport,
+    ) where
+        R: Rng + ?Sized,
+    {
+        let graph = def.part_graph.as_ref().unwrap();
+        let part = &graph[index];
+        let mut force = hit.force;
+
+        for (layer_idx, layer) in part.layers.iter().enumerate() {
+            if force <= 0.0 {
+                return;
+            }
+
+            let resistance = layer_resistance(layer, hit.kind, materials);
+            let severity = if resistance > 0.0 {
+                (force / resistance).min(1.0)
+            } else {
+                1.0
+            };
+            let pierced = force >= resistance;
+
+            let kind = match hit.kind {
+                AttackKind::Stabbing | AttackKind::Cutting if pierced => WoundKind::Cut,
+                AttackKind::Bashing if pierced && layer.flags.contains(PartFlags::Skeleton) => {
+                    WoundKind::Fracture
+                }
+                _ => WoundKind::Bruise,
+            };
+            let wound = Wound::new(kind, severity, layer);
+            self.part_states[index.index()].layer_states[layer_idx]
+                .wounds
+                .push(wound);
+            report.wounds.push((index.index(), layer_idx, wound));
+
+            force = match hit.kind {
+                AttackKind::Bashing => force * 0.75,
+                _ if !pierced => 0.0,
+                AttackKind::Cutting => force - resistance,
+                AttackKind::Stabbing => force - resistance / 2.0,
+            };
+        }
+
+        if force <= 0.0 {
+            return;
+        }
+
+        if hit.kind == AttackKind::Cutting
+            && part
+                .layers
+                .iter()
+                .any(|layer| layer.flags.contains(PartFlags::Limb))
+        {
+            self.sever(index, def, report);
+            return;
+        }
+
+        let inside = graph
+            .edges_directed(index, Direction::Outgoing)
+            .filter(|edge| edge.weight().relation.contains(JointRelation::Inside))
+            .map(|edge| edge.target())
+            .filter(|target| !self.part_states[target.index()].severed)
+            .collect::<Vec<_>>();
+        if let Some(target) = pick_weighted(&inside, |index| graph[*index].relative_size, rng) {
+            self.hit_node(
+                target,
+                Hit::new(hit.kind, force),
+                def,
+                materials,
+                rng,
+                report,
+            );
+        }
+    }
+
+    /// Cuts off a part, along with everything attached to the outside of it or inside it.
+    fn sever(&mut self, index: NodeIndex, def: &BodyDefinition, report: &mut HitReport) {
+        let graph = def.part_graph.as_ref().unwrap();
+
+        let mut pending = vec![index];
+        while let Some(next) = pending.pop() {
+            let state = &mut self.part_states[next.index()];
+            if state.severed {
+                continue;
+            }
+            state.severed = true;
+            report.severed.push(next.index());
+
+            pending.extend(
+                graph
+                    .edges_directed(next, Direction::Incoming)
+                    .filter(|edge| edge.weight().relation.contains(JointRelation::Outside))
+                    .map(|edge| edge.source()),
+            );
+            pending.extend(
+                graph
+                    .edges_directed(next, Direction::Outgoing)
+                    .filter(|edge| edge.weight().relation.contains(JointRelation::Inside))
+                    .map(|edge| edge.target()),
+            );
+        }
+
+        let layers = &graph[index].layers;
+        for (layer, layer_state) in layers
+            .iter()
+            .zip(&mut self.part_states[index.index()].layer_states)
+        {
+            layer_state
+                .wounds
+                .push(Wound::new(WoundKind::Sever, 1.0, layer));
+        }
+    }
+}
+
+fn pick_weighted<T, F, R>(items: &[T], weight: F, rng: &mut R) -> Option<T>
+where
+    T: Copy,
+    F: Fn(&T) -> u32,
+    R: Rng + ?Sized,
+{
+    let total = items
+        .iter()
+        .map(|item| u64::from(weight(item)))
+        .sum::<u64>();
+    if total == 0 {
+        return items.first().copied();
+    }
+
+    let mut roll = rng.gen_range(0, total);
+    for item in items {
+        let weight = u64::from(weight(item));
+        if roll < weight {
+            return Some(*item);
+        }
+        roll -= weight;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::rand::SeedableRng;
+    use core::rand_xorshift::XorShiftRng;
+
+    fn load() -> (
+        DefinitionStorage<BodyDefinition>,
+        DefinitionStorage<MaterialDefinition>,
+    ) {
+        (
+            DefinitionStorage::from_folder("../resources/defs/bodies").unwrap(),
+            DefinitionStorage::from_folder("../resources/defs/materials").unwrap(),
+        )
+    }
+
+    #[test]
+    fn layered_hits() {
+        let (bodies, materials) = load();
+        let id = bodies.get_id("Humanoid").unwrap();
+        let def = bodies.get(id).unwrap();
+        let mut rng = XorShiftRng::seed_from_u64(0);
+
+        // A light blow bruises the skin of an arm without breaking the bone.
+        let mut body = BodyComponent::new(id, &bodies);
+        let hit = Hit::from_property(&Property::Bashing(30)).unwrap();
+        let report = body
+            .hit_part("Right Upper Arm", hit, def, &materials, &mut rng)
+            .unwrap();
+        assert_eq!(report.wounds.len(), 3);
+        assert!(report
+            .wounds
+            .iter()
+            .all(|(_, _, w)| w.kind == WoundKind::Bruise));
+        assert!(report.severed.is_empty());
+
+        // A heavy one breaks it.
+        let report = body
+            .hit_part(
+                "Right Upper Arm",
+                Hit::new(AttackKind::Bashing, 120.0),
+                def,
+                &materials,
+                &mut rng,
+            )
+            .unwrap();
+        assert_eq!(report.wounds[2].2.kind, WoundKind::Fracture);
+
+        // A deep stab into the torso reaches an organ inside it.
+        let report = body
+            .hit_part(
+                "Torso",
+                Hit::new(AttackKind::Stabbing, 200.0),
+                def,
+                &materials,
+                &mut rng,
+            )
+            .unwrap();
+        let graph = def.part_graph.as_ref().unwrap();
+        assert!(report
+            .wounds
+            .iter()
+            .any(|(part, _, _)| graph.raw_nodes()[*part].weight.name() != "Torso"));
+
+        // Cutting clean through an arm takes everything below it too.
+        let report = body
+            .hit_part(
+                "Left Upper Arm",
+                Hit::new(AttackKind::Cutting, 200.0),
+                def,
+                &materials,
+                &mut rng,
+            )
+            .unwrap();
+        let severed = report
+            .severed
+            .iter()
+            .map(|part| graph.raw_nodes()[*part].weight.name())
+            .collect::<Vec<_>>();
+        assert_eq!(severed.len(), 3);
+        assert!(severed.contains(&"Left Hand"));
+        assert!(body
+            .hit_part("Left Hand", hit, def, &materials, &mut rng)
+            .is_none());
+
+        for _ in 0..20 {
+            let report = body.hit_random(hit, def, &materials, &mut rng).unwrap();
+            assert!(!body.part_states[report.wounds[0].0].severed);
+        }
+    }
+}
@@ -11,11 +11,17 @@
 use core::amethyst::ecs::{Component, FlaggedStorage, VecStorage};
 use core::defs::{
     body::{BodyDefinition, Joint, Part, PartLayer},
-    DefinitionComponent, DefinitionStorage,
+    DefinitionComponent, DefinitionStorage, Named,
 };
+use damage::Wound;
+use physiology::Physiology;
+use std::collections::HashMap;
 use survival_derive::DefinitionComponent;
 pub mod bundle;
+pub mod capabilities;
+pub mod damage;
 pub mod inventory;
+pub mod physiology;
 pub mod systems;
 
 pub mod components {
@@ -25,10 +31,23 @@ pub mod components {
 #[derive(Default, Debug)]
 pub struct LayerState {
     pub layer_idx: usize,
+    pub wounds: Vec<Wound>,
 }
 impl LayerState {
     pub fn new(layer_idx: usize, _layer: &PartLayer) -> Self {
-        Self { layer_idx }
+        Self {
+            layer_idx,
+            wounds: Vec::new(),
+        }
+    }
+
+    /// Combined severity of every wound on this layer, capped at 1.0 for a destroyed layer.
+    pub fn damage(&self) -> f32 {
+        self.wounds
+            .iter()
+            .map(|wound| wound.severity)
+            .sum::<f32>()
+            .min(1.0)
     }
 }
 
@@ -36,6 +55,7 @@ impl LayerState {
 pub struct PartState {
     pub node_idx: usize,
     pub layer_states: Vec<LayerState>,
+    pub severed: bool,
 }
 impl PartState {
     pub fn new(node_idx: usize, part: &Part) -> Self {
@@ -47,6 +67,7 @@ impl PartState {
                 .enumerate()
                 .map(|(idx, l)| LayerState::new(idx, l))
                 .collect(),
+            severed: false,
         }
     }
 }
@@ -56,9 +77,7 @@ pub struct JointState {
     pub joint_idx: usize,
 }
 impl JointState {
-    pub fn new(joint_idx: usize, _part: &Joint) -> Self {
-        Self { joint_idx }
-    }
+    pub fn new(joint_idx: usize, _part: &Joint) -> Self { Self { joint_idx } }
 }
 
 #[derive(DefinitionComponent, Debug)]
@@ -66,6 +85,7 @@ impl JointState {
 pub struct BodyComponent {
     pub part_states: Vec<PartState>,
     pub joint_states: Vec<JointState>,
+    pub physiology: Physiology,
     pub def: u32,
 }
 impl Component for BodyComponent {
@@ -95,9 +115,61 @@ impl BodyComponent {
                 .enumerate()
                 .map(|(idx, n)| JointState::new(idx, &n.weight))
                 .collect(),
+            physiology: Physiology::new(definition),
             def: id,
         }
     }
+
+    /// Carries the body's wounds over from `before` to `after`, a new version of its definition,
+    /// so its states line up with the new part graph. Parts and layers are matched by name: new
+    /// ones start out unharmed, and wounds on ones which are gone are dropped.
+    pub fn remap(&mut self, before: &BodyDefinition, after: &BodyDefinition) {
+        let (before_graph, after_graph) = match (&before.part_graph, &after.part_graph) {
+            (Some(before), Some(after)) => (before, after),
+            _ => return,
+        };
+
+        let mut previous = std::mem::replace(&mut self.part_states, Vec::new())
+            .into_iter()
+            .zip(before_graph.raw_nodes())
+            .map(|(state, node)| (node.weight.name(), (state, &node.weight)))
+            .collect::<HashMap<_, _>>();
+
+        self.part_states = after_graph
+            .raw_nodes()
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                let part = &node.weight;
+                let mut state = PartState::new(idx, part);
+                if let Some((old, old_part)) = previous.remove(part.name()) {
+                    state.severed = old.severed;
+                    for (layer_state, layer) in old.layer_states.into_iter().zip(&old_part.layers) {
+                        let matching = part.layers.iter().position(|l| l.name() == layer.name());
+                        if let Some(layer_idx) = matching {
+                            state.layer_states[layer_idx].wounds = layer_state.wounds;
+                        }
+                    }
+                }
+                state
+            })
+            .collect();
+        self.joint_states = after_graph
+            .raw_edges()
+            .iter()
+            .enumerate()
+            .map(|(idx, n)| JointState::new(idx, &n.weight))
+            .collect();
+
+        // Keep the fraction of blood lost, should the body's size change
+        let max_blood = Physiology::new(after).max_blood;
+        if self.physiology.max_blood > 0.0 {
+            self.physiology.blood *= max_blood / self.physiology.max_blood;
+        } else {
+            self.physiology.blood = max_blood;
+        }
+        self.physiology.max_blood = max_blood;
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,213 @@
+use crate::{damage::WoundKind, BodyComponent};
+use core::{
+    components::{HungerState, ThirstState},
+    defs::{body::BodyDefinition, race::Attributes},
+};
+
+/// Fraction of blood lost at which a creature goes into shock, falls unconscious and dies.
+pub const SHOCK_BLOOD_LOSS: f32 = 0.3;
+pub const UNCONSCIOUS_BLOOD_LOSS: f32 = 0.4;
+pub const FATAL_BLOOD_LOSS: f32 = 0.5;
+
+/// Pain at which an average creature goes into shock and falls unconscious.
+pub const SHOCK_PAIN: f32 = 80.0;
+pub const UNCONSCIOUS_PAIN: f32 = 120.0;
+
+/// Fraction of their bleeding a wound stops per second, for an average healer.
+const CLOTTING_RATE: f32 = 0.02;
+/// Bleeding in ml per second below which a wound counts as clotted.
+const CLOTTED: f32 = 0.01;
+/// Seconds for an average healer to fully heal a wound once it stops bleeding.
+const HEALING_TIME: f32 = 3.0 * 86400.0;
+/// Seconds for an average healer to fully replace lost blood.
+const BLOOD_REGEN_TIME: f32 = 2.0 * 86400.0;
+/// Seconds of sickness from eating spoiled food, and the pain it causes meanwhile.
+pub const FOOD_POISONING_TIME: f32 = 86400.0;
+const SICKNESS_PAIN: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Consciousness {
+    Awake,
+    Shock,
+    Unconscious,
+}
+impl Default for Consciousness {
+    fn default() -> Self { Consciousness::Awake }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Physiology {
+    /// Blood in ml.
+    pub blood: f32,
+    pub max_blood: f32,
+    /// Pain felt from every wound, after the creature's tolerance for it.
+    pub pain: f32,
+    pub consciousness: Consciousness,
+    /// Mirrored from the creature's `DigestionComponent`.
+    pub hunger: HungerState,
+    pub thirst: ThirstState,
+    /// Seconds of sickness left, e.g. from eating rotten food.
+    pub sickness: f32,
+}
+impl Physiology {
+    /// Blood volume is taken as 7% of the body's mean mass.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(def: &BodyDefinition) -> Self {
+        let mass = def.mass.map_or(0, |mass| mass.mean);
+        let max_blood = mass as f32 * 0.07;
+        Self {
+            blood: max_blood,
+            max_blood,
+            ..Self::default()
+        }
+    }
+
+    pub fn blood_loss(&self) -> f32 {
+        if self.max_blood > 0.0 {
+            1.0 - self.blood / self.max_blood
+        } else {
+            0.0
+        }
+    }
+
+    pub fn bled_out(&self) -> bool { self.blood_loss() >= FATAL_BLOOD_LOSS }
+
+    pub fn conscious(&self) -> bool { self.consciousness != Consciousness::Unconscious }
+
+    /// Sick, starving or dehydrated creatures are too weak to move at full speed.
+    pub fn weakened(&self) -> bool {
+        self.sickness > 0.0
+            || self.hunger == HungerState::Starving
+            || self.thirst == ThirstState::Dehydrated
+    }
+
+    pub fn sicken(&mut self, time: f32) { self.sickness = self.sickness.max(time); }
+}
+
+impl BodyComponent {
+    /// Whether `tick_physiology` has anything to do: a wound to bleed or heal, or blood to
+    /// replace.
+    pub fn needs_physiology_tick(&self) -> bool {
+        self.physiology.blood < self.physiology.max_blood
+            || self.physiology.pain > 0.0
+            || self.physiology.sickness > 0.0
+            || self.physiology.consciousness != Consciousness::Awake
+            || self
+                .part_states
+                .iter()
+                .flat_map(|part| &part.layer_states)
+                .any(|layer| !layer.wounds.is_empty())
+    }
+
+    /// Advances bleeding, clotting and healing by `elapsed` seconds, then updates pain and
+    /// consciousness. `pain_tolerance` is the creature's `NeedKind::PainTolerance` value.
+    pub fn tick_physiology(&mut self, elapsed: f32, attributes: &Attributes, pain_tolerance: i16) {
+        let healing = f32::from(attributes.healing) / 1000.0;
+        let toughness = f32::from(attributes.toughness) / 1000.0;
+        let clotting = (-CLOTTING_RATE * healing * elapsed).exp();
+
+        let mut bled = 0.0;
+        let mut pain = 0.0;
+        for layer in self
+            .part_states
+            .iter_mut()
+            .flat_map(|part| &mut part.layer_states)
+        {
+            for wound in &mut layer.wounds {
+                if wound.bleeding > 0.0 {
+                    // Blood lost while the bleeding slows from its current rate.
+                    bled += if clotting < 1.0 {
+                        wound.bleeding * (1.0 - clotting) / (CLOTTING_RATE * healing)
+                    } else {
+                        wound.bleeding * elapsed
+                    };
+                    wound.bleeding *= clotting;
+                    if wound.bleeding < CLOTTED {
+                        wound.bleeding = 0.0;
+                    }
+                } else {
+                    let rate = match wound.kind {
+                        WoundKind::Fracture => 0.25,
+                        _ => 1.0,
+                    };
+                    wound.severity -= elapsed * healing * rate / HEALING_TIME;
+                }
+                pain += wound.current_pain().max(0.0);
+            }
+            layer.wounds.retain(|wound| wound.severity > 0.0);
+        }
+
+        let physiology = &mut self.physiology;
+        if physiology.sickness > 0.0 {
+            pain += SICKNESS_PAIN;
+            physiology.sickness = (physiology.sickness - elapsed).max(0.0);
+        }
+
+        physiology.blood = (physiology.blood - bled
+            + physiology.max_blood * healing * elapsed / BLOOD_REGEN_TIME)
+            .max(0.0)
+            .min(physiology.max_blood);
+
+        let tolerance = (1.0 + f32::from(pain_tolerance) / 100.0).max(0.25) * toughness.max(0.1);
+        physiology.pain = pain / tolerance;
+
+        let loss = physiology.blood_loss();
+        physiology.consciousness =
+            if loss >= UNCONSCIOUS_BLOOD_LOSS || physiology.pain >= UNCONSCIOUS_PAIN {
+                Consciousness::Unconscious
+            } else if loss >= SHOCK_BLOOD_LOSS || physiology.pain >= SHOCK_PAIN {
+                Consciousness::Shock
+            } else {
+                Consciousness::Awake
+            };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::damage::{AttackKind, Hit};
+    use core::{
+        defs::{material::MaterialDefinition, DefinitionStorage},
+        rand::SeedableRng,
+        rand_xorshift::XorShiftRng,
+    };
+
+    #[test]
+    fn bleeding_and_healing() {
+        let bodies =
+            DefinitionStorage::<BodyDefinition>::from_folder("../resources/defs/bodies").unwrap();
+        let materials =
+            DefinitionStorage::<MaterialDefinition>::from_folder("../resources/defs/materials")
+                .unwrap();
+        let id = bodies.get_id("Humanoid").unwrap();
+        let def = bodies.get(id).unwrap();
+        let attributes = Attributes::default();
+        let mut rng = XorShiftRng::seed_from_u64(0);
+
+        let mut body = BodyComponent::new(id, &bodies);
+        assert!(!body.needs_physiology_tick());
+
+        let cut = Hit::new(AttackKind::Cutting, 200.0);
+        body.hit_part("Right Upper Arm", cut, def, &materials, &mut rng);
+        body.tick_physiology(1.0, &attributes, 0);
+        assert!(body.physiology.blood < body.physiology.max_blood);
+        assert!(body.physiology.pain > 0.0);
+        assert!(body.physiology.conscious());
+
+        // Losing a second arm is too much blood.
+        body.hit_part("Left Upper Arm", cut, def, &materials, &mut rng);
+        for _ in 0..600 {
+            body.tick_physiology(1.0, &attributes, 0);
+        }
+        assert_eq!(body.physiology.consciousness, Consciousness::Unconscious);
+        assert!(!body.physiology.bled_out());
+
+        // Given time, everything clots and heals.
+        for _ in 0..10 {
+            body.tick_physiology(86400.0, &attributes, 0);
+        }
+        assert!(!body.needs_physiology_tick());
+        assert_eq!(body.physiology.consciousness, Consciousness::Awake);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::components::BodyComponent;
+use core::{
+    amethyst::{
+        derive::SystemDesc,
+        ecs::{Entities, Join, Read, System, SystemData, World, WriteStorage},
+    },
+    clock::{Instant, WorldTime},
+    components::DigestionComponent,
+    defs::{
+        digestion::DigestionDefinition, psyche::NeedKind, DefinitionComponent, DefinitionStorage,
+    },
+};
+use psyche::components::PyscheNeedsComponent;
+
+/// Burns calories and hydration and absorbs what has been eaten as world time passes. Hunger and
+/// thirst are passed on to the body, and going without costs the psyche its sense of `Safety`.
+#[derive(Default, SystemDesc)]
+pub struct DigestionSystem {
+    pub last: Instant,
+}
+impl<'s> System<'s> for DigestionSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, WorldTime>,
+        Read<'s, DefinitionStorage<DigestionDefinition>>,
+        WriteStorage<'s, DigestionComponent>,
+        WriteStorage<'s, BodyComponent>,
+        WriteStorage<'s, PyscheNeedsComponent>,
+    );
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn run(
+        &mut self,
+        (entities, time, defs, mut digestions, mut bodies, mut needs_storage): Self::SystemData,
+    ) {
+        // Skip execution if the game time hasn't progressed
+        if self.last == time.now() {
+            return;
+        }
+        let elapsed = (time.now() - self.last).value() as u32;
+        self.last = time.now();
+
+        for (entity, digestion) in (&entities, &mut digestions).join() {
+            let def = digestion.fetch_def(&defs).unwrap();
+            let tolerance = |kind| {
+                needs_storage
+                    .get(entity)
+                    .map_or(0, |needs| needs.need(kind).value)
+            };
+
+            let digested = digestion.digest(
+                def,
+                elapsed,
+                tolerance(NeedKind::HungerTolerance),
+                tolerance(NeedKind::ThirstTolerance),
+            );
+
+            if digested.changed {
+                if let Some(body) = bodies.get_mut(entity) {
+                    body.physiology.hunger = digestion.hunger();
+                    body.physiology.thirst = digestion.thirst();
+                }
+            }
+
+            if digested.distress > 0 {
+                if let Some(needs) = needs_storage.get_mut(entity) {
+                    let safety = needs.need_mut(NeedKind::Safety);
+                    safety.value = (safety.value - digested.distress).max(safety.decay.minmax.0);
+                }
+            }
+        }
+    }
+}
@@ -12,9 +12,12 @@ use core::{
     rayon::iter::ParallelIterator,
 };
 
+pub mod digestion;
 pub mod item_sprites;
+pub mod physiology;
 
-// Does sanity checks on the inventory
+/// Recomputes the body properties of an entity, like how fast it walks and what it can sense,
+/// from its working parts whenever its `BodyComponent` is modified.
 pub struct BodyUpdatePropertiesSystem {
     body_event_reader_id: ReaderId<ComponentEvent>,
 
@@ -59,38 +62,42 @@ impl<'s> System<'s> for BodyUpdatePropertiesSystem {
 
                 let def = body.fetch_def(&body_defs).unwrap();
 
-                // TOOD: more detailed
-                // For now, we just iterate all part layers and combine all the flags to a single flag
-                let mut flags = PartFlags::default();
-                def.part_graph
-                    .as_ref()
-                    .unwrap()
-                    .raw_nodes()
-                    .iter()
-                    .for_each(|part| {
-                        part.weight.layers.iter().for_each(|layer| {
-                            flags |= layer.flags;
-                        });
-                    });
-
-                if flags.contains(PartFlags::FineMotor) {
-                    props_container.insert(Property::Manipulate(ManipulateFlags::Any));
+                let capabilities = body.capabilities(def);
+                if !capabilities.alive {
+                    props_container.insert(Property::Dead);
+                    return;
                 }
-                if flags.contains(PartFlags::Stance) {
-                    props_container.insert(Property::Movement(MovementFlags::Walk));
+                if !capabilities.conscious {
+                    props_container.insert(Property::Unconscious);
+                    return;
                 }
 
-                // Debug, just add them:
-                props_container.insert(Property::Manipulate(ManipulateFlags::Any));
-                props_container.insert(Property::Movement(MovementFlags::Walk));
-
-                props_container.insert(Property::MovementSpeed(1000));
+                if capabilities.stance > 0.0 {
+                    props_container.insert(Property::Movement(MovementFlags::Walk));
+                    props_container.insert(Property::MovementSpeed(scaled(capabilities.stance)));
+                }
+                if capabilities.fine_motor {
+                    props_container.insert(Property::Manipulate(ManipulateFlags::Any));
+                }
+                if capabilities.sight > 0.0 {
+                    props_container.insert(Property::Sight(scaled(capabilities.sight)));
+                }
+                if capabilities.hearing > 0.0 {
+                    props_container.insert(Property::Hearing(scaled(capabilities.hearing)));
+                }
+                if capabilities.smell > 0.0 {
+                    props_container.insert(Property::Smell(scaled(capabilities.smell)));
+                }
 
                 log::trace!("Final: {:?}", props_container);
             });
     }
 }
 
+/// A capability from 0.0 to 1.0 as a property value out of 1000.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn scaled(value: f32) -> u32 { (value.max(0.0).min(1.0) * 1000.0) as u32 }
+
 #[derive(Default)]
 pub struct BodyUpdatePropertiesSystemDesc;
 impl<'a, 'b> SystemDesc<'a, 'b, BodyUpdatePropertiesSystem> for BodyUpdatePropertiesSystemDesc {
@@ -107,3 +114,41 @@ impl<'a, 'b> SystemDesc<'a, 'b, BodyUpdatePropertiesSystem> for BodyUpdateProper
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{
+        amethyst::ecs::{Builder, RunNow, WorldExt},
+        components::PropertiesMergeResolution,
+        defs::{race::RaceDefinition, HasProperties, Named},
+    };
+
+    #[test]
+    fn refresh_keeps_race_properties() -> Result<(), core::failure::Error> {
+        let races = DefinitionStorage::<RaceDefinition>::from_folder("../resources/defs/races")?;
+        let bodies = DefinitionStorage::<BodyDefinition>::from_folder("../resources/defs/bodies")?;
+        let race = races.find("Human").unwrap();
+        let body_def = bodies.find(&race.body).unwrap();
+        let body = BodyComponent::new(body_def.id().unwrap(), &bodies);
+        let properties = race.default_properties().merge(
+            PropertiesMergeResolution::Error,
+            &body_def.default_properties(),
+        );
+        assert!(properties.contains(PropertyKind::Sociable));
+
+        let mut world = World::new();
+        world.insert(bodies);
+        let mut system = BodyUpdatePropertiesSystemDesc::default().build(&mut world);
+        let pawn = world.create_entity().with(body).with(properties).build();
+        system.run_now(&world);
+
+        let properties = world.read_storage::<PropertiesComponent>();
+        let properties = properties.get(pawn).unwrap();
+        assert!(properties.contains(PropertyKind::Sociable));
+        assert!(properties.contains(PropertyKind::Movement));
+        assert!(properties.contains(PropertyKind::Sight));
+
+        Ok(())
+    }
+}
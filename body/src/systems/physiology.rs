@@ -0,0 +1,61 @@
+use crate::components::BodyComponent;
+use core::{
+    amethyst::{
+        derive::SystemDesc,
+        ecs::{Entities, Join, Read, ReadStorage, System, SystemData, World, WriteStorage},
+    },
+    clock::{Instant, WorldTime},
+    components::AttributesComponent,
+    defs::{psyche::NeedKind, race::Attributes},
+};
+use psyche::components::PyscheNeedsComponent;
+
+/// Bleeds, clots and heals wounded bodies as world time passes. Only bodies with something to
+/// heal are written to, so `BodyUpdatePropertiesSystem` only sees the ones which changed.
+#[derive(Default, SystemDesc)]
+pub struct PhysiologySystem {
+    pub last: Instant,
+}
+impl<'s> System<'s> for PhysiologySystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, WorldTime>,
+        ReadStorage<'s, AttributesComponent>,
+        ReadStorage<'s, PyscheNeedsComponent>,
+        WriteStorage<'s, BodyComponent>,
+    );
+
+    #[allow(clippy::cast_precision_loss)]
+    fn run(
+        &mut self,
+        (entities, time, attributes_storage, needs_storage, mut bodies): Self::SystemData,
+    ) {
+        // Skip execution if the game time hasn't progressed
+        if self.last == time.now() {
+            return;
+        }
+        let elapsed = (time.now() - self.last).value() as f32;
+        self.last = time.now();
+
+        let wounded = (&entities, &bodies)
+            .join()
+            .filter(|(_, body)| body.needs_physiology_tick())
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+
+        let default_attributes = Attributes::default();
+        for entity in wounded {
+            let attributes = attributes_storage
+                .get(entity)
+                .map_or(&default_attributes, |attributes| &**attributes);
+            let pain_tolerance = needs_storage
+                .get(entity)
+                .map_or(0, |needs| needs.need(NeedKind::PainTolerance).value);
+
+            bodies
+                .get_mut(entity)
+                .unwrap()
+                .tick_physiology(elapsed, attributes, pain_tolerance);
+        }
+    }
+}
@@ -5,7 +5,7 @@ pub use crate::defs::{
     item::{ItemDefinition, ItemPart},
     material::*,
     property::{Dimensions, Property, PropertyCategory, PropertyKind},
-    psyche::NeedState,
+    psyche::{NeedDecay, NeedState},
     race::{Attributes, RaceDefinition},
     Definition, DefinitionComponent, DefinitionStorage,
 };
@@ -44,8 +44,39 @@ impl Component for StaticTransform {
     type Storage = VecStorage<Self>;
 }
 
+/// Names a pawn for as long as the game runs and across saves, unlike its `Entity`, whose index
+/// is handed to something else once the pawn is deleted.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct PawnId(pub u64);
+
+/// Hands out a new `PawnId` to every pawn spawned.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct PawnIds {
+    last: u64,
+}
+impl PawnIds {
+    pub fn allocate(&mut self) -> PawnId {
+        self.last += 1;
+        PawnId(self.last)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PawnComponent {
     pub name: String,
+    pub id: PawnId,
 }
 impl Component for PawnComponent {
     type Storage = VecStorage<Self>;
@@ -54,6 +85,7 @@ impl Default for PawnComponent {
     fn default() -> Self {
         Self {
             name: "asdf".to_string(),
+            id: PawnId::default(),
         }
     }
 }
@@ -214,6 +246,10 @@ impl PropertiesComponent {
         self.inner.insert(prop.into(), prop)
     }
 
+    pub fn remove(&mut self, prop: PropertyKind) -> Option<Property> {
+        self.inner.remove(&prop)
+    }
+
     pub fn contains(&self, property: PropertyKind) -> bool {
         self.inner.contains_key(&property)
     }
@@ -226,9 +262,10 @@ impl PropertiesComponent {
         self.inner.iter().map(|(_, p)| p)
     }
 
+    /// Removes every property in `category`. Properties without a category are kept.
     pub fn clear_category(&mut self, category: PropertyCategory) {
         self.inner
-            .retain(|_, p| p.get_str("Category").unwrap() != category.as_ref());
+            .retain(|_, p| p.get_str("Category") != Some(category.as_ref()));
     }
 
     #[allow(clippy::needless_pass_by_value)]
@@ -321,12 +358,24 @@ impl Component for AttributesComponent {
     type Storage = VecStorage<Self>;
 }
 
+/// Volume in mL of food which does not say how much of it there is.
+pub const DEFAULT_EDIBLE_VOLUME: u32 = 250;
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct EdibleComponent {
     state: EdibleState,
     kind: EdibleKind,
     calories: Option<u32>,
     hydration: Option<u32>,
+    #[serde(default)]
+    volume: Option<u32>,
+
+    /// Seconds of room temperature storage the food has been through, and how many it takes to
+    /// rot. Age is fractional, as cold food ages by a fraction of a second each second.
+    #[serde(default)]
+    age: f64,
+    #[serde(default)]
+    spoil_time: Option<u32>,
 }
 impl EdibleComponent {
     pub fn new(kind: EdibleKind) -> Self {
@@ -335,9 +384,26 @@ impl EdibleComponent {
             kind,
             calories: None,
             hydration: None,
+            volume: None,
+            age: 0.0,
+            spoil_time: None,
         }
     }
 
+    /// Food made of `material`, with calories and hydration for `volume` mL of it.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn with_material(mut self, material: &MaterialStateDefinition, volume: u32) -> Self {
+        let per_liter = |amount: Option<u32>| {
+            amount.map(|amount| (u64::from(amount) * u64::from(volume) / 1000) as u32)
+        };
+
+        self.volume = Some(volume);
+        self.calories = per_liter(material.calories);
+        self.hydration = per_liter(material.hydration);
+        self.spoil_time = material.spoil_time;
+        self
+    }
+
     pub fn with_state(mut self, state: EdibleState) -> Self {
         self.state = state;
         self
@@ -352,32 +418,334 @@ impl EdibleComponent {
         self.hydration = Some(hydration);
         self
     }
+
+    pub fn with_volume(mut self, volume: u32) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    pub fn state(&self) -> EdibleState {
+        self.state
+    }
+
+    pub fn kind(&self) -> EdibleKind {
+        self.kind
+    }
+
+    pub fn calories(&self) -> u32 {
+        self.calories.unwrap_or(0)
+    }
+
+    pub fn hydration(&self) -> u32 {
+        self.hydration.unwrap_or(0)
+    }
+
+    pub fn volume(&self) -> u32 {
+        self.volume.unwrap_or(DEFAULT_EDIBLE_VOLUME)
+    }
+
+    /// Ages the food by `elapsed` seconds, sped up or slowed down by `rate`. Returns true if this
+    /// made it rot.
+    pub fn spoil(&mut self, elapsed: u32, rate: f32) -> bool {
+        let spoil_time = match (self.state, self.spoil_time) {
+            (EdibleState::Raw, Some(time)) => time,
+            // Cooking keeps food good for twice as long
+            (EdibleState::Cooked, Some(time)) => time.saturating_mul(2),
+            _ => return false,
+        };
+
+        self.age += f64::from(elapsed) * f64::from(rate.max(0.0));
+        if self.age >= f64::from(spoil_time) {
+            self.state = EdibleState::Rotten;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Turns raw food into `state`, e.g. cooks or burns it. Returns false for food which is not
+    /// raw.
+    pub fn cook(&mut self, state: EdibleState) -> bool {
+        if self.state != EdibleState::Raw {
+            return false;
+        }
+
+        self.state = state;
+        self.age = 0.0;
+        true
+    }
 }
 impl Component for EdibleComponent {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(DefinitionComponent, Default, Debug, Clone, Copy)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum HungerState {
+    Fed,
+    Hungry,
+    Malnourished,
+    Starving,
+}
+impl Default for HungerState {
+    fn default() -> Self {
+        HungerState::Fed
+    }
+}
+impl HungerState {
+    /// The state for a calorie balance, with thresholds stretched by `HungerTolerance`.
+    pub fn from_calories(calories: i16, tolerance: i16) -> Self {
+        let calories = f32::from(calories) / tolerance_scale(tolerance);
+        if calories <= -15000.0 {
+            HungerState::Starving
+        } else if calories <= -5000.0 {
+            HungerState::Malnourished
+        } else if calories <= -500.0 {
+            HungerState::Hungry
+        } else {
+            HungerState::Fed
+        }
+    }
+}
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum ThirstState {
+    Hydrated,
+    Thirsty,
+    Dehydrated,
+}
+impl Default for ThirstState {
+    fn default() -> Self {
+        ThirstState::Hydrated
+    }
+}
+impl ThirstState {
+    /// The state for a hydration balance, with thresholds stretched by `ThirstTolerance`.
+    pub fn from_hydration(hydration: i16, tolerance: i16) -> Self {
+        let hydration = f32::from(hydration) / tolerance_scale(tolerance);
+        if hydration <= -3000.0 {
+            ThirstState::Dehydrated
+        } else if hydration <= -500.0 {
+            ThirstState::Thirsty
+        } else {
+            ThirstState::Hydrated
+        }
+    }
+}
+
+fn tolerance_scale(tolerance: i16) -> f32 {
+    (1.0 + f32::from(tolerance) / 100.0).max(0.25)
+}
+
+/// Food sitting in one digestion part, waiting to be absorbed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StomachContents {
+    pub volume: u32,
+    pub calories: u32,
+    pub hydration: u32,
+}
+
+/// What a call to `DigestionComponent::digest` changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Digested {
+    /// Hunger or thirst moved to a different state.
+    pub changed: bool,
+    /// Points of `Safety` lost to going hungry or thirsty for too long.
+    pub distress: i16,
+}
+
+#[derive(DefinitionComponent, Default, Debug, Clone)]
 #[def(DigestionDefinition)]
 pub struct DigestionComponent {
     pub def: u32,
 
     calories: NeedState,
     hydration: NeedState,
+
+    /// Contents of each of the definition's `digestion_parts`, in the same order.
+    contents: Vec<StomachContents>,
+    hunger: HungerState,
+    thirst: ThirstState,
+    distress_acc: u32,
 }
 impl DigestionComponent {
-    pub fn new(id: u32, _: &DefinitionStorage<DigestionDefinition>) -> Self {
+    pub fn new(id: u32, storage: &DefinitionStorage<DigestionDefinition>) -> Self {
+        let def = storage.get(id).unwrap();
+        let need = |decay: Option<NeedDecay>| NeedState {
+            decay: decay.unwrap_or(NeedDecay {
+                value: 0,
+                ..NeedDecay::default()
+            }),
+            ..NeedState::default()
+        };
+
         Self {
             def: id,
-            calories: NeedState::default(),
-            hydration: NeedState::default(),
+            calories: need(def.calorie_burn_rate),
+            hydration: need(def.hydration_burn_rate),
+            contents: vec![StomachContents::default(); def.digestion_parts.len()],
+            hunger: HungerState::default(),
+            thirst: ThirstState::default(),
+            distress_acc: 0,
         }
     }
+
+    pub fn calories(&self) -> &NeedState {
+        &self.calories
+    }
+
+    pub fn hydration(&self) -> &NeedState {
+        &self.hydration
+    }
+
+    pub fn contents(&self) -> &[StomachContents] {
+        &self.contents
+    }
+
+    pub fn hunger(&self) -> HungerState {
+        self.hunger
+    }
+
+    pub fn thirst(&self) -> ThirstState {
+        self.thirst
+    }
+
+    /// Whether this creature eats food like `edible`, and has room for it.
+    pub fn can_ingest(&self, def: &DigestionDefinition, edible: &EdibleComponent) -> bool {
+        def.can_eat(edible.state(), edible.kind())
+            && self.free_part(def, edible.volume()).is_some()
+    }
+
+    /// Swallows `edible` into the first digestion part with room for it. Returns false, leaving
+    /// the food uneaten, if it can't be eaten.
+    pub fn ingest(&mut self, def: &DigestionDefinition, edible: &EdibleComponent) -> bool {
+        if !def.can_eat(edible.state(), edible.kind()) {
+            return false;
+        }
+
+        if let Some(index) = self.free_part(def, edible.volume()) {
+            // Cooked food gives up more of its calories, and spoiled food less
+            let percent = match edible.state() {
+                EdibleState::Cooked => 120,
+                EdibleState::Burnt => 75,
+                EdibleState::Rotten => 50,
+                _ => 100,
+            };
+
+            let contents = &mut self.contents[index];
+            contents.volume += edible.volume();
+            contents.calories += edible.calories() * percent / 100;
+            contents.hydration += edible.hydration();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn free_part(&self, def: &DigestionDefinition, volume: u32) -> Option<usize> {
+        def.digestion_parts
+            .iter()
+            .zip(&self.contents)
+            .position(|(part, contents)| contents.volume + volume <= part.capacity)
+    }
+
+    /// Burns calories and hydration for `elapsed` seconds, while absorbing whatever drains out
+    /// of each digestion part.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn digest(
+        &mut self,
+        def: &DigestionDefinition,
+        elapsed: u32,
+        hunger_tolerance: i16,
+        thirst_tolerance: i16,
+    ) -> Digested {
+        burn(&mut self.calories, elapsed);
+        burn(&mut self.hydration, elapsed);
+
+        for (part, contents) in def.digestion_parts.iter().zip(&mut self.contents) {
+            if contents.volume == 0 || part.capacity_decay.time == 0 {
+                continue;
+            }
+
+            let rate = u64::from(part.capacity_decay.value.abs() as u16);
+            let drained = (rate * u64::from(elapsed) / u64::from(part.capacity_decay.time))
+                .min(u64::from(contents.volume)) as u32;
+            if drained == 0 {
+                continue;
+            }
+
+            let share = |amount: u32| {
+                (u64::from(amount) * u64::from(drained) / u64::from(contents.volume)) as u32
+            };
+            let calories = share(contents.calories);
+            let hydration = share(contents.hydration);
+            contents.volume -= drained;
+            contents.calories -= calories;
+            contents.hydration -= hydration;
+
+            absorb(&mut self.calories, calories * part.calorie_efficiency / 100);
+            absorb(&mut self.hydration, hydration * part.hydration_effiency / 100);
+        }
+
+        let hunger = HungerState::from_calories(self.calories.value, hunger_tolerance);
+        let thirst = ThirstState::from_hydration(self.hydration.value, thirst_tolerance);
+        let changed = hunger != self.hunger || thirst != self.thirst;
+        self.hunger = hunger;
+        self.thirst = thirst;
+
+        // An hour spent malnourished, starving or dehydrated costs a point of safety each.
+        let severity = match hunger {
+            HungerState::Starving => 2,
+            HungerState::Malnourished => 1,
+            _ => 0,
+        } + match thirst {
+            ThirstState::Dehydrated => 2,
+            _ => 0,
+        };
+        let mut distress = 0;
+        if severity > 0 {
+            self.distress_acc += elapsed;
+            while self.distress_acc >= 3600 {
+                distress += severity;
+                self.distress_acc -= 3600;
+            }
+        } else {
+            self.distress_acc = 0;
+        }
+
+        Digested { changed, distress }
+    }
 }
 impl Component for DigestionComponent {
     type Storage = FlaggedStorage<Self, VecStorage<Self>>;
 }
 
+/// Applies a need's decay for `elapsed` seconds, clamping to its range rather than stopping.
+fn burn(need: &mut NeedState, elapsed: u32) {
+    if need.decay.value == 0 || need.decay.time == 0 {
+        return;
+    }
+
+    need.acc += elapsed;
+    while need.acc >= need.decay.time {
+        need.value = need
+            .value
+            .saturating_add(need.decay.value)
+            .max(need.decay.minmax.0)
+            .min(need.decay.minmax.1);
+        need.acc -= need.decay.time;
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+fn absorb(need: &mut NeedState, amount: u32) {
+    let amount = amount.min(i16::max_value() as u32) as i16;
+    need.value = need.value.saturating_add(amount).min(need.decay.minmax.1);
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum PawnType {
     Player,
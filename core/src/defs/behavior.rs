@@ -0,0 +1,172 @@
+use crate::defs::{property::Property, psyche::NeedKind, Definition, Named};
+use survival_derive::NamedDefinition;
+
+/// The raw value a consideration scores, before it is shaped by the consideration's curve.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ConsiderationInput {
+    /// Current value of one of the entity's needs.
+    Need(NeedKind),
+    /// Calories the entity is short of, rising as its stomach empties.
+    Hunger,
+    /// Hydration the entity is short of, rising as it dries out.
+    Thirst,
+    /// The entity's mood, from -100 for miserable to 100 for elated.
+    Mood,
+    /// How bad a breakdown the entity is having, from 0.0 for none to 3.0 for extreme.
+    Breakdown,
+    /// Seconds since the entity last had something to do.
+    IdleTime,
+    /// Distance in tiles to the nearest entity with the given property.
+    Distance(Property),
+    /// 1.0 if the entity itself has the given property, otherwise 0.0.
+    HasProperty(Property),
+    /// A fixed value, for decisions which should always be available.
+    Constant(f32),
+}
+
+/// Response curves, mirroring `iaus::curves`. Inputs are normalized over `range` before the curve
+/// is applied.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum CurveDefinition {
+    Linear {
+        range: (f32, f32),
+        slope: f32,
+        intercept: f32,
+    },
+    Exponential {
+        range: (f32, f32),
+        power: f32,
+    },
+    ExponentialDecay {
+        range: (f32, f32),
+        magnitude: f32,
+    },
+    Logistic {
+        range: (f32, f32),
+        steepness: f32,
+        #[serde(default = "CurveDefinition::default_midpoint")]
+        midpoint: f32,
+    },
+    Logit {
+        range: (f32, f32),
+        steepness: f32,
+    },
+    SmoothStep {
+        range: (f32, f32),
+    },
+    Inverse {
+        range: (f32, f32),
+        magnitude: f32,
+    },
+    Step {
+        range: (f32, f32),
+        threshold: f32,
+        #[serde(default)]
+        low: f32,
+        #[serde(default = "CurveDefinition::default_high")]
+        high: f32,
+    },
+    /// Control points as `(x, y)` over the normalized input.
+    PiecewiseLinear {
+        range: (f32, f32),
+        points: Vec<(f32, f32)>,
+    },
+}
+impl CurveDefinition {
+    fn default_midpoint() -> f32 { 0.5 }
+
+    fn default_high() -> f32 { 1.0 }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ConsiderationDefinition {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    pub input: ConsiderationInput,
+
+    pub curve: CurveDefinition,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DecisionDefinition {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The action made the entity's goal when this decision is chosen.
+    #[serde(default)]
+    pub action: Option<String>,
+
+    #[serde(default = "DecisionDefinition::default_base")]
+    pub base: f32,
+
+    /// Seconds after being replaced before this decision can be chosen again.
+    #[serde(default)]
+    pub cooldown: f32,
+
+    pub considerations: Vec<ConsiderationDefinition>,
+}
+impl DecisionDefinition {
+    fn default_base() -> f32 { 1.0 }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BucketDefinition {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Higher priority buckets are considered first.
+    pub priority: u32,
+
+    pub decisions: Vec<DecisionDefinition>,
+}
+
+#[derive(
+    NamedDefinition, Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize,
+)]
+pub struct BehaviorDefinition {
+    name: String,
+
+    #[serde(skip)]
+    id: Option<u32>,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Bonus applied to the score of the current decision, as a fraction of it, so another
+    /// decision in the same bucket has to clearly beat it before the entity switches.
+    #[serde(default = "BehaviorDefinition::default_momentum")]
+    pub momentum: f32,
+
+    pub buckets: Vec<BucketDefinition>,
+}
+impl BehaviorDefinition {
+    fn default_momentum() -> f32 { 0.25 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defs::DefinitionStorage;
+
+    #[test]
+    fn load_behaviors() -> Result<(), failure::Error> {
+        let storage =
+            DefinitionStorage::<BehaviorDefinition>::from_folder("../resources/defs/behaviors")?;
+
+        let cow = storage.find("Cow Root").unwrap();
+        assert!(!cow.buckets.is_empty());
+        assert!(cow.buckets.iter().all(|bucket| bucket
+            .decisions
+            .iter()
+            .all(|d| !d.considerations.is_empty())));
+
+        Ok(())
+    }
+}
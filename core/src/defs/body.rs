@@ -3,12 +3,12 @@ use crate::{
     components::PropertiesComponent,
     defs::{
         material::MaterialLayerRef,
-        property::{Average, Dimensions, Property},
+        property::{Average, Dimensions, Property, PropertyKind},
         sprites::SpriteRef,
         Definition, HasProperties, InheritDefinition, Named,
     },
 };
-use survival_derive::NamedDefinition;
+use survival_derive::{InheritDefinition, NamedDefinition};
 
 use bitflags::*;
 use petgraph;
@@ -104,11 +104,12 @@ pub struct Joint {
     pub relative_size: u32,
 }
 
-#[derive(NamedDefinition, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[derive(NamedDefinition, InheritDefinition, Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct BodyDefinition {
     name: String,
 
     #[serde(default)]
+    #[inherit(parent)]
     inherits: Option<String>,
 
     #[serde(default)]
@@ -130,38 +131,29 @@ pub struct BodyDefinition {
     pub digestion: Option<String>,
 
     #[serde(default)]
+    #[inherit(with = "Self::inherit_properties")]
     pub properties: Vec<Property>,
+
+    /// Inherited properties this body drops. Properties the body lists itself always replace
+    /// inherited ones of the same kind.
+    #[serde(default)]
+    pub remove_properties: Vec<PropertyKind>,
 }
 
 pub fn default_digestion() -> Option<String> {
     Some("default".to_string())
 }
 
-impl InheritDefinition for BodyDefinition {
-    fn parent(&self) -> Option<&str> {
-        self.inherits.as_ref().map(String::as_str)
-    }
-
-    fn inherit_from(&mut self, parent: &Self) {
-        if self.part_graph.is_none() {
-            self.part_graph = parent.part_graph.clone();
-        }
-
-        if self.digestion.is_none() {
-            self.digestion = parent.digestion.clone();
-        }
-
-        self.mass = self.mass.map_or(parent.mass, Some);
-        self.dimensions = self.dimensions.map_or(parent.dimensions, Some);
-
-        if self.sprite.is_none() {
-            self.sprite = parent.sprite.clone();
-        }
-
-        parent
+impl BodyDefinition {
+    fn inherit_properties(&mut self, parent: &Self) {
+        let removed = &self.remove_properties;
+        let inherited = parent
             .properties
             .iter()
-            .for_each(|p| self.properties.push(p.clone()));
+            .filter(|p| !self.properties.contains(p) && !removed.contains(&PropertyKind::from(*p)))
+            .copied()
+            .collect::<Vec<_>>();
+        self.properties.extend(inherited);
     }
 }
 
@@ -176,6 +168,7 @@ impl Default for BodyDefinition {
             mass: Some(Average::default()),
             dimensions: Some(Average::default()),
             properties: Vec::new(),
+            remove_properties: Vec::new(),
             sprite: Some(SpriteRef::default()),
         }
     }
@@ -2,7 +2,7 @@ use crate::bitflags_serial;
 use crate::{
     components::PropertiesComponent,
     defs::{
-        property::{Dimensions, Property},
+        property::{Dimensions, MovementCosts, Property},
         sprites::SpriteRef,
         Definition, HasProperties, Named,
     },
@@ -37,6 +37,10 @@ pub struct BuildingDefinition {
 
     #[serde(default)]
     pub properties: Vec<Property>,
+
+    /// Buildings are impassable unless they declare a cost for a movement mode.
+    #[serde(default)]
+    pub movement_costs: MovementCosts,
 }
 
 impl HasProperties for BuildingDefinition {
@@ -0,0 +1,362 @@
+//! Every definition storage compiled into a single binary file, so startup doesn't have to parse
+//! and inherit each RON file again. A bundle records a hash of the mods it was built from and is
+//! only used while that hash still matches.
+
+use crate::defs::{
+    action::ActionDefinition,
+    behavior::BehaviorDefinition,
+    body::BodyDefinition,
+    building::BuildingDefinition,
+    creature::CreatureDefinition,
+    digestion::DigestionDefinition,
+    foliage::FoliageDefinition,
+    item::ItemDefinition,
+    material::MaterialDefinition,
+    mods::ModDirectory,
+    psyche::{PsycheTraitDefinition, ThoughtDefinition},
+    race::RaceDefinition,
+    reaction::ReactionDefinition,
+    validation, Definition, DefinitionLayer, DefinitionStorage, InheritDefinitionStorage,
+};
+use amethyst::ecs::{World, WorldExt};
+use hibitset::BitSetLike;
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// Bumped whenever the bundle layout or the serialized form of a definition changes, so bundles
+/// written by an older build are ignored rather than misread.
+pub const BUNDLE_VERSION: u32 = 2;
+
+/// Where the bundle is written by default, next to the base game's definitions.
+pub const DEFAULT_BUNDLE_PATH: &str = "resources/defs.bundle";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct BundledStorage<T> {
+    source: PathBuf,
+    layers: Vec<DefinitionLayer>,
+    definitions: Vec<T>,
+    origins: HashMap<u32, PathBuf>,
+    mods: HashMap<u32, String>,
+}
+
+/// Definition storages after inheritance and validation, keyed by the folder they load from.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DefinitionBundle {
+    pub version: u32,
+    /// `source_hash` of the mods the bundle was built from.
+    pub hash: u64,
+    storages: BTreeMap<String, serde_cbor::Value>,
+}
+impl DefinitionBundle {
+    /// Loads, inherits and validates every definition in `mods` and bundles the result.
+    pub fn build(mods: &[ModDirectory]) -> Result<Self, failure::Error> {
+        let mut bundle = Self {
+            version: BUNDLE_VERSION,
+            hash: source_hash(mods)?,
+            storages: BTreeMap::new(),
+        };
+
+        let mut world = World::new();
+        load_definitions(&mut world, mods)?;
+        validation::validate(&world)?;
+
+        bundle.store::<MaterialDefinition>(&world, "materials")?;
+        bundle.store::<BodyDefinition>(&world, "bodies")?;
+        bundle.store::<DigestionDefinition>(&world, "digestion")?;
+        bundle.store::<RaceDefinition>(&world, "races")?;
+        bundle.store::<BehaviorDefinition>(&world, "behaviors")?;
+        bundle.store::<CreatureDefinition>(&world, "creatures")?;
+        bundle.store::<ActionDefinition>(&world, "actions")?;
+        bundle.store::<ReactionDefinition>(&world, "reactions")?;
+        bundle.store::<BuildingDefinition>(&world, "buildings")?;
+        bundle.store::<FoliageDefinition>(&world, "foliage")?;
+        bundle.store::<ItemDefinition>(&world, "items")?;
+        bundle.store::<PsycheTraitDefinition>(&world, "psyche")?;
+        bundle.store::<ThoughtDefinition>(&world, "thoughts")?;
+
+        Ok(bundle)
+    }
+
+    /// Inserts every bundled storage into the world, replacing any already there.
+    pub fn insert_into(&self, world: &mut World) -> Result<(), failure::Error> {
+        self.restore::<MaterialDefinition>(world, "materials")?;
+        self.restore::<BodyDefinition>(world, "bodies")?;
+        self.restore::<DigestionDefinition>(world, "digestion")?;
+        self.restore::<RaceDefinition>(world, "races")?;
+        self.restore::<BehaviorDefinition>(world, "behaviors")?;
+        self.restore::<CreatureDefinition>(world, "creatures")?;
+        self.restore::<ActionDefinition>(world, "actions")?;
+        self.restore::<ReactionDefinition>(world, "reactions")?;
+        self.restore::<BuildingDefinition>(world, "buildings")?;
+        self.restore::<FoliageDefinition>(world, "foliage")?;
+        self.restore::<ItemDefinition>(world, "items")?;
+        self.restore::<PsycheTraitDefinition>(world, "psyche")?;
+        self.restore::<ThoughtDefinition>(world, "thoughts")?;
+        Ok(())
+    }
+
+    /// Adds a storage to the bundle. Only live definitions are kept, so ids are compacted.
+    pub fn insert<T>(
+        &mut self,
+        kind: &str,
+        storage: &DefinitionStorage<T>,
+    ) -> Result<(), failure::Error>
+    where
+        T: std::fmt::Debug + Clone + Definition + for<'a> serde::Deserialize<'a> + serde::Serialize,
+    {
+        let mut bundled = BundledStorage {
+            source: storage.source.clone(),
+            layers: storage.layers.clone(),
+            definitions: Vec::with_capacity(storage.len()),
+            origins: HashMap::new(),
+            mods: HashMap::new(),
+        };
+        for id in (&storage.bitset).iter() {
+            let bundled_id = bundled.definitions.len() as u32;
+            if let Some(origin) = storage.origins.get(&id) {
+                bundled.origins.insert(bundled_id, origin.clone());
+            }
+            if let Some(source_mod) = storage.mods.get(&id) {
+                bundled.mods.insert(bundled_id, source_mod.clone());
+            }
+            bundled
+                .definitions
+                .push(storage.storage[id as usize].clone());
+        }
+
+        self.storages
+            .insert(kind.to_string(), serde_cbor::value::to_value(&bundled)?);
+        Ok(())
+    }
+
+    pub fn extract<T>(&self, kind: &str) -> Result<DefinitionStorage<T>, failure::Error>
+    where
+        T: std::fmt::Debug + Clone + Definition + for<'a> serde::Deserialize<'a> + serde::Serialize,
+    {
+        let value = self
+            .storages
+            .get(kind)
+            .ok_or_else(|| failure::format_err!("Definitions bundle has no '{}' storage", kind))?;
+        let bundled: BundledStorage<T> = serde_cbor::value::from_value(value.clone())?;
+
+        let mut storage = DefinitionStorage::new(&bundled.source);
+        for def in bundled.definitions {
+            storage.insert(def);
+        }
+        storage.layers = bundled.layers;
+        storage.origins = bundled.origins;
+        storage.mods = bundled.mods;
+        Ok(storage)
+    }
+
+    pub fn write<P>(&self, path: P) -> Result<(), failure::Error>
+    where
+        P: AsRef<Path>,
+    {
+        std::fs::write(path, serde_cbor::to_vec(self)?)?;
+        Ok(())
+    }
+
+    pub fn read<P>(path: P) -> Result<Self, failure::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let bundle: Self = serde_cbor::from_slice(&std::fs::read(path)?)?;
+        if bundle.version != BUNDLE_VERSION {
+            failure::bail!(
+                "bundle version {} does not match {}",
+                bundle.version,
+                BUNDLE_VERSION
+            );
+        }
+        Ok(bundle)
+    }
+
+    /// Reads the bundle at `path` if it exists and was built from `mods` as they currently are.
+    /// Anything else means the caller should fall back to loading RON.
+    pub fn load_current<P>(path: P, mods: &[ModDirectory]) -> Option<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let bundle = match Self::read(path) {
+            Ok(bundle) => bundle,
+            Err(e) => {
+                log::info!("Not using definitions bundle {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        match source_hash(mods) {
+            Ok(hash) if hash == bundle.hash => Some(bundle),
+            Ok(_) => {
+                log::info!("Definitions bundle {} is stale", path.display());
+                None
+            }
+            Err(e) => {
+                log::warn!("Failed to hash definitions: {}", e);
+                None
+            }
+        }
+    }
+
+    fn store<T>(&mut self, world: &World, kind: &str) -> Result<(), failure::Error>
+    where
+        T: std::fmt::Debug
+            + Clone
+            + Definition
+            + for<'a> serde::Deserialize<'a>
+            + serde::Serialize
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.insert(kind, &world.fetch::<DefinitionStorage<T>>())
+    }
+
+    fn restore<T>(&self, world: &mut World, kind: &str) -> Result<(), failure::Error>
+    where
+        T: std::fmt::Debug
+            + Clone
+            + Definition
+            + for<'a> serde::Deserialize<'a>
+            + serde::Serialize
+            + Send
+            + Sync
+            + 'static,
+    {
+        world.insert(self.extract::<T>(kind)?);
+        Ok(())
+    }
+}
+
+/// Loads every definition storage from the RON files of each mod in turn and resolves
+/// inheritance.
+pub fn load_definitions(world: &mut World, mods: &[ModDirectory]) -> Result<(), failure::Error> {
+    let mut storage = DefinitionStorage::<MaterialDefinition>::from_mods(mods, "materials")?;
+    storage.apply_inherits()?;
+    world.insert(storage);
+
+    let mut storage = DefinitionStorage::<BodyDefinition>::from_mods(mods, "bodies")?;
+    storage.apply_inherits()?;
+    world.insert(storage);
+
+    let mut storage = DefinitionStorage::<DigestionDefinition>::from_mods(mods, "digestion")?;
+    storage.apply_inherits()?;
+    world.insert(storage);
+
+    world.insert(DefinitionStorage::<RaceDefinition>::from_mods(
+        mods, "races",
+    )?);
+    world.insert(DefinitionStorage::<BehaviorDefinition>::from_mods(
+        mods,
+        "behaviors",
+    )?);
+    world.insert(DefinitionStorage::<CreatureDefinition>::from_mods(
+        mods,
+        "creatures",
+    )?);
+    world.insert(DefinitionStorage::<ActionDefinition>::from_mods(
+        mods, "actions",
+    )?);
+    world.insert(DefinitionStorage::<ReactionDefinition>::from_mods(
+        mods,
+        "reactions",
+    )?);
+    world.insert(DefinitionStorage::<BuildingDefinition>::from_mods(
+        mods,
+        "buildings",
+    )?);
+    world.insert(DefinitionStorage::<FoliageDefinition>::from_mods(
+        mods, "foliage",
+    )?);
+    world.insert(DefinitionStorage::<ItemDefinition>::from_mods(
+        mods, "items",
+    )?);
+    world.insert(DefinitionStorage::<PsycheTraitDefinition>::from_mods(
+        mods, "psyche",
+    )?);
+    world.insert(DefinitionStorage::<ThoughtDefinition>::from_mods(
+        mods, "thoughts",
+    )?);
+
+    Ok(())
+}
+
+/// Hashes each mod's name and the path and content of every file in it, in a stable order, so
+/// adding, removing, reordering or editing anything changes the result.
+pub fn source_hash(mods: &[ModDirectory]) -> Result<u64, failure::Error> {
+    let mut hasher = fnv::FnvHasher::default();
+
+    for m in mods {
+        hasher.write(m.name().as_bytes());
+        hasher.write_u8(0);
+
+        let files = WalkDir::new(&m.path).sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        for entry in files {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            for component in entry.path().strip_prefix(&m.path)?.components() {
+                hasher.write(component.as_os_str().to_string_lossy().as_bytes());
+                hasher.write_u8(b'/');
+            }
+            let content = std::fs::read(entry.path())?;
+            hasher.write_u64(content.len() as u64);
+            hasher.write(&content);
+        }
+    }
+
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defs::Named;
+
+    #[test]
+    fn bundle_round_trip() -> Result<(), failure::Error> {
+        let path = std::env::temp_dir().join(format!("defs_bundle_{}.bundle", std::process::id()));
+
+        let mods = vec![ModDirectory::base("../resources/defs")];
+
+        let bundle = DefinitionBundle::build(&mods)?;
+        bundle.write(&path)?;
+        let bundle = DefinitionBundle::load_current(&path, &mods).unwrap();
+
+        let mut loaded = World::new();
+        load_definitions(&mut loaded, &mods)?;
+        let mut bundled = World::new();
+        bundle.insert_into(&mut bundled)?;
+
+        let loaded = loaded.fetch::<DefinitionStorage<BodyDefinition>>();
+        let bundled = bundled.fetch::<DefinitionStorage<BodyDefinition>>();
+        assert_eq!(loaded.len(), bundled.len());
+        for def in loaded.iter() {
+            let other = bundled.find(def.name()).unwrap();
+            assert_eq!(def.id(), other.id());
+            assert_eq!(ron::ser::to_string(def)?, ron::ser::to_string(other)?);
+            assert_eq!(
+                loaded.origin(def.id().unwrap()),
+                bundled.origin(other.id().unwrap())
+            );
+            assert_eq!(bundled.source_mod(other.id().unwrap()), Some("base"));
+        }
+
+        // A bundle built from other sources is ignored
+        let mut stale = DefinitionBundle::read(&path)?;
+        stale.hash ^= 1;
+        stale.write(&path)?;
+        assert!(DefinitionBundle::load_current(&path, &mods).is_none());
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
     components::PropertiesComponent,
-    defs::{property::Property, sprites::SpriteRef, Definition, HasProperties, Named},
+    defs::{
+        behavior::BehaviorDefinition, property::Property, sprites::SpriteRef, Definition,
+        DefinitionStorage, HasProperties, Named,
+    },
 };
 use survival_derive::NamedDefinition;
 
@@ -19,6 +22,14 @@ pub struct CreatureDefinition {
 
     pub properties: Vec<Property>,
 }
+impl CreatureDefinition {
+    pub fn behavior_definition<'a>(
+        &self,
+        behaviors: &'a DefinitionStorage<BehaviorDefinition>,
+    ) -> Option<&'a BehaviorDefinition> {
+        self.behavior.as_ref().and_then(|name| behaviors.find(name))
+    }
+}
 impl HasProperties for CreatureDefinition {
     fn default_properties(&self) -> PropertiesComponent {
         PropertiesComponent::from_iter_ref(self.properties.iter())
@@ -4,7 +4,7 @@ use crate::defs::{
 };
 use smallvec::SmallVec;
 use strum_macros::AsRefStr;
-use survival_derive::NamedDefinition;
+use survival_derive::{InheritDefinition, NamedDefinition};
 
 #[derive(
     Debug, Clone, Copy, Hash, PartialEq, Eq, serde::Deserialize, serde::Serialize, AsRefStr,
@@ -34,56 +34,80 @@ pub enum EdibleKind {
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub struct DigestionPart {
-    body_part_name: String,
+    pub body_part_name: String,
 
-    capacity: u32,
-    capacity_decay: NeedDecay,
+    /// Volume this part holds, in mL.
+    pub capacity: u32,
+    /// How quickly the contents of this part are digested and absorbed.
+    pub capacity_decay: NeedDecay,
 
-    calorie_efficiency: u32,
-    hydration_effiency: u32,
+    /// Percentage of the calories and hydration in food which are absorbed as it is digested.
+    pub calorie_efficiency: u32,
+    pub hydration_effiency: u32,
 }
 
-#[derive(NamedDefinition, Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[derive(
+    NamedDefinition, InheritDefinition, Default, Debug, Clone, serde::Deserialize, serde::Serialize,
+)]
 pub struct DigestionDefinition {
     name: String,
 
+    #[inherit(parent)]
     inherits: Option<String>,
 
     #[serde(skip)]
     id: Option<u32>,
 
-    can_eat: Option<SmallVec<[(EdibleState, EdibleKind); 9]>>,
+    pub can_eat: Option<SmallVec<[(EdibleState, EdibleKind); 9]>>,
+
+    #[serde(default)]
+    #[inherit(with = "Self::inherit_parts")]
+    pub digestion_parts: Vec<DigestionPart>,
 
+    /// Body parts whose inherited digestion parts are dropped. Parts listed by this definition
+    /// always replace inherited parts on the same body part.
     #[serde(default)]
-    digestion_parts: Vec<DigestionPart>,
+    remove_parts: Vec<String>,
 
     #[serde(default)]
-    calorie_burn_rate: Option<NeedDecay>,
+    pub calorie_burn_rate: Option<NeedDecay>,
 
     #[serde(default)]
-    hydration_burn_rate: Option<NeedDecay>,
+    pub hydration_burn_rate: Option<NeedDecay>,
 }
 
-impl InheritDefinition for DigestionDefinition {
-    fn inherit_from(&mut self, parent: &Self) {
-        if self.can_eat.is_none() {
-            self.can_eat = parent.can_eat.clone();
-        }
-        self.calorie_burn_rate = self
-            .calorie_burn_rate
-            .map_or(parent.calorie_burn_rate, Some);
-        self.hydration_burn_rate = self
-            .hydration_burn_rate
-            .map_or(parent.hydration_burn_rate, Some);
-
-        parent
-            .digestion_parts
-            .iter()
-            .for_each(|p| self.digestion_parts.push(p.clone()));
+impl DigestionDefinition {
+    /// Whether food of the given state and kind matches any entry of `can_eat`. `Any` matches
+    /// every state or kind, and `Foliage(None)` matches every kind of foliage.
+    pub fn can_eat(&self, state: EdibleState, kind: EdibleKind) -> bool {
+        self.can_eat.as_ref().map_or(false, |can_eat| {
+            can_eat.iter().any(|(eat_state, eat_kind)| {
+                let state_matches = *eat_state == EdibleState::Any || *eat_state == state;
+                let kind_matches = match (eat_kind, kind) {
+                    (EdibleKind::Any, _) | (EdibleKind::Foliage(None), EdibleKind::Foliage(_)) => {
+                        true
+                    }
+                    (eat_kind, kind) => *eat_kind == kind,
+                };
+                state_matches && kind_matches
+            })
+        })
     }
 
-    fn parent(&self) -> Option<&str> {
-        self.inherits.as_ref().map(String::as_str)
+    fn inherit_parts(&mut self, parent: &Self) {
+        let inherited = parent
+            .digestion_parts
+            .iter()
+            .filter(|p| {
+                !self.remove_parts.contains(&p.body_part_name)
+                    && !self
+                        .digestion_parts
+                        .iter()
+                        .any(|own| own.body_part_name == p.body_part_name)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        self.digestion_parts.extend(inherited);
     }
 }
 
@@ -112,6 +136,97 @@ mod tests {
         println!("{}", serialized);
     }
 
+    #[test]
+    fn can_eat_matches() -> Result<(), failure::Error> {
+        let defs: Vec<DigestionDefinition> = ron::de::from_str(
+            r#"[(
+                name: "herbivore",
+                can_eat: Some([(Raw, Foliage(None)), (Any, Liquid)]),
+            )]"#,
+        )?;
+        let herbivore = &defs[0];
+
+        let tree = EdibleKind::Foliage(Some(FoliageCategory::Tree));
+        assert!(herbivore.can_eat(EdibleState::Raw, tree));
+        assert!(herbivore.can_eat(EdibleState::Cooked, EdibleKind::Liquid));
+        assert!(!herbivore.can_eat(EdibleState::Cooked, EdibleKind::Foliage(None)));
+        assert!(!herbivore.can_eat(EdibleState::Raw, EdibleKind::Meat(0)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn digest_food() -> Result<(), failure::Error> {
+        use crate::components::{DigestionComponent, EdibleComponent, HungerState, ThirstState};
+        use crate::defs::DefinitionStorage;
+
+        let defs =
+            DefinitionStorage::<DigestionDefinition>::from_folder("../resources/defs/digestion")?;
+        let id = defs.get_id("default").unwrap();
+        let def = defs.get(id).unwrap();
+        let mut digestion = DigestionComponent::new(id, &defs);
+
+        let apple = EdibleComponent::new(EdibleKind::Foliage(None))
+            .with_calories(500)
+            .with_hydration(200)
+            .with_volume(300);
+        let mut eaten = 0;
+        while digestion.can_ingest(def, &apple) {
+            assert!(digestion.ingest(def, &apple));
+            eaten += 1;
+        }
+        assert_eq!(eaten, 8);
+        assert!(!digestion.ingest(def, &apple));
+
+        digestion.digest(def, 8 * 3600, 0, 0);
+        assert_eq!(digestion.contents()[0].volume, 0);
+        assert!(digestion.calories().value > 0);
+        assert_eq!(digestion.hunger(), HungerState::Fed);
+
+        let digested = digestion.digest(def, 8 * 24 * 3600, 0, 0);
+        assert!(digested.changed);
+        assert!(digested.distress > 0);
+        assert_eq!(digestion.hunger(), HungerState::Starving);
+        assert_eq!(digestion.thirst(), ThirstState::Dehydrated);
+
+        Ok(())
+    }
+
+    #[test]
+    fn spoil_and_cook() -> Result<(), failure::Error> {
+        use crate::components::EdibleComponent;
+        use crate::defs::{material::MaterialDefinition, DefinitionStorage};
+
+        let materials =
+            DefinitionStorage::<MaterialDefinition>::from_folder("../resources/defs/materials")?;
+        let meat = materials.find("meat").unwrap();
+        let state = &meat.states[&crate::defs::material::MaterialState::Solid];
+
+        let meat = || EdibleComponent::new(EdibleKind::Meat(0)).with_material(state, 500);
+        let mut raw = meat();
+        assert_eq!(raw.calories(), 1000);
+        assert_eq!(raw.hydration(), 300);
+
+        // Cooked meat lasts twice as long as raw meat, and cold storage slows both down.
+        let mut cooked = meat();
+        assert!(cooked.cook(EdibleState::Cooked));
+        assert!(!cooked.cook(EdibleState::Burnt));
+
+        let mut cold = meat();
+        assert!(raw.spoil(3 * 86400, 1.0));
+        assert_eq!(raw.state(), EdibleState::Rotten);
+        assert!(!cooked.spoil(3 * 86400, 1.0));
+        assert!(cooked.spoil(3 * 86400, 1.0));
+        assert!(!cold.spoil(3 * 86400, 0.1));
+        assert_eq!(cold.state(), EdibleState::Raw);
+
+        // Cold food still spoils in the end, even a second at a time.
+        let seconds = (0..).take_while(|_| !cold.spoil(1, 0.1)).count();
+        assert!((27 * 86400 - 2..=27 * 86400).contains(&seconds));
+
+        Ok(())
+    }
+
     #[test]
     fn digestion_deserialize() -> Result<(), failure::Error> {
         init_test_log();
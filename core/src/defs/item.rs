@@ -1,7 +1,7 @@
 use crate::{
     components::PropertiesComponent,
     defs::{
-        material::{MaterialCategory, MaterialState},
+        material::{MaterialCategory, MaterialRef, MaterialState},
         property::{Dimensions, Property},
         sprites::SpriteRef,
         Definition, HasProperties, Named,
@@ -39,6 +39,10 @@ pub struct ItemDefinition {
 
     pub dimensions: Option<Dimensions>,
 
+    /// Material the item is made of when it is spawned without one.
+    #[serde(default)]
+    pub material: Option<MaterialRef>,
+
     #[serde(default)]
     pub properties: Vec<Property>,
 
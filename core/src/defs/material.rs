@@ -1,6 +1,9 @@
 use super::{Definition, InheritDefinition, Named};
-use crate::{defs::DefinitionStorage, strum_macros::AsRefStr};
-use survival_derive::NamedDefinition;
+use crate::{
+    defs::{property::MovementCosts, DefinitionStorage},
+    strum_macros::AsRefStr,
+};
+use survival_derive::{InheritDefinition, NamedDefinition};
 
 #[derive(
     AsRefStr,
@@ -79,6 +82,7 @@ impl Default for MaterialCategory {
 
 #[derive(
     NamedDefinition,
+    InheritDefinition,
     Debug,
     Clone,
     Default,
@@ -96,6 +100,7 @@ pub struct MaterialStateDefinition {
     id: Option<u32>,
 
     #[serde(default)]
+    #[inherit(parent)]
     inherits: Option<String>,
 
     #[serde(default)]
@@ -136,61 +141,34 @@ pub struct MaterialStateDefinition {
     #[serde(default)]
     pub abrasive_hardness: Option<u32>,
 
-    pub sprite: (String, u32),
-}
-
-impl InheritDefinition for MaterialStateDefinition {
-    fn inherit_from(&mut self, parent: &Self) {
-        self.density = self.density.map_or(parent.density, Some);
-        self.hardness = self.hardness.map_or(parent.hardness, Some);
-        self.porosity = self.porosity.map_or(parent.porosity, Some);
-        self.permeability = self.permeability.map_or(parent.permeability, Some);
-        self.elasticity = self.elasticity.map_or(parent.elasticity, Some);
-        self.tensile_strength = self.tensile_strength.map_or(parent.tensile_strength, Some);
-        self.tensile_yield = self.tensile_yield.map_or(parent.tensile_yield, Some);
-        self.compressive_yield_strength = self
-            .compressive_yield_strength
-            .map_or(parent.compressive_yield_strength, Some);
-        self.fatigue_strength = self.fatigue_strength.map_or(parent.fatigue_strength, Some);
-        self.fracture_toughness = self
-            .fracture_toughness
-            .map_or(parent.fracture_toughness, Some);
-        self.flexural_strength = self
-            .flexural_strength
-            .map_or(parent.flexural_strength, Some);
-        self.shear_modulus = self.shear_modulus.map_or(parent.abrasive_hardness, Some);
-        self.poisson_ratio = self.poisson_ratio.map_or(parent.poisson_ratio, Some);
-        self.impact_toughness = self.impact_toughness.map_or(parent.impact_toughness, Some);
-        self.electric_resistance = self
-            .electric_resistance
-            .map_or(parent.electric_resistance, Some);
-        self.specific_heat_capacity = self
-            .specific_heat_capacity
-            .map_or(parent.specific_heat_capacity, Some);
-        self.thermal_conductivity = self
-            .thermal_conductivity
-            .map_or(parent.thermal_conductivity, Some);
-        self.abrasive_hardness = self
-            .abrasive_hardness
-            .map_or(parent.abrasive_hardness, Some);
-    }
+    /// kcal per liter, for materials which can be eaten.
+    #[serde(default)]
+    pub calories: Option<u32>,
+    /// mL of water per liter, for materials which can be eaten or drunk.
+    #[serde(default)]
+    pub hydration: Option<u32>,
+    /// Seconds until food of this material rots at room temperature. Never, if unset.
+    #[serde(default)]
+    pub spoil_time: Option<u32>,
 
-    fn parent(&self) -> Option<&str> {
-        self.inherits.as_ref().map(String::as_str)
-    }
+    pub sprite: (String, u32),
 }
 
-#[derive(NamedDefinition, Clone, Default, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(
+    NamedDefinition, InheritDefinition, Clone, Default, Debug, serde::Serialize, serde::Deserialize,
+)]
 pub struct MaterialDefinition {
     name: String,
     #[serde(skip)]
     id: Option<u32>,
 
+    #[inherit(parent)]
     pub inherits: Option<String>,
 
     pub category: MaterialCategory,
 
     #[serde(default)]
+    #[inherit(merge)]
     pub states: fnv::FnvHashMap<MaterialState, MaterialStateDefinition>,
 
     #[serde(default)]
@@ -204,6 +182,11 @@ pub struct MaterialDefinition {
 
     #[serde(default)]
     pub freeze_point: Option<u64>,
+
+    /// Cost overrides for moving across a tile whose surface is this material.
+    #[serde(default)]
+    #[inherit(replace)]
+    pub movement_costs: MovementCosts,
 }
 
 impl PartialEq for MaterialDefinition {
@@ -215,6 +198,7 @@ impl PartialEq for MaterialDefinition {
             && self.boil_point == other.boil_point
             && self.ignite_point == other.ignite_point
             && self.freeze_point == other.freeze_point
+            && self.movement_costs == other.movement_costs
         {
             for (self_k, self_v) in &self.states {
                 if let Some(v) = other.states.get(self_k) {
@@ -242,6 +226,7 @@ impl std::hash::Hash for MaterialDefinition {
         self.boil_point.hash(state);
         self.ignite_point.hash(state);
         self.freeze_point.hash(state);
+        self.movement_costs.hash(state);
 
         self.states.iter().for_each(|(k, v)| {
             k.hash(state);
@@ -250,25 +235,6 @@ impl std::hash::Hash for MaterialDefinition {
     }
 }
 
-impl InheritDefinition for MaterialDefinition {
-    fn inherit_from(&mut self, parent: &Self) {
-        self.freeze_point = self.freeze_point.map_or(parent.freeze_point, Some);
-        self.melt_point = self.melt_point.map_or(parent.melt_point, Some);
-        self.boil_point = self.boil_point.map_or(parent.boil_point, Some);
-        self.ignite_point = self.ignite_point.map_or(parent.ignite_point, Some);
-
-        parent.states.iter().for_each(|(k, v)| {
-            if !self.states.contains_key(k) {
-                self.states.insert(*k, v.clone());
-            }
-        });
-    }
-
-    fn parent(&self) -> Option<&str> {
-        self.inherits.as_ref().map(String::as_str)
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct MaterialRef {
     pub name: String,
@@ -1,20 +1,26 @@
 pub mod action;
+pub mod behavior;
 pub mod body;
 pub mod building;
+pub mod bundle;
 pub mod creature;
 pub mod digestion;
 pub mod foliage;
 pub mod item;
 pub mod material;
+pub mod mods;
 pub mod property;
 pub mod psyche;
 pub mod race;
 pub mod reaction;
 pub mod sprites;
+pub mod validation;
+pub mod watcher;
 
-use hibitset::BitSet;
+use hibitset::{BitSet, BitSetLike};
+use self::mods::{DefinitionPatch, ModDirectory};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::metadata,
     path::{Path, PathBuf},
 };
@@ -68,12 +74,23 @@ where
     pub fn get(&self, id: u32) -> Option<&T> { self.storage.get(id) }
 }
 
+/// A folder definitions are loaded from, and the mod it belongs to. Later layers add to and
+/// override earlier ones.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DefinitionLayer {
+    pub source_mod: Option<String>,
+    pub folder: PathBuf,
+}
+
 #[derive(Clone, Default, Debug)]
 pub struct DefinitionStorage<T: Clone + std::fmt::Debug, D = T> {
     bitset: BitSet,
     lookup: HashMap<String, u32>,
     storage: Vec<D>,
     source: PathBuf,
+    layers: Vec<DefinitionLayer>,
+    origins: HashMap<u32, PathBuf>,
+    mods: HashMap<u32, String>,
     _marker: std::marker::PhantomData<(T, D)>,
 }
 
@@ -105,69 +122,70 @@ where
 
     pub fn bitset(&self) -> &BitSet { &self.bitset }
 
-    pub fn len(&self) -> usize { self.storage.len() }
-    pub fn is_empty(&self) -> bool { self.len() < 1 }
+    /// The file a definition was loaded from, if it came from disk.
+    pub fn origin(&self, id: u32) -> Option<&Path> { self.origins.get(&id).map(PathBuf::as_path) }
 
-    pub fn reload(&mut self) -> Result<(), failure::Error> {
-        self.storage.clear();
-        self.lookup.clear();
+    /// The mod which added a definition, if it was loaded from mods.
+    pub fn source_mod(&self, id: u32) -> Option<&str> { self.mods.get(&id).map(String::as_str) }
 
-        self.internal_from_folder(self.source.clone())?;
+    pub fn layers(&self) -> &[DefinitionLayer] { &self.layers }
 
-        Ok(())
-    }
+    pub fn len(&self) -> usize { self.storage.len() }
+    pub fn is_empty(&self) -> bool { self.len() < 1 }
 
-    fn internal_from_folder<P>(&mut self, folder: P) -> Result<(), failure::Error>
-    where
-        P: AsRef<Path>,
-    {
+    fn load_layer(&mut self, layer: &DefinitionLayer) -> Result<(), failure::Error> {
         log::trace!(
             "Loading definitions from path: cwd={}, path={}",
             std::env::current_dir().unwrap().display(),
-            folder.as_ref().display()
+            layer.folder.display()
         );
-        // Collect the files and try the root name as well.
-        let mut files = Vec::new();
-
-        // Add the root entry
-        let root_file = folder.as_ref().with_extension("ron");
-        if let Ok(meta) = metadata(&root_file) {
-            if meta.file_type().is_file() {
-                files.push(root_file);
-            }
-        }
 
-        for entry in WalkDir::new(folder.as_ref())
+        for entry in definition_files(&layer.folder)
             .into_iter()
-            .filter_map(Result::ok)
+            .filter(|file| !is_patch_file(file))
         {
-            if let Ok(meta) = entry.metadata() {
-                if meta.file_type().is_file() {
-                    files.push(entry.into_path());
-                }
-            }
-        }
-
-        files.sort();
-
-        for entry in files {
             log::trace!("Attempting definitions from: {:?}", entry);
 
-            let file = std::fs::OpenOptions::new().read(true).open(entry)?;
+            let file = std::fs::OpenOptions::new().read(true).open(&entry)?;
             let def_entries = ron::de::from_reader::<std::fs::File, Vec<T>>(file)?;
 
             self.storage.reserve(self.storage.len() + def_entries.len());
             self.lookup.reserve(self.lookup.len() + def_entries.len());
 
-            for def in &def_entries {
-                self.insert(def.clone());
+            for def in def_entries {
+                if let Some(id) = self.get_id(def.name()) {
+                    if self.mods.get(&id) == layer.source_mod.as_ref() {
+                        log::warn!(
+                            "Duplicate definition '{}' in {}, replacing the one in {}",
+                            def.name(),
+                            entry.display(),
+                            self.origin(id)
+                                .map_or("<unknown>".into(), Path::to_string_lossy)
+                        );
+                    }
+                }
+
+                let id = self.insert(def);
+                self.origins.insert(id, entry.clone());
+                match &layer.source_mod {
+                    Some(source_mod) => self.mods.insert(id, source_mod.clone()),
+                    None => self.mods.remove(&id),
+                };
             }
         }
 
         Ok(())
     }
 
-    pub fn insert(&mut self, mut def: T) {
+    /// Adds a definition, or replaces the one with the same name in place so its id is kept.
+    pub fn insert(&mut self, mut def: T) -> u32 {
+        if let Some(id) = self.get_id(def.name()) {
+            def.set_id(id);
+            log::trace!("Replaced '{}'", def.name().to_lowercase());
+            self.storage[id as usize] = def;
+            return id;
+        }
+
         def.set_id(self.storage.len() as u32);
         self.bitset.add(def.id().unwrap());
 
@@ -175,14 +193,21 @@ where
             .insert(def.name().to_lowercase().to_string(), def.id().unwrap());
         log::trace!("Inserted '{}'", def.name().to_lowercase());
         self.storage.push(def);
+        self.storage.len() as u32 - 1
     }
 
     pub fn from_folder<P>(folder: P) -> Result<Self, failure::Error>
     where
         P: AsRef<Path>,
     {
+        let layer = DefinitionLayer {
+            source_mod: None,
+            folder: folder.as_ref().to_path_buf(),
+        };
+
         let mut s = Self::new(&folder);
-        s.internal_from_folder(folder)?;
+        s.load_layer(&layer)?;
+        s.layers.push(layer);
         Ok(s)
     }
 
@@ -193,6 +218,9 @@ where
         Self {
             bitset: BitSet::new(),
             source: folder.as_ref().to_path_buf(),
+            layers: Vec::new(),
+            origins: HashMap::new(),
+            mods: HashMap::new(),
             lookup: HashMap::new(),
             storage: Vec::new(),
             _marker: Default::default(),
@@ -200,38 +228,282 @@ where
     }
 }
 
+impl<T> DefinitionStorage<T>
+where
+    T: std::fmt::Debug + Clone + Definition + for<'a> serde::Deserialize<'a> + serde::Serialize,
+{
+    /// Reloads the storage from its folder in place, keeping ids stable by name.
+    pub fn reload(&mut self) -> Result<DefinitionDiff, failure::Error> {
+        let fresh = self.load_fresh()?;
+        Ok(self.update_from(fresh))
+    }
+
+    /// Loads this storage's folders again into a new storage, so it can be prepared (inherited,
+    /// validated) before being merged back with `update_from`.
+    pub fn load_fresh(&self) -> Result<Self, failure::Error> {
+        if self.layers.is_empty() {
+            Self::from_folder(&self.source)
+        } else {
+            Self::from_layers(self.layers.clone())
+        }
+    }
+
+    /// Loads the `kind` folder (e.g. `materials`) of each mod in turn, each on top of the last.
+    pub fn from_mods(mods: &[ModDirectory], kind: &str) -> Result<Self, failure::Error> {
+        Self::from_layers(
+            mods.iter()
+                .map(|m| DefinitionLayer {
+                    source_mod: Some(m.name().to_string()),
+                    folder: m.path.join(kind),
+                })
+                .collect(),
+        )
+    }
+
+    pub fn from_layers(layers: Vec<DefinitionLayer>) -> Result<Self, failure::Error> {
+        let mut s = Self::new(
+            &layers
+                .first()
+                .map_or_else(PathBuf::new, |l| l.folder.clone()),
+        );
+        for layer in layers {
+            s.load_layer(&layer)?;
+            s.apply_patches(&layer)?;
+            s.layers.push(layer);
+        }
+        Ok(s)
+    }
+
+    fn apply_patches(&mut self, layer: &DefinitionLayer) -> Result<(), failure::Error> {
+        for file in definition_files(&layer.folder)
+            .into_iter()
+            .filter(|file| is_patch_file(file))
+        {
+            for patch in DefinitionPatch::parse_file(&std::fs::read_to_string(&file)?)? {
+                let id = self.get_id(&patch.name).ok_or_else(|| {
+                    failure::format_err!(
+                        "{}: patch for missing definition '{}'",
+                        file.display(),
+                        patch.name
+                    )
+                })?;
+
+                let mut def = patch.apply(&self.storage[id as usize])?;
+                def.set_id(id);
+                self.storage[id as usize] = def;
+                log::debug!("Patched '{}' from {}", patch.name, file.display());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces these definitions with those in `fresh`. A definition keeps its id for as long
+    /// as its name exists, so ids held by components stay valid across reloads. Removed
+    /// definitions are dropped from name lookups but keep their slot, so stale ids still resolve
+    /// until whatever holds them is refreshed; if the name returns, so does the id.
+    pub fn update_from(&mut self, fresh: Self) -> DefinitionDiff {
+        let mut diff = DefinitionDiff::default();
+        let mut seen = HashSet::new();
+
+        for mut def in fresh.storage {
+            let origin = def.id().and_then(|id| fresh.origins.get(&id).cloned());
+            let source_mod = def.id().and_then(|id| fresh.mods.get(&id).cloned());
+            let key = def.name().to_lowercase();
+            seen.insert(key.clone());
+
+            let existing = self.lookup.get(&key).copied();
+            let slot = existing.or_else(|| {
+                self.storage
+                    .iter()
+                    .position(|old| old.name().to_lowercase() == key)
+                    .map(|id| id as u32)
+            });
+
+            match slot {
+                Some(id) => {
+                    def.set_id(id);
+                    self.set_origin(id, origin, source_mod);
+                    if existing.is_none() {
+                        diff.added.push(def.name().to_string());
+                        self.lookup.insert(key, id);
+                        self.bitset.add(id);
+                    } else if ron::ser::to_string(&self.storage[id as usize]).ok()
+                        != ron::ser::to_string(&def).ok()
+                    {
+                        diff.changed.push(def.name().to_string());
+                    }
+                    self.storage[id as usize] = def;
+                }
+                None => {
+                    diff.added.push(def.name().to_string());
+                    let id = self.insert(def);
+                    self.set_origin(id, origin, source_mod);
+                }
+            }
+        }
+
+        let removed = self
+            .lookup
+            .iter()
+            .filter(|(key, _)| !seen.contains(*key))
+            .map(|(key, id)| (key.clone(), *id))
+            .collect::<Vec<_>>();
+        for (key, id) in removed {
+            self.lookup.remove(&key);
+            self.bitset.remove(id);
+            diff.removed
+                .push(self.storage[id as usize].name().to_string());
+        }
+
+        diff.added.sort();
+        diff.changed.sort();
+        diff.removed.sort();
+        diff
+    }
+
+    fn set_origin(&mut self, id: u32, origin: Option<PathBuf>, source_mod: Option<String>) {
+        match origin {
+            Some(origin) => self.origins.insert(id, origin),
+            None => self.origins.remove(&id),
+        };
+        match source_mod {
+            Some(source_mod) => self.mods.insert(id, source_mod),
+            None => self.mods.remove(&id),
+        };
+    }
+}
+
+/// The definition files for a folder: `<folder>.ron`, `<folder>.patch.ron` and every file
+/// beneath `<folder>`, sorted by path.
+fn definition_files(folder: &Path) -> Vec<PathBuf> {
+    let mut files = [
+        folder.with_extension("ron"),
+        folder.with_extension("patch.ron"),
+    ]
+    .iter()
+    .filter(|file| metadata(file).map_or(false, |meta| meta.file_type().is_file()))
+    .cloned()
+    .collect::<Vec<_>>();
+
+    for entry in WalkDir::new(folder).into_iter().filter_map(Result::ok) {
+        if let Ok(meta) = entry.metadata() {
+            if meta.file_type().is_file() {
+                files.push(entry.into_path());
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+fn is_patch_file(path: &Path) -> bool {
+    path.file_name()
+        .map_or(false, |name| name.to_string_lossy().ends_with(".patch.ron"))
+}
+
+/// What reloading a `DefinitionStorage` changed, by definition name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DefinitionDiff {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+impl DefinitionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+impl std::fmt::Display for DefinitionDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "added: [{}], changed: [{}], removed: [{}]",
+            self.added.join(", "),
+            self.changed.join(", "),
+            self.removed.join(", ")
+        )
+    }
+}
+
 impl<T> InheritDefinitionStorage for DefinitionStorage<T>
 where
     T: std::fmt::Debug + InheritDefinition + Definition + for<'a> serde::Deserialize<'a> + Clone,
 {
+    /// Resolves every definition's parent before the definition itself, so inheritance chains of
+    /// any depth are applied in full. Fails if a definition (indirectly) inherits from itself.
     fn apply_inherits(&mut self) -> Result<(), failure::Error> {
-        let mut parents = Vec::new();
+        let mut resolved = HashSet::new();
+        let mut order = Vec::new();
 
-        for item in self.iter() {
-            if let Some(parent_name) = item.parent() {
-                parents.push((item.id().unwrap(), parent_name.to_string()));
-            }
+        for id in (&self.bitset).iter() {
+            inherit_order(self, id, &mut resolved, &mut Vec::new(), &mut order)?;
         }
 
-        for (index, parent) in &parents {
-            let parent = { self.iter().find(|v| v.name() == *parent).unwrap().clone() };
-            if let Some(item) = self.get_mut(*index as u32) {
-                item.inherit_from(&parent);
-            }
+        for (id, parent) in order {
+            let parent = self.storage[parent as usize].clone();
+            self.storage[id as usize].inherit_from(&parent);
         }
 
         Ok(())
     }
 }
 
+/// Depth-first walk up the parents of `id`, pushing each `(child, parent)` pair after the parent's
+/// own pairs. `path` holds the chain currently being walked, to detect cycles.
+fn inherit_order<T>(
+    storage: &DefinitionStorage<T>,
+    id: u32,
+    resolved: &mut HashSet<u32>,
+    path: &mut Vec<u32>,
+    order: &mut Vec<(u32, u32)>,
+) -> Result<(), failure::Error>
+where
+    T: std::fmt::Debug + InheritDefinition + Definition + for<'a> serde::Deserialize<'a> + Clone,
+{
+    if resolved.contains(&id) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|v| *v == id) {
+        let cycle = path[start..]
+            .iter()
+            .chain(std::iter::once(&id))
+            .filter_map(|id| storage.get(*id))
+            .map(Named::name)
+            .collect::<Vec<_>>();
+        return Err(failure::format_err!(
+            "Definition inheritance cycle: {}",
+            cycle.join(" -> ")
+        ));
+    }
+
+    if let Some(parent_name) = storage.get(id).and_then(InheritDefinition::parent) {
+        // Missing parents are left for validation to report alongside every other broken
+        // reference.
+        match storage.get_id(parent_name) {
+            Some(parent) => {
+                path.push(id);
+                inherit_order(storage, parent, resolved, path, order)?;
+                path.pop();
+                order.push((id, parent));
+            }
+            None => log::warn!("Missing parent definition '{}'", parent_name),
+        }
+    }
+
+    resolved.insert(id);
+    Ok(())
+}
+
 pub trait HasProperties {
     fn default_properties(&self) -> crate::components::PropertiesComponent;
 }
 
 #[cfg(test)]
 mod tests {
-    use super::material::MaterialDefinition;
     use super::*;
+    use super::{body::BodyDefinition, material::MaterialDefinition, property::Property};
 
     #[test]
     fn material_definitions() -> Result<(), failure::Error> {
@@ -295,4 +567,143 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn update_keeps_ids_stable() -> Result<(), failure::Error> {
+        let mut storage =
+            DefinitionStorage::<MaterialDefinition>::from_folder("../resources/defs/materials")?;
+        let names = storage
+            .iter()
+            .map(|def| def.name().to_string())
+            .collect::<Vec<_>>();
+        assert!(names.len() > 2);
+
+        // Reload from a reordered set with one definition changed and one removed
+        let mut fresh = DefinitionStorage::<MaterialDefinition>::new(&"");
+        let mut reordered = storage.raw_storage().clone();
+        reordered.reverse();
+        let removed = reordered.remove(0);
+        reordered[0].ignite_point = Some(123_456_789);
+        let changed = reordered[0].name().to_string();
+        for def in reordered {
+            fresh.insert(def);
+        }
+
+        let diff = storage.update_from(fresh.clone());
+        assert_eq!(diff.changed, vec![changed.clone()]);
+        assert_eq!(diff.removed, vec![removed.name().to_string()]);
+        assert!(diff.added.is_empty());
+
+        for (id, name) in names.iter().enumerate() {
+            if *name == removed.name() {
+                assert!(storage.find(name).is_none());
+                assert_eq!(storage.get(id as u32).unwrap().name(), name);
+            } else {
+                assert_eq!(storage.find(name).unwrap().id(), Some(id as u32));
+            }
+        }
+
+        // Bringing the definition back restores its id
+        fresh.insert(removed.clone());
+        let diff = storage.update_from(fresh);
+        assert_eq!(diff.added, vec![removed.name().to_string()]);
+        assert!(diff.changed.is_empty());
+        assert_eq!(storage.find(removed.name()).unwrap().id(), removed.id());
+
+        Ok(())
+    }
+
+    fn bodies(source: &str) -> Result<DefinitionStorage<BodyDefinition>, failure::Error> {
+        let mut storage = DefinitionStorage::new(&"");
+        for def in ron::de::from_str::<Vec<BodyDefinition>>(source)? {
+            storage.insert(def);
+        }
+        Ok(storage)
+    }
+
+    #[test]
+    fn multi_level_inherits() -> Result<(), failure::Error> {
+        // Children are listed before their parents, so a single pass in storage order would miss
+        // the grandparent's properties.
+        let mut storage = bodies(
+            r#"#![enable(implicit_some)]
+            [
+                (name: "Goblin", inherits: "humanoid", remove_properties: [CanPickup]),
+                (name: "Humanoid", inherits: "Creature", properties: [MovementSpeed(5)]),
+                (name: "Creature", properties: [MovementSpeed(1), CanPickup, Building]),
+            ]"#,
+        )?;
+        storage.apply_inherits()?;
+
+        let humanoid = storage.find("Humanoid").unwrap();
+        assert_eq!(humanoid.properties.len(), 3);
+        let speeds = humanoid
+            .properties
+            .iter()
+            .filter_map(|p| match p {
+                Property::MovementSpeed(speed) => Some(*speed),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(speeds, vec![5]);
+
+        let goblin = storage.find("Goblin").unwrap();
+        assert_eq!(goblin.properties.len(), 2);
+        assert!(!goblin.properties.contains(&Property::CanPickup));
+        assert!(goblin.properties.contains(&Property::Building));
+
+        Ok(())
+    }
+
+    #[test]
+    fn inherit_cycles_fail() -> Result<(), failure::Error> {
+        let mut storage = bodies(
+            r#"#![enable(implicit_some)]
+            [
+                (name: "A", inherits: "C"),
+                (name: "B", inherits: "A"),
+                (name: "C", inherits: "B"),
+            ]"#,
+        )?;
+        let error = storage.apply_inherits().unwrap_err().to_string();
+        assert!(error.contains("A -> C -> B -> A"), "{}", error);
+
+        let mut storage = bodies(r#"#![enable(implicit_some)] [(name: "A", inherits: "a")]"#)?;
+        assert!(storage.apply_inherits().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn derived_inherits() {
+        use super::material::{MaterialState, MaterialStateDefinition};
+
+        let mut solid = MaterialStateDefinition::default();
+        solid.density = Some(1);
+        solid.shear_modulus = Some(2);
+        solid.abrasive_hardness = Some(3);
+
+        let mut parent = MaterialDefinition::default();
+        parent.melt_point = Some(100);
+        parent.states.insert(MaterialState::Solid, solid.clone());
+        parent
+            .states
+            .insert(MaterialState::Liquid, MaterialStateDefinition::default());
+
+        let mut state = MaterialStateDefinition::default();
+        state.density = Some(4);
+        state.inherit_from(&solid);
+        assert_eq!(state.density, Some(4));
+        assert_eq!(state.shear_modulus, Some(2));
+        assert_eq!(state.abrasive_hardness, Some(3));
+
+        let mut child = MaterialDefinition::default();
+        child.inherits = Some("parent".to_string());
+        child.states.insert(MaterialState::Solid, state);
+        child.inherit_from(&parent);
+        assert_eq!(child.parent(), Some("parent"));
+        assert_eq!(child.melt_point, Some(100));
+        assert_eq!(child.states.len(), 2);
+        assert_eq!(child.states[&MaterialState::Solid].density, Some(4));
+    }
 }
@@ -0,0 +1,434 @@
+//! Mods layer extra definition folders on top of the base game. A mod is a directory holding a
+//! `mod.ron` manifest and definition folders laid out like `resources/defs`. Definitions in a mod
+//! are added, or replace an earlier definition with the same name, while entries in
+//! `*.patch.ron` files only replace the fields they list:
+//!
+//! ```text
+//! [
+//!     (name: "Human", body: "Goblinoid"),
+//! ]
+//! ```
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ModManifest {
+    pub name: String,
+
+    #[serde(default)]
+    pub version: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Mods which have to be loaded before this one.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+
+    /// Mods which don't depend on each other load in ascending order of this, then by name.
+    #[serde(default)]
+    pub load_order: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ModDirectory {
+    pub manifest: ModManifest,
+    pub path: PathBuf,
+}
+impl ModDirectory {
+    pub const MANIFEST: &'static str = "mod.ron";
+    pub const BASE: &'static str = "base";
+
+    /// The base game's definitions, which every other mod is loaded on top of.
+    pub fn base<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            manifest: ModManifest {
+                name: Self::BASE.to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                description: None,
+                dependencies: Vec::new(),
+                load_order: std::i32::MIN,
+            },
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn open<P>(path: P) -> Result<Self, failure::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path.join(Self::MANIFEST))?;
+        Ok(Self {
+            manifest: ron::de::from_reader(file)?,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn name(&self) -> &str { &self.manifest.name }
+}
+
+/// Every mod directly under `root`. A directory without a manifest is skipped, and a missing
+/// `root` simply means there are no mods.
+pub fn discover<P>(root: P) -> Result<Vec<ModDirectory>, failure::Error>
+where
+    P: AsRef<Path>,
+{
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut mods = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.join(ModDirectory::MANIFEST).is_file() {
+            mods.push(ModDirectory::open(&path)?);
+        } else if path.is_dir() {
+            log::warn!("Skipping {}: no {}", path.display(), ModDirectory::MANIFEST);
+        }
+    }
+    Ok(mods)
+}
+
+/// Orders mods so each one loads after its dependencies, by `load_order` and then name where
+/// they don't depend on each other. The base game always loads first.
+pub fn load_order(mods: Vec<ModDirectory>) -> Result<Vec<ModDirectory>, failure::Error> {
+    let mut names = HashSet::new();
+    for m in &mods {
+        if !names.insert(m.name().to_lowercase()) {
+            failure::bail!("Mod '{}' is installed twice", m.name());
+        }
+    }
+    for m in &mods {
+        for dependency in &m.manifest.dependencies {
+            if !names.contains(&dependency.to_lowercase()) {
+                failure::bail!("Mod '{}' depends on missing mod '{}'", m.name(), dependency);
+            }
+        }
+    }
+
+    let mut pending = mods;
+    let mut loaded = HashSet::new();
+    let mut ordered = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let next = pending
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| {
+                m.manifest
+                    .dependencies
+                    .iter()
+                    .all(|dependency| loaded.contains(&dependency.to_lowercase()))
+            })
+            .min_by(|(_, a), (_, b)| {
+                (
+                    a.name() != ModDirectory::BASE,
+                    a.manifest.load_order,
+                    a.name(),
+                )
+                    .cmp(&(
+                        b.name() != ModDirectory::BASE,
+                        b.manifest.load_order,
+                        b.name(),
+                    ))
+            })
+            .map(|(index, _)| index);
+
+        match next {
+            Some(index) => {
+                let m = pending.remove(index);
+                loaded.insert(m.name().to_lowercase());
+                ordered.push(m);
+            }
+            None => failure::bail!(
+                "Mod dependency cycle between: {}",
+                pending
+                    .iter()
+                    .map(ModDirectory::name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    Ok(ordered)
+}
+
+/// The base game's definitions under `base`, followed by every mod under `mods_root` in load
+/// order.
+pub fn mod_list<B, M>(base: B, mods_root: M) -> Result<Vec<ModDirectory>, failure::Error>
+where
+    B: AsRef<Path>,
+    M: AsRef<Path>,
+{
+    let mut mods = discover(mods_root)?;
+    mods.push(ModDirectory::base(base));
+    load_order(mods)
+}
+
+/// Fields to replace on an existing definition, as RON text keyed by field name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinitionPatch {
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+}
+impl DefinitionPatch {
+    /// Parses a patch file: a list of structs each naming the definition they patch.
+    pub fn parse_file(text: &str) -> Result<Vec<Self>, failure::Error> {
+        let text = strip_comments(text);
+        let list = delimited(strip_attributes(&text), '[', ']')?;
+
+        split_top_level(list)
+            .into_iter()
+            .map(|entry| {
+                let mut fields = struct_fields(entry)?;
+                let index = fields
+                    .iter()
+                    .position(|(field, _)| field == "name")
+                    .ok_or_else(|| failure::format_err!("Patch without a name: {}", entry))?;
+                let name = fields.remove(index).1;
+                Ok(Self {
+                    name: ron::de::from_str(&name)?,
+                    fields,
+                })
+            })
+            .collect()
+    }
+
+    /// Re-reads `def` with this patch's fields in place of its own.
+    pub fn apply<T>(&self, def: &T) -> Result<T, failure::Error>
+    where
+        T: serde::Serialize + for<'a> serde::Deserialize<'a>,
+    {
+        let current = ron::ser::to_string(def)?;
+        let mut fields = struct_fields(&current)?;
+        for (field, value) in &self.fields {
+            match fields.iter_mut().find(|(existing, _)| existing == field) {
+                Some(existing) => existing.1 = value.clone(),
+                None => fields.push((field.clone(), value.clone())),
+            }
+        }
+
+        let patched = fields
+            .iter()
+            .map(|(field, value)| format!("{}: {}", field, value))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Ok(ron::de::from_str(&format!(
+            "#![enable(implicit_some)]\n({})",
+            patched
+        ))?)
+    }
+}
+
+/// Replaces `//` and `/* */` comments outside of strings with whitespace.
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut quote = None;
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) => {
+                out.push(c);
+                if c == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        out.push(escaped);
+                    }
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None if c == '/' && chars.peek() == Some(&'/') => {
+                while chars.peek().map_or(false, |c| *c != '\n') {
+                    chars.next();
+                }
+                out.push(' ');
+            }
+            None if c == '/' && chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                out.push(' ');
+            }
+            None => {
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                }
+                out.push(c);
+            }
+        }
+    }
+
+    out
+}
+
+/// Drops leading `#![enable(...)]` attributes.
+fn strip_attributes(mut text: &str) -> &str {
+    while text.trim_start().starts_with("#!") {
+        text = text.trim_start();
+        text = text.find(']').map_or("", |end| &text[end + 1..]);
+    }
+    text.trim()
+}
+
+/// The text between `open` and its matching `close`, skipping a struct name before `open`.
+fn delimited(text: &str, open: char, close: char) -> Result<&str, failure::Error> {
+    let text = text.trim();
+    match (text.find(open), text.ends_with(close)) {
+        (Some(start), true) => Ok(&text[start + 1..text.len() - 1]),
+        _ => failure::bail!("Expected {} ... {}: {}", open, close, text),
+    }
+}
+
+/// Splits on commas which aren't nested in brackets or strings, dropping empty items.
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0_i32;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (index, c) in text.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&text[start..]);
+
+    items
+        .into_iter()
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// The `(field, value)` pairs of a RON struct, with values left as RON text.
+fn struct_fields(text: &str) -> Result<Vec<(String, String)>, failure::Error> {
+    split_top_level(delimited(text, '(', ')')?)
+        .into_iter()
+        .map(|field| match field.find(':') {
+            Some(split) => Ok((
+                field[..split].trim().to_string(),
+                field[split + 1..].trim().to_string(),
+            )),
+            None => failure::bail!("Expected `field: value`, found: {}", field),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defs::{property::Property, race::RaceDefinition, DefinitionStorage, Named};
+
+    fn write_mod(root: &Path, name: &str, manifest: &str, files: &[(&str, &str)]) -> PathBuf {
+        let path = root.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join(ModDirectory::MANIFEST), manifest).unwrap();
+        for (file, content) in files {
+            std::fs::write(path.join(file), content).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn mods_load_in_dependency_order() -> Result<(), failure::Error> {
+        let root = std::env::temp_dir().join(format!("defs_mods_order_{}", std::process::id()));
+        write_mod(&root, "a", r#"(name: "A", load_order: 5)"#, &[]);
+        write_mod(&root, "b", r#"(name: "B", dependencies: ["C"])"#, &[]);
+        write_mod(&root, "c", r#"(name: "C", load_order: 10)"#, &[]);
+        write_mod(&root, "d", r#"(name: "D")"#, &[]);
+
+        let mods = mod_list("../resources/defs", &root)?;
+        let names = mods.iter().map(ModDirectory::name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["base", "D", "A", "C", "B"]);
+
+        write_mod(&root, "c", r#"(name: "C", dependencies: ["B"])"#, &[]);
+        assert!(mod_list("../resources/defs", &root).is_err());
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn mods_add_override_and_patch() -> Result<(), failure::Error> {
+        let root = std::env::temp_dir().join(format!("defs_mods_layers_{}", std::process::id()));
+        let path = write_mod(
+            &root,
+            "goblins",
+            r#"(name: "Goblins", version: "0.1.0")"#,
+            &[
+                (
+                    "races.ron",
+                    r#"#![enable(implicit_some)]
+                    [
+                        (name: "Goblin", body: "Humanoid"),
+                    ]"#,
+                ),
+                (
+                    "races.patch.ron",
+                    r#"#![enable(implicit_some)]
+                    [
+                        // Humans can pick things up, everything else is kept
+                        (name: "human", properties: [CanPickup, Building]),
+                    ]"#,
+                ),
+            ],
+        );
+
+        let base = DefinitionStorage::<RaceDefinition>::from_folder("../resources/defs/races")?;
+        let mods = vec![
+            ModDirectory::base("../resources/defs"),
+            ModDirectory::open(&path)?,
+        ];
+        let storage = DefinitionStorage::<RaceDefinition>::from_mods(&mods, "races")?;
+
+        let human = storage.find("Human").unwrap();
+        assert_eq!(human.id(), base.find("Human").unwrap().id());
+        assert_eq!(human.body, base.find("Human").unwrap().body);
+        assert_eq!(
+            human.properties,
+            vec![Property::CanPickup, Property::Building]
+        );
+        assert_eq!(storage.source_mod(human.id().unwrap()), Some("base"));
+
+        let goblin = storage.find("Goblin").unwrap();
+        assert_eq!(storage.source_mod(goblin.id().unwrap()), Some("Goblins"));
+        assert_eq!(storage.len(), base.len() + 1);
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
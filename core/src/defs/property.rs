@@ -19,6 +19,22 @@ pub enum Dimensions {
 impl Default for Dimensions {
     fn default() -> Self { Dimensions::Sphere { radius: 0 } }
 }
+impl Dimensions {
+    /// Volume in mm3.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn volume(&self) -> u64 {
+        match *self {
+            Dimensions::Cube { x, y, z } => x * y * z,
+            Dimensions::Sphere { radius } => {
+                (4.0 / 3.0 * std::f64::consts::PI * (radius as f64).powi(3)) as u64
+            }
+        }
+    }
+}
 
 impl From<&Vector3<u64>> for Dimensions {
     fn from(rhv: &Vector3<u64>) -> Self {
@@ -89,9 +105,29 @@ bitflags_serial! {
 bitflags_serial! {
     pub struct MovementFlags: u16 {
         const Walk = 1 << 1;
+        const Swim = 1 << 2;
+        const Fly  = 1 << 3;
     }
 }
 
+/// Traversal cost overrides per movement mode, as a list of `(modes, cost)` pairs. A cost of 0
+/// makes the tile impassable for those modes.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct MovementCosts(pub Vec<(MovementFlags, u32)>);
+impl MovementCosts {
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    pub fn get(&self, mode: MovementFlags) -> Option<u32> {
+        self.0
+            .iter()
+            .find(|(modes, _)| modes.intersects(mode))
+            .map(|(_, cost)| *cost)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(MovementFlags, u32)> { self.0.iter() }
+}
+
 bitflags_serial! {
     pub struct ManipulateFlags: u16 {
         const Any = 1;
@@ -125,7 +161,7 @@ pub enum PropertyCategory {
 )]
 #[allow(clippy::derive_hash_xor_eq)]
 #[strum_discriminants(name(PropertyKind))]
-#[strum_discriminants(derive(Hash, AsRefStr))]
+#[strum_discriminants(derive(Hash, AsRefStr, serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Property {
     Edible(EdibleKind, EdibleState),
@@ -149,6 +185,10 @@ pub enum Property {
     Container {
         dimensions: Dimensions,
     },
+    /// Cold storage, which slows the spoiling of food kept in or on it.
+    Cold,
+    /// Creatures which others can chat with, insult or comfort.
+    Sociable,
     Size {
         dimensions: Dimensions, // cm3
     },
@@ -164,6 +204,22 @@ pub enum Property {
     #[strum(props=(Category="Body"))]
     Manipulate(ManipulateFlags),
 
+    // Senses, as a fraction of full function * 1000
+    #[strum(props=(Category="Body"))]
+    Sight(u32),
+
+    #[strum(props=(Category="Body"))]
+    Hearing(u32),
+
+    #[strum(props=(Category="Body"))]
+    Smell(u32),
+
+    #[strum(props=(Category="Body"))]
+    Unconscious,
+
+    #[strum(props=(Category="Body"))]
+    Dead,
+
     // Foliage types
     #[strum(props=(Category="Foliage"))]
     Foliage(FoliageCategory),
@@ -5,6 +5,7 @@ use shrinkwraprs::Shrinkwrap;
 use strum_macros::{AsRefStr, EnumCount, EnumIter};
 use survival_derive::NamedDefinition;
 pub type PsycheTraitId = u32;
+pub type ThoughtId = u32;
 
 #[derive(
     Debug,
@@ -111,9 +112,37 @@ pub struct NeedEffect {
     pub kind: NeedKind,
     pub value: NeedEffectValue,
 }
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Changes how much a thought moves the mood of a creature with the trait. A `scale` of 0.0
+/// ignores the thought and a negative one turns it around.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ThoughtEffect {
+    pub thought: String,
+    pub scale: f32,
+}
+
+/// Ways creatures interact with each other, each moving what they think of each other.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, serde::Serialize, serde::Deserialize,
+)]
+pub enum SocialInteraction {
+    Chat,
+    Insult,
+    Comfort,
+}
+
+/// Changes how much the opinion of a creature with the trait moves when it is on the receiving
+/// end of an interaction, like `ThoughtEffect` does for thoughts.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SocialEffect {
+    pub interaction: SocialInteraction,
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum PsycheTraitEffectKind {
     NeedEffect(NeedEffect),
+    ThoughtEffect(ThoughtEffect),
+    SocialEffect(SocialEffect),
     None,
 }
 
@@ -126,11 +155,41 @@ pub struct PsycheTraitDefinition {
     #[serde(default)]
     id: Option<u32>,
 
+    /// Traits in the same group rule each other out, e.g. introverts and extroverts.
+    #[serde(default)]
+    pub group: Option<String>,
+
     pub effects: Vec<PsycheTraitEffectKind>,
 }
 
 pub type PsycheTraitRef = (String, f32);
 
+/// A memory of something which happened to a creature, such as a good meal or seeing a corpse,
+/// which moves its mood until it wears off.
+#[derive(NamedDefinition, Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ThoughtDefinition {
+    name: String,
+
+    #[serde(default)]
+    description: String,
+
+    #[serde(default)]
+    id: Option<u32>,
+
+    /// Mood the thought adds while it lasts, before any trait scales it.
+    pub mood: i16,
+    /// Seconds of game time the thought lasts.
+    pub duration: u32,
+    /// How many of this thought a creature can hold at once. Any more refresh the oldest.
+    #[serde(default = "ThoughtDefinition::stack_limit")]
+    pub stack_limit: u8,
+}
+impl ThoughtDefinition {
+    fn stack_limit() -> u8 {
+        1
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,6 +209,7 @@ mod tests {
             name: "Introvert".to_string(),
             description: "".to_string(),
             id: None,
+            group: None,
             effects: vec![PsycheTraitEffectKind::NeedEffect(NeedEffect {
                 kind: NeedKind::Social,
                 value: NeedEffectValue::Decay(NeedDecay::default()),
@@ -1,9 +1,10 @@
 use crate::{
     components::PropertiesComponent,
     defs::{
+        behavior::BehaviorDefinition,
         property::Property,
         psyche::{NeedsContainer, PsycheTraitRef},
-        Definition, HasProperties, Named,
+        Definition, DefinitionStorage, HasProperties, Named,
     },
 };
 use survival_derive::NamedDefinition;
@@ -212,6 +213,9 @@ pub struct RaceDefinition {
     id: Option<u32>,
     pub body: String,
 
+    #[serde(default)]
+    pub behavior: Option<String>,
+
     #[serde(default)]
     pub properties: Vec<Property>,
 
@@ -224,6 +228,14 @@ pub struct RaceDefinition {
     #[serde(default)]
     pub needs: NeedsContainer,
 }
+impl RaceDefinition {
+    pub fn behavior_definition<'a>(
+        &self,
+        behaviors: &'a DefinitionStorage<BehaviorDefinition>,
+    ) -> Option<&'a BehaviorDefinition> {
+        self.behavior.as_ref().and_then(|name| behaviors.find(name))
+    }
+}
 impl HasProperties for RaceDefinition {
     fn default_properties(&self) -> PropertiesComponent {
         PropertiesComponent::from_iter_ref(self.properties.iter())
@@ -241,6 +253,7 @@ mod tests {
             name: "Human".to_string(),
             id: None,
             body: "humanoid".to_string(),
+            behavior: Some("Human Root".to_string()),
             properties: Vec::new(),
             psyche: Vec::new(),
             attributes: Attributes::default_with_deviation(),
@@ -1,4 +1,5 @@
 use crate::defs::{
+    digestion::EdibleState,
     material::{MaterialRef, MaterialState},
     property::Property,
     Definition, Named,
@@ -25,6 +26,9 @@ pub enum Kind {
         #[serde(default)]
         level: u8,
     },
+    /// As a product, turns the consumed edible reagents into this state rather than using them
+    /// up, e.g. cooking.
+    Edible(EdibleState),
     Invalid,
 }
 impl Default for Kind {
@@ -0,0 +1,520 @@
+use crate::{
+    defs::{
+        action::ActionDefinition,
+        behavior::BehaviorDefinition,
+        body::BodyDefinition,
+        building::BuildingDefinition,
+        creature::CreatureDefinition,
+        digestion::DigestionDefinition,
+        foliage::FoliageDefinition,
+        item::ItemDefinition,
+        material::{MaterialDefinition, MaterialRef},
+        psyche::{PsycheTraitDefinition, PsycheTraitEffectKind, ThoughtDefinition},
+        race::RaceDefinition,
+        reaction::{self, ReactionDefinition},
+        sprites::{SpriteRef, SpriteSource},
+        Definition, DefinitionStorage, InheritDefinition, Named,
+    },
+    fsm::Event,
+    settings::GraphicsSettings,
+};
+use amethyst::ecs::{World, WorldExt};
+use std::path::PathBuf;
+
+/// A reference from one definition to something which does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub kind: &'static str,
+    pub definition: String,
+    pub file: Option<PathBuf>,
+    pub message: String,
+}
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}: ", file.display())?,
+            None => write!(f, "<unknown file>: ")?,
+        }
+        write!(f, "{} '{}': {}", self.kind, self.definition, self.message)
+    }
+}
+
+/// Every broken reference found across the definition storages.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} invalid definition references:", self.0.len())?;
+        for error in &self.0 {
+            writeln!(f, "  {}", error)?;
+        }
+        Ok(())
+    }
+}
+impl failure::Fail for ValidationErrors {}
+
+struct Report {
+    errors: Vec<ValidationError>,
+}
+impl Report {
+    fn error<T>(
+        &mut self,
+        kind: &'static str,
+        storage: &DefinitionStorage<T>,
+        def: &T,
+        message: String,
+    ) where
+        T: std::fmt::Debug + Clone + Definition + for<'a> serde::Deserialize<'a>,
+    {
+        self.errors.push(ValidationError {
+            kind,
+            definition: def.name().to_string(),
+            file: def
+                .id()
+                .and_then(|id| storage.origin(id))
+                .map(std::path::Path::to_path_buf),
+            message,
+        });
+    }
+}
+
+fn exists<T>(storage: &DefinitionStorage<T>, name: &str) -> bool
+where
+    T: std::fmt::Debug + Clone + Definition + for<'a> serde::Deserialize<'a>,
+{
+    storage.find(name).is_some()
+}
+
+fn check_parents<T>(report: &mut Report, kind: &'static str, storage: &DefinitionStorage<T>)
+where
+    T: std::fmt::Debug + Clone + Definition + InheritDefinition + for<'a> serde::Deserialize<'a>,
+{
+    for def in storage.iter() {
+        if let Some(parent) = def.parent() {
+            if !exists(storage, parent) {
+                report.error(
+                    kind,
+                    storage,
+                    def,
+                    format!("inherits missing parent '{}'", parent),
+                );
+            }
+        }
+    }
+}
+
+fn check_sprite<T>(
+    report: &mut Report,
+    kind: &'static str,
+    storage: &DefinitionStorage<T>,
+    def: &T,
+    sprite: &SpriteRef,
+    graphics: Option<&GraphicsSettings>,
+) where
+    T: std::fmt::Debug + Clone + Definition + for<'a> serde::Deserialize<'a>,
+{
+    match &sprite.source {
+        SpriteSource::Sheet(sheet) => {
+            if let Some(graphics) = graphics {
+                if !graphics.sprite_sheets.contains_key(sheet) {
+                    report.error(
+                        kind,
+                        storage,
+                        def,
+                        format!("missing sprite sheet '{}'", sheet),
+                    );
+                }
+            }
+        }
+        SpriteSource::RexPaint(name) => report.error(
+            kind,
+            storage,
+            def,
+            format!("unsupported RexPaint sprite '{}'", name),
+        ),
+    }
+}
+
+fn check_material<T>(
+    report: &mut Report,
+    kind: &'static str,
+    storage: &DefinitionStorage<T>,
+    def: &T,
+    material: &MaterialRef,
+    materials: &DefinitionStorage<MaterialDefinition>,
+) where
+    T: std::fmt::Debug + Clone + Definition + for<'a> serde::Deserialize<'a>,
+{
+    if !exists(materials, &material.name) {
+        report.error(
+            kind,
+            storage,
+            def,
+            format!("missing material '{}'", material.name),
+        );
+    }
+}
+
+fn check_reaction_kind(
+    report: &mut Report,
+    storage: &DefinitionStorage<ReactionDefinition>,
+    def: &ReactionDefinition,
+    kind: &reaction::Kind,
+    items: &DefinitionStorage<ItemDefinition>,
+    buildings: &DefinitionStorage<BuildingDefinition>,
+) {
+    match kind {
+        reaction::Kind::Item(name) if !exists(items, name) => {
+            report.error(
+                "reactions",
+                storage,
+                def,
+                format!("missing item '{}'", name),
+            );
+        }
+        reaction::Kind::Location { name, .. } if !exists(buildings, name) => {
+            report.error(
+                "reactions",
+                storage,
+                def,
+                format!("missing building '{}'", name),
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Walks every definition storage in `world` and collects each reference to a definition,
+/// material or sprite sheet which does not exist. Sprite sheets are only checked when
+/// `GraphicsSettings` is present.
+pub fn validate(world: &World) -> Result<(), ValidationErrors> {
+    let materials = world.fetch::<DefinitionStorage<MaterialDefinition>>();
+    let bodies = world.fetch::<DefinitionStorage<BodyDefinition>>();
+    let digestion = world.fetch::<DefinitionStorage<DigestionDefinition>>();
+    let races = world.fetch::<DefinitionStorage<RaceDefinition>>();
+    let behaviors = world.fetch::<DefinitionStorage<BehaviorDefinition>>();
+    let creatures = world.fetch::<DefinitionStorage<CreatureDefinition>>();
+    let actions = world.fetch::<DefinitionStorage<ActionDefinition>>();
+    let reactions = world.fetch::<DefinitionStorage<ReactionDefinition>>();
+    let buildings = world.fetch::<DefinitionStorage<BuildingDefinition>>();
+    let foliage = world.fetch::<DefinitionStorage<FoliageDefinition>>();
+    let items = world.fetch::<DefinitionStorage<ItemDefinition>>();
+    let traits = world.fetch::<DefinitionStorage<PsycheTraitDefinition>>();
+    let thoughts = world.fetch::<DefinitionStorage<ThoughtDefinition>>();
+    let graphics = world.try_fetch::<GraphicsSettings>();
+    let graphics = graphics.as_ref().map(|graphics| &**graphics);
+
+    let mut report = Report { errors: Vec::new() };
+
+    check_parents(&mut report, "materials", &materials);
+    check_parents(&mut report, "bodies", &bodies);
+    check_parents(&mut report, "digestion", &digestion);
+
+    for race in races.iter() {
+        if !exists(&bodies, &race.body) {
+            report.error(
+                "races",
+                &races,
+                race,
+                format!("missing body '{}'", race.body),
+            );
+        }
+        if let Some(name) = &race.behavior {
+            if !exists(&behaviors, name) {
+                report.error(
+                    "races",
+                    &races,
+                    race,
+                    format!("missing behavior '{}'", name),
+                );
+            }
+        }
+        for ((name, _), _) in &race.psyche {
+            if !exists(&traits, name) {
+                report.error(
+                    "races",
+                    &races,
+                    race,
+                    format!("missing psyche trait '{}'", name),
+                );
+            }
+        }
+    }
+
+    for body in bodies.iter() {
+        if let Some(name) = &body.digestion {
+            if !exists(&digestion, name) {
+                report.error(
+                    "bodies",
+                    &bodies,
+                    body,
+                    format!("missing digestion '{}'", name),
+                );
+            }
+        }
+        if let Some(sprite) = &body.sprite {
+            check_sprite(&mut report, "bodies", &bodies, body, sprite, graphics);
+        }
+        if let Some(graph) = &body.part_graph {
+            for part in graph.node_indices().map(|index| &graph[index]) {
+                for layer in &part.layers {
+                    if !exists(&materials, &layer.material.material.name) {
+                        report.error(
+                            "bodies",
+                            &bodies,
+                            body,
+                            format!(
+                                "part '{}' layer '{}' has missing material '{}'",
+                                part.name(),
+                                layer.name(),
+                                layer.material.material.name
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    for creature in creatures.iter() {
+        if let Some(name) = &creature.body {
+            if !exists(&bodies, name) {
+                report.error(
+                    "creatures",
+                    &creatures,
+                    creature,
+                    format!("missing body '{}'", name),
+                );
+            }
+        }
+        if let Some(name) = &creature.behavior {
+            if !exists(&behaviors, name) {
+                report.error(
+                    "creatures",
+                    &creatures,
+                    creature,
+                    format!("missing behavior '{}'", name),
+                );
+            }
+        }
+        if let Some(sprite) = &creature.sprite {
+            check_sprite(
+                &mut report,
+                "creatures",
+                &creatures,
+                creature,
+                sprite,
+                graphics,
+            );
+        }
+    }
+
+    for behavior in behaviors.iter() {
+        for decision in behavior.buckets.iter().flat_map(|b| b.decisions.iter()) {
+            if let Some(action) = &decision.action {
+                if !exists(&actions, action) {
+                    report.error(
+                        "behaviors",
+                        &behaviors,
+                        behavior,
+                        format!(
+                            "decision '{}' has missing action '{}'",
+                            decision.name, action
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    for action in actions.iter() {
+        if let Event::ActivateReaction(name) = &action.event {
+            if !exists(&reactions, name) {
+                report.error(
+                    "actions",
+                    &actions,
+                    action,
+                    format!("missing reaction '{}'", name),
+                );
+            }
+        }
+    }
+
+    for def in reactions.iter() {
+        for reagent in &def.reagents {
+            check_reaction_kind(
+                &mut report,
+                &reactions,
+                def,
+                &reagent.kind,
+                &items,
+                &buildings,
+            );
+            for material in &reagent.materials {
+                if let reaction::Material::Material(material) = material {
+                    check_material(
+                        &mut report,
+                        "reactions",
+                        &reactions,
+                        def,
+                        material,
+                        &materials,
+                    );
+                }
+            }
+        }
+
+        check_reaction_kind(
+            &mut report,
+            &reactions,
+            def,
+            &def.product.kind,
+            &items,
+            &buildings,
+        );
+        if let reaction::Material::Material(material) = &def.product.material {
+            check_material(
+                &mut report,
+                "reactions",
+                &reactions,
+                def,
+                material,
+                &materials,
+            );
+        }
+    }
+
+    for building in buildings.iter() {
+        check_sprite(
+            &mut report,
+            "buildings",
+            &buildings,
+            building,
+            &building.sprite,
+            graphics,
+        );
+    }
+
+    for def in foliage.iter() {
+        check_sprite(&mut report, "foliage", &foliage, def, &def.sprite, graphics);
+        for layer in &def.material_layers {
+            check_material(
+                &mut report,
+                "foliage",
+                &foliage,
+                def,
+                &layer.material,
+                &materials,
+            );
+        }
+    }
+
+    for item in items.iter() {
+        check_sprite(&mut report, "items", &items, item, &item.sprite, graphics);
+        if item.dimensions.is_none() {
+            report.error("items", &items, item, "missing dimensions".to_string());
+        }
+        if let Some(material) = &item.material {
+            check_material(&mut report, "items", &items, item, material, &materials);
+        }
+    }
+
+    for def in traits.iter() {
+        for effect in &def.effects {
+            if let PsycheTraitEffectKind::ThoughtEffect(effect) = effect {
+                if !exists(&thoughts, &effect.thought) {
+                    report.error(
+                        "psyche",
+                        &traits,
+                        def,
+                        format!("missing thought '{}'", effect.thought),
+                    );
+                }
+            }
+        }
+    }
+
+    if report.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors(report.errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defs::{
+        reaction::{Kind, Reagent},
+        sprites::SpriteRef,
+    };
+
+    fn load<T>(world: &mut World, folder: &str)
+    where
+        T: std::fmt::Debug + Clone + Definition + for<'a> serde::Deserialize<'a> + Send + Sync,
+        T: 'static,
+    {
+        world.insert(
+            DefinitionStorage::<T>::from_folder(format!("../resources/defs/{}", folder)).unwrap(),
+        );
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        load::<MaterialDefinition>(&mut world, "materials");
+        load::<BodyDefinition>(&mut world, "bodies");
+        load::<DigestionDefinition>(&mut world, "digestion");
+        load::<RaceDefinition>(&mut world, "races");
+        load::<BehaviorDefinition>(&mut world, "behaviors");
+        load::<CreatureDefinition>(&mut world, "creatures");
+        load::<ActionDefinition>(&mut world, "actions");
+        load::<ReactionDefinition>(&mut world, "reactions");
+        load::<BuildingDefinition>(&mut world, "buildings");
+        load::<FoliageDefinition>(&mut world, "foliage");
+        load::<ItemDefinition>(&mut world, "items");
+        load::<PsycheTraitDefinition>(&mut world, "psyche");
+        load::<ThoughtDefinition>(&mut world, "thoughts");
+        world
+    }
+
+    #[test]
+    fn shipped_definitions_are_valid() {
+        if let Err(errors) = validate(&world()) {
+            panic!("{}", errors);
+        }
+    }
+
+    #[test]
+    fn reports_every_broken_reference() {
+        let world = world();
+        {
+            let mut races = world.fetch_mut::<DefinitionStorage<RaceDefinition>>();
+            races.find_mut("Human").unwrap().body = "Nothing".to_string();
+
+            let mut reactions = world.fetch_mut::<DefinitionStorage<ReactionDefinition>>();
+            let reaction = reactions.find_mut("Cut planks").unwrap();
+            reaction.reagents.push(Reagent {
+                kind: Kind::Item("Unobtainium".to_string()),
+                ..Reagent::default()
+            });
+
+            let mut items = world.fetch_mut::<DefinitionStorage<ItemDefinition>>();
+            items.find_mut("Axe").unwrap().sprite = SpriteRef {
+                source: SpriteSource::RexPaint("axe".to_string()),
+                ..SpriteRef::default()
+            };
+        }
+
+        let errors = validate(&world).unwrap_err().0;
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors
+            .iter()
+            .any(|e| e.kind == "races" && e.definition == "Human" && e.file.is_some()));
+        assert!(errors
+            .iter()
+            .any(|e| e.kind == "reactions" && e.message.contains("Unobtainium")));
+        assert!(errors
+            .iter()
+            .any(|e| e.kind == "items" && e.message.contains("RexPaint")));
+    }
+}
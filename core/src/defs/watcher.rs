@@ -0,0 +1,109 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+use walkdir::WalkDir;
+
+/// Polls definition folders for files which were added, removed or modified since the last
+/// poll. Polling is throttled to `interval`, so it is cheap enough to call every frame.
+#[derive(Debug)]
+pub struct DefinitionWatcher {
+    roots: Vec<PathBuf>,
+    interval: Duration,
+    last_poll: Instant,
+    stamps: HashMap<PathBuf, (SystemTime, u64)>,
+}
+impl DefinitionWatcher {
+    pub fn new<P>(root: P, interval: Duration) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let roots = vec![root.as_ref().to_path_buf()];
+        Self {
+            stamps: Self::scan(&roots),
+            roots,
+            interval,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Also watches `root`, e.g. a folder of mods.
+    pub fn watch<P>(mut self, root: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.roots.push(root.as_ref().to_path_buf());
+        self.stamps = Self::scan(&self.roots);
+        self
+    }
+
+    pub fn roots(&self) -> &[PathBuf] { &self.roots }
+
+    /// The files changed since the last poll, or nothing if polled again within `interval`.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.interval {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let stamps = Self::scan(&self.roots);
+        let mut changed = stamps
+            .iter()
+            .filter(|(path, stamp)| self.stamps.get(*path) != Some(stamp))
+            .map(|(path, _)| path.clone())
+            .chain(
+                self.stamps
+                    .keys()
+                    .filter(|path| !stamps.contains_key(*path))
+                    .cloned(),
+            )
+            .collect::<Vec<_>>();
+        changed.sort();
+
+        self.stamps = stamps;
+        changed
+    }
+
+    fn scan(roots: &[PathBuf]) -> HashMap<PathBuf, (SystemTime, u64)> {
+        roots
+            .iter()
+            .flat_map(WalkDir::new)
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let meta = entry.metadata().ok()?;
+                if meta.file_type().is_file() {
+                    Some((entry.into_path(), (meta.modified().ok()?, meta.len())))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_changes() -> Result<(), failure::Error> {
+        let root = std::env::temp_dir().join(format!("defs_watcher_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("nested"))?;
+        let file = root.join("nested/test.ron");
+        std::fs::write(&file, "[]")?;
+
+        let mut watcher = DefinitionWatcher::new(&root, Duration::from_secs(0));
+        assert!(watcher.poll().is_empty());
+
+        std::fs::write(&file, "[(name: \"Changed\")]")?;
+        assert_eq!(watcher.poll(), vec![file.clone()]);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::remove_file(&file)?;
+        assert_eq!(watcher.poll(), vec![file]);
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{defs::property::InteractionType, shrinkwraprs::Shrinkwrap};
+use crate::{
+    defs::{property::InteractionType, psyche::SocialInteraction},
+    shrinkwraprs::Shrinkwrap,
+};
 use amethyst::{
     core::math::{Point3, Vector3},
     ecs::Entity,
@@ -101,8 +104,10 @@ pub struct ActionComplete(ActionEvent);
 )]
 pub enum Event {
     Interact(InteractionType),
+    Socialize(SocialInteraction),
     Move(MovementEvent),
     Pickup,
+    Ingest,
     ActivateReaction(String),
     Invalid,
 }
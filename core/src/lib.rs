@@ -22,6 +22,7 @@ pub mod components;
 pub mod embark;
 pub mod fsm;
 pub mod input;
+pub mod rng;
 pub mod utils;
 
 pub mod initializers;
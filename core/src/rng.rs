@@ -0,0 +1,23 @@
+use rand::{thread_rng, RngCore, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use shrinkwraprs::Shrinkwrap;
+
+/// The random number generator for everything which happens in the world, such as rolling new
+/// pawns. Seeding it from the world's seed makes a world play out the same way every time.
+#[derive(Shrinkwrap, Debug, Clone)]
+#[shrinkwrap(mutable)]
+pub struct WorldRng(pub XorShiftRng);
+impl WorldRng {
+    pub fn from_seed(seed: [u8; 16]) -> Self { Self(XorShiftRng::from_seed(seed)) }
+
+    /// Seeds a new generator from `rng`, so the two don't produce the same numbers.
+    pub fn from_rng<R>(rng: &mut R) -> Self
+    where
+        R: RngCore,
+    {
+        Self(XorShiftRng::from_rng(rng).unwrap())
+    }
+}
+impl Default for WorldRng {
+    fn default() -> Self { Self::from_rng(&mut thread_rng()) }
+}
@@ -1,6 +1,8 @@
 use crate::{
     defs::{
+        building::BuildingDefinition,
         material::{MaterialDefinition, MaterialLayerRef, MaterialLayerRefCompact, MaterialState},
+        property::MovementCosts,
         DefinitionStorage,
     },
     shrinkwraprs::Shrinkwrap,
@@ -17,13 +19,67 @@ use amethyst::{
     tiles::{iters::Region, CoordinateEncoder, DrawTiles2DBounds, Map, Tile, TileMap},
     window::ScreenDimensions,
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 pub mod region;
 pub mod world;
 
 pub const TILE_SCALE: u64 = 1000;
 
+/// Cost of moving straight across a tile with no cost overrides.
+pub const BASE_MOVEMENT_COST: u32 = 10;
+
+/// Cost of moving diagonally across a tile, relative to `BASE_MOVEMENT_COST`.
+pub const DIAGONAL_MOVEMENT_COST: u32 = 14;
+
+/// Movement costs of all materials and buildings, indexed by definition id. This is cheap to
+/// clone so pathing can carry it off the main thread.
+#[derive(Debug, Clone)]
+pub struct MovementCostTable {
+    materials: Arc<Vec<MovementCosts>>,
+    buildings: Arc<Vec<MovementCosts>>,
+    min_cost: u32,
+}
+impl Default for MovementCostTable {
+    fn default() -> Self {
+        Self {
+            materials: Arc::new(Vec::new()),
+            buildings: Arc::new(Vec::new()),
+            min_cost: BASE_MOVEMENT_COST,
+        }
+    }
+}
+impl MovementCostTable {
+    pub fn new(
+        materials: &DefinitionStorage<MaterialDefinition>,
+        buildings: &DefinitionStorage<BuildingDefinition>,
+    ) -> Self {
+        let materials: Vec<_> = materials.iter().map(|d| d.movement_costs.clone()).collect();
+        let buildings: Vec<_> = buildings.iter().map(|d| d.movement_costs.clone()).collect();
+
+        let min_cost = materials
+            .iter()
+            .chain(buildings.iter())
+            .flat_map(MovementCosts::iter)
+            .map(|(_, cost)| *cost)
+            .filter(|cost| *cost > 0)
+            .fold(BASE_MOVEMENT_COST, u32::min);
+
+        Self {
+            materials: Arc::new(materials),
+            buildings: Arc::new(buildings),
+            min_cost,
+        }
+    }
+
+    pub fn material(&self, id: u32) -> Option<&MovementCosts> { self.materials.get(id as usize) }
+
+    pub fn building(&self, id: u32) -> Option<&MovementCosts> { self.buildings.get(id as usize) }
+
+    /// The cheapest cost of entering any tile, used to keep pathing heuristics admissible.
+    pub fn min_cost(&self) -> u32 { self.min_cost }
+}
+
 #[derive(Default)]
 pub struct CurrentTileZ(pub u32, pub (f32, f32));
 
@@ -95,9 +151,7 @@ impl LayerBits {
             .iter()
             .fold(0, |acc, m| if *m == 0 { acc } else { acc + 1 })
     }
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
-    }
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
 
     #[inline]
     pub fn material(&self, layer_number: usize) -> u32 {
@@ -1,7 +1,7 @@
 use crate::{
-    defs::property::MovementFlags,
+    defs::{material::MaterialState, property::MovementFlags},
     settings::{GraphicsSettings, RegionMapRenderMode},
-    tiles::LayerBits,
+    tiles::{LayerBits, MovementCostTable, BASE_MOVEMENT_COST},
 };
 use amethyst::{
     core::{ecs::World, math::Point3},
@@ -18,10 +18,16 @@ bitflags_serial! {
     }
 }
 
+/// Fired whenever a region tile is modified, so systems holding a copy of the map can re-sync it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegionTileChangedEvent(pub Point3<u32>);
+
 #[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RegionTile {
     layers: LayerBits,
     pub flags: RegionTileFlags,
+    #[serde(default)]
+    building: Option<u32>,
 }
 
 impl RegionTile {
@@ -30,22 +36,70 @@ impl RegionTile {
             layers,
             //entities: None,
             flags: RegionTileFlags::empty(),
+            building: None,
         }
     }
 
-    pub fn passable(&self, flags: MovementFlags) -> bool {
-        self.movement_modifier(flags) > 0
+    pub fn building(&self) -> Option<u32> {
+        self.building
     }
 
-    pub fn movement_modifier(&self, _: MovementFlags) -> u32 {
-        if self.flags.contains(RegionTileFlags::HasBuilding)
-            || self.layers.len() == 4
-            || self.layers.is_empty()
-        {
-            0
-        } else {
-            1
+    pub fn set_building(&mut self, building: Option<u32>) {
+        self.flags.set(RegionTileFlags::HasBuilding, building.is_some());
+        self.building = building;
+    }
+
+    pub fn passable(&self, flags: MovementFlags, costs: &MovementCostTable) -> bool {
+        self.movement_modifier(flags, costs) > 0
+    }
+
+    /// Cost of moving onto this tile with any of the given movement modes, or 0 if none of them
+    /// can enter it.
+    pub fn movement_modifier(&self, flags: MovementFlags, costs: &MovementCostTable) -> u32 {
+        [MovementFlags::Walk, MovementFlags::Swim, MovementFlags::Fly]
+            .iter()
+            .filter(|mode| flags.contains(**mode))
+            .map(|mode| self.mode_cost(*mode, costs))
+            .filter(|cost| *cost > 0)
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn mode_cost(&self, mode: MovementFlags, costs: &MovementCostTable) -> u32 {
+        if self.flags.contains(RegionTileFlags::HasBuilding) {
+            return self
+                .building
+                .and_then(|id| costs.building(id))
+                .and_then(|c| c.get(mode))
+                .unwrap_or(0);
         }
+
+        // Fully solid
+        if self.layers.len() == 4 {
+            return 0;
+        }
+
+        let surface = if self.layers.is_empty() {
+            None
+        } else {
+            Some(self.layers.len() - 1)
+        };
+
+        let default = match surface.map(|n| self.layers.state(n)) {
+            None | Some(MaterialState::Gas) => MovementFlags::Fly,
+            Some(MaterialState::Liquid) => MovementFlags::Swim | MovementFlags::Fly,
+            Some(_) => MovementFlags::Walk | MovementFlags::Fly,
+        };
+        let default = if default.contains(mode) {
+            BASE_MOVEMENT_COST
+        } else {
+            0
+        };
+
+        surface
+            .and_then(|n| costs.material(self.layers.material(n)))
+            .and_then(|c| c.get(mode))
+            .unwrap_or(default)
     }
 }
 
@@ -54,7 +108,11 @@ impl Tile for RegionTile {
         match world.fetch::<GraphicsSettings>().map_render_mode {
             RegionMapRenderMode::Normal => Srgba::new(1.0, 1.0, 1.0, 1.0),
             RegionMapRenderMode::Pathing => {
-                if self.passable(MovementFlags::Walk) {
+                let passable = world.try_fetch::<MovementCostTable>().map_or_else(
+                    || self.passable(MovementFlags::Walk, &MovementCostTable::default()),
+                    |costs| self.passable(MovementFlags::Walk, &costs),
+                );
+                if passable {
                     Srgba::new(0.0, 1.0, 0.0, 1.0)
                 } else {
                     Srgba::new(1.0, 0.0, 0.0, 1.0)
@@ -4,8 +4,8 @@ use derivative::Derivative;
 use rayon::prelude::*;
 use std::{fmt::Debug, hash::Hash, marker::PhantomData};
 
-pub trait State: Clone + Debug + Send + Sync {}
-impl<T> State for T where T: Clone + Debug + Send + Sync {}
+pub trait State: Clone + Debug + Hash + Eq + Send + Sync {}
+impl<T> State for T where T: Clone + Debug + Hash + Eq + Send + Sync {}
 
 pub trait Condition<S: State>: Debug + Send + Sync {
     fn unique_id(&self) -> u64;
@@ -28,6 +28,9 @@ pub trait Action<S: State>: Debug + Send + Sync {
 
     fn apply(&self, state: &mut S);
 
+    /// The cost of performing this action, which the planner minimizes over the whole plan.
+    fn cost(&self) -> u32 { 1 }
+
     fn check(&self, state: &S) -> bool {
         for condition in self.conditions() {
             if !condition.check(state) {
@@ -58,73 +61,61 @@ impl<S: State> Hash for dyn Action<S> {
     fn hash<H: std::hash::Hasher>(&self, hasher: &mut H) { self.unique_id().hash(hasher) }
 }
 
+/// Why the planner gave up without a plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanFailure {
+    /// Every reachable state was searched, and none satisfies the goal.
+    NoPlan,
+    /// No plan exists within `PlanLimits::max_depth` actions, a longer one might.
+    DepthExceeded,
+    /// More than `PlanLimits::max_nodes` states were expanded before a plan was found.
+    NodeBudgetExceeded,
+}
+
+/// Bounds on how far the planner searches before giving up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlanLimits {
+    pub max_depth: usize,
+    pub max_nodes: usize,
+}
+impl Default for PlanLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 16,
+            max_nodes: 4096,
+        }
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Debug(bound = ""), Clone(bound = ""))]
-struct PlanNode<'a, S: State> {
-    action: Option<&'a dyn Action<S>>,
+struct PlanNode<S: State> {
     state: S,
+    depth: usize,
 }
-impl<'a, S: State> Hash for PlanNode<'a, S> {
-    fn hash<H: std::hash::Hasher>(&self, hasher: &mut H) {
-        if let Some(action) = &self.action {
-            action.unique_id().hash(hasher)
-        } else {
-            std::u64::MAX.hash(hasher)
-        }
-    }
+// Nodes are keyed on their state alone, so a state is only searched once however many ways it
+// can be reached. Depth is ignored for the same reason. Which action led to each state is worked
+// out once the plan is found, see `Planner::action_between`.
+impl<S: State> Hash for PlanNode<S> {
+    fn hash<H: std::hash::Hasher>(&self, hasher: &mut H) { self.state.hash(hasher); }
 }
-impl<'a, S: State> PartialEq<PlanNode<'a, S>> for PlanNode<'a, S> {
-    fn eq(&self, other: &PlanNode<S>) -> bool {
-        if let Some(action) = &self.action {
-            if let Some(other_action) = &other.action {
-                action.unique_id().eq(&other_action.unique_id())
-            } else {
-                false
-            }
-        } else {
-            false
-        }
-    }
+impl<S: State> PartialEq<PlanNode<S>> for PlanNode<S> {
+    fn eq(&self, other: &PlanNode<S>) -> bool { self.state == other.state }
 }
-impl<'a, S: State> Eq for PlanNode<'a, S> {}
+impl<S: State> Eq for PlanNode<S> {}
 
-impl<'a, S: State> PlanNode<'a, S> {
-    fn new(state: S) -> Self {
-        Self {
-            action: None,
-            state,
-        }
-    }
-    fn with_action(action: &'a dyn Action<S>, state: S) -> Self {
-        Self {
-            action: Some(action),
-            state,
-        }
-    }
-    #[allow(clippy::clone_double_ref)]
-    fn neighbors<'b>(
-        &self,
-        actions: &'b [&'b dyn Action<S>],
-        goal: &'b [&'b dyn Condition<S>],
-    ) -> Vec<(PlanNode<'b, S>, usize)> {
+impl<S: State> PlanNode<S> {
+    fn new(state: S, depth: usize) -> Self { Self { state, depth } }
+
+    fn neighbors(&self, actions: &[&dyn Action<S>]) -> Vec<(PlanNode<S>, u32)> {
         actions
             .par_iter()
             .filter_map(|action| {
-                if let Some(self_action) = &self.action {
-                    if action.unique_id() == self_action.unique_id() {
-                        return None;
-                    }
-                }
-
-                let mut state_copy = self.state.clone();
-                action.apply(&mut state_copy);
+                if action.check(&self.state) {
+                    let mut state_copy = self.state.clone();
+                    action.apply(&mut state_copy);
 
-                if action.check(&state_copy) {
-                    let distance = Self::distance(&state_copy, &Some(*action), goal);
-                    Some((
-                        PlanNode::with_action((*action).clone(), state_copy),
-                        distance,
-                    ))
+                    Some((PlanNode::new(state_copy, self.depth + 1), action.cost()))
                 } else {
                     None
                 }
@@ -132,19 +123,17 @@ impl<'a, S: State> PlanNode<'a, S> {
             .collect::<Vec<_>>()
     }
 
-    fn distance(state: &S, action: &Option<&dyn Action<S>>, goal: &[&dyn Condition<S>]) -> usize {
-        if action.is_some() {
-            goal.par_iter()
-                .filter_map(|condition| {
-                    if !condition.check(&state) {
-                        Some(1)
-                    } else {
-                        None
-                    }
-                })
-                .sum()
+    fn satisfies(state: &S, goal: &[&dyn Condition<S>]) -> bool {
+        goal.iter().all(|condition| condition.check(state))
+    }
+
+    /// Any unsatisfied goal takes at least one more action, so the cheapest action cost never
+    /// overestimates the remaining cost.
+    fn heuristic(state: &S, goal: &[&dyn Condition<S>], min_cost: u32) -> u32 {
+        if Self::satisfies(state, goal) {
+            0
         } else {
-            goal.len()
+            min_cost
         }
     }
 }
@@ -154,45 +143,92 @@ pub struct Planner<S: State> {
     _marker: PhantomData<S>,
 }
 impl<S: State> Planner<S> {
-    #[allow(clippy::clone_double_ref)]
     pub fn plan<'b>(
         state: &S,
         goal: &'b [&'b dyn Condition<S>],
         actions: &'b [&'b dyn Action<S>],
-    ) -> Option<Vec<&'b dyn Action<S>>> {
+    ) -> Result<Vec<&'b dyn Action<S>>, PlanFailure> {
+        Self::plan_with_limits(state, goal, actions, PlanLimits::default())
+    }
+
+    pub fn plan_with_limits<'b>(
+        state: &S,
+        goal: &'b [&'b dyn Condition<S>],
+        actions: &'b [&'b dyn Action<S>],
+        limits: PlanLimits,
+    ) -> Result<Vec<&'b dyn Action<S>>, PlanFailure> {
         log::trace!("Beginning plan @ [{:?}]", goal);
 
         use pathfinding::prelude::astar;
 
-        let start = PlanNode::<S>::new(state.clone());
+        let start = PlanNode::<S>::new(state.clone(), 0);
+        let min_cost = actions.iter().map(|a| a.cost()).min().unwrap_or(0);
+
+        let mut expanded = 0;
+        let mut depth_exceeded = false;
+        let mut budget_exceeded = false;
 
-        if let Some(plan) = astar(
+        let plan = astar(
             &start,
-            |ref node| node.neighbors(actions, goal),
-            |ref node| PlanNode::distance(&node.state, &node.action, goal),
-            |ref node| PlanNode::distance(&node.state, &node.action, goal) == 0,
-        ) {
-            Some(
-                plan.0
-                    .iter()
-                    .skip(1)
-                    .map(|node| node.action.as_ref().unwrap().clone())
-                    .collect::<Vec<_>>(),
-            )
+            |node| {
+                if expanded >= limits.max_nodes {
+                    budget_exceeded = true;
+                    return Vec::new();
+                }
+                expanded += 1;
+
+                if node.depth >= limits.max_depth {
+                    depth_exceeded = true;
+                    return Vec::new();
+                }
+                node.neighbors(actions)
+            },
+            |node| PlanNode::heuristic(&node.state, goal, min_cost),
+            |node| PlanNode::satisfies(&node.state, goal),
+        );
+
+        if let Some(plan) = plan {
+            Ok(plan
+                .0
+                .windows(2)
+                .map(|step| Self::action_between(&step[0].state, &step[1].state, actions))
+                .collect::<Vec<_>>())
+        } else if budget_exceeded {
+            Err(PlanFailure::NodeBudgetExceeded)
+        } else if depth_exceeded {
+            Err(PlanFailure::DepthExceeded)
         } else {
-            None
+            Err(PlanFailure::NoPlan)
         }
     }
+
+    /// The cheapest action taking `from` to `to`. The search only ever steps between states
+    /// through an action, so there always is one, and the cheapest is the one it costed the step
+    /// with.
+    fn action_between<'b>(from: &S, to: &S, actions: &'b [&'b dyn Action<S>]) -> &'b dyn Action<S> {
+        actions
+            .iter()
+            .filter(|action| action.check(from))
+            .filter(|action| {
+                let mut state = from.clone();
+                action.apply(&mut state);
+                state == *to
+            })
+            .min_by_key(|action| action.cost())
+            .copied()
+            .unwrap()
+    }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[derive(Default, Debug, Clone)]
+    #[derive(Default, Debug, Clone, Hash, PartialEq, Eq)]
     pub struct TestState {
         value_one: bool,
         value_two: bool,
         value_three: bool,
+        counter: u32,
     }
 
     #[derive(Default, Debug, Clone)]
@@ -275,7 +311,146 @@ mod tests {
             ..Default::default()
         };
 
-        let plan = Planner::plan(&state, &goal, &actions);
+        let plan = Planner::plan(&state, &goal, &actions).unwrap();
         println!("Plan = {:?}", plan);
+
+        assert_eq!(
+            plan.iter().map(|a| a.unique_id()).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    /// An action with a configurable cost and effect, usable without any conditions.
+    #[derive(Debug)]
+    pub struct TestCostAction {
+        id: u64,
+        cost: u32,
+        effect: fn(&mut TestState),
+    }
+    impl Action<TestState> for TestCostAction {
+        fn unique_id(&self) -> u64 { self.id }
+
+        fn conditions(&self) -> &[&dyn Condition<TestState>] { &[] }
+
+        fn apply(&self, state: &mut TestState) { (self.effect)(state) }
+
+        fn cost(&self) -> u32 { self.cost }
+    }
+
+    #[test]
+    fn prefers_cheaper_plan() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let slow = TestCostAction {
+            id: 1,
+            cost: 10,
+            effect: |state| state.value_three = true,
+        };
+        let fast_one = TestCostAction {
+            id: 2,
+            cost: 2,
+            effect: |state| state.value_two = true,
+        };
+        let fast_two = TestCostAction {
+            id: 3,
+            cost: 2,
+            effect: |state| {
+                if state.value_two {
+                    state.value_three = true
+                }
+            },
+        };
+        let actions: [&dyn Action<TestState>; 3] = [&slow, &fast_one, &fast_two];
+        let goal: [&dyn Condition<TestState>; 1] = [&TestConditionThree::default()];
+
+        let plan = Planner::plan(&TestState::default(), &goal, &actions).unwrap();
+        assert_eq!(
+            plan.iter().map(|a| a.unique_id()).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+
+    #[test]
+    fn plans_through_cheapest_route_to_a_state() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // Both routes reach the same state before finishing, the slow one first
+        let slow = TestCostAction {
+            id: 1,
+            cost: 10,
+            effect: |state| state.value_two = true,
+        };
+        let prepare = TestCostAction {
+            id: 2,
+            cost: 1,
+            effect: |state| state.counter = 1,
+        };
+        let fast = TestCostAction {
+            id: 3,
+            cost: 1,
+            effect: |state| {
+                if state.counter == 1 {
+                    state.counter = 0;
+                    state.value_two = true;
+                }
+            },
+        };
+        let finish = TestCostAction {
+            id: 4,
+            cost: 1,
+            effect: |state| state.value_three = state.value_two,
+        };
+        let actions: [&dyn Action<TestState>; 4] = [&slow, &prepare, &fast, &finish];
+        let goal: [&dyn Condition<TestState>; 1] = [&TestConditionThree::default()];
+
+        let plan = Planner::plan(&TestState::default(), &goal, &actions).unwrap();
+        assert_eq!(
+            plan.iter().map(|a| a.unique_id()).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn limits_unbounded_search() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // Every application reaches a new state, so without limits this would never end
+        let up = TestCostAction {
+            id: 1,
+            cost: 1,
+            effect: |state| state.counter += 1,
+        };
+        let up_again = TestCostAction {
+            id: 2,
+            cost: 1,
+            effect: |state| state.counter += 2,
+        };
+        let actions: [&dyn Action<TestState>; 2] = [&up, &up_again];
+        let goal: [&dyn Condition<TestState>; 1] = [&TestConditionThree::default()];
+
+        let limits = PlanLimits {
+            max_depth: 8,
+            max_nodes: std::usize::MAX,
+        };
+        assert_eq!(
+            Planner::plan_with_limits(&TestState::default(), &goal, &actions, limits).unwrap_err(),
+            PlanFailure::DepthExceeded
+        );
+
+        let limits = PlanLimits {
+            max_depth: std::usize::MAX,
+            max_nodes: 64,
+        };
+        assert_eq!(
+            Planner::plan_with_limits(&TestState::default(), &goal, &actions, limits).unwrap_err(),
+            PlanFailure::NodeBudgetExceeded
+        );
+
+        // A finite search which simply has no answer
+        let actions: [&dyn Action<TestState>; 0] = [];
+        assert_eq!(
+            Planner::plan(&TestState::default(), &goal, &actions).unwrap_err(),
+            PlanFailure::NoPlan
+        );
     }
 }
@@ -10,26 +10,50 @@ where
     fn range(&self) -> R;
     fn transform(&self, input: f32) -> f32;
 
-    fn normalize(&self, input: f32) -> f32 {
-        let range = self.range();
-
-        let start = match range.start_bound() {
-            Bound::Unbounded => std::f32::MIN,
-            Bound::Included(start) => *start,
-            Bound::Excluded(start) => *start + 1.0,
-        };
+    fn normalize(&self, input: f32) -> f32 { normalize(&self.range(), input) }
+}
 
-        let end = match range.end_bound() {
-            Bound::Unbounded => std::f32::MAX,
-            Bound::Included(end) => *end,
-            Bound::Excluded(end) => *end - 1.0,
-        };
+/// Maps `input` onto `0.0..=1.0` over `range`, clamping at bounded ends. Ranges are continuous, so
+/// excluded bounds normalize the same as included ones. An unbounded end is approached but never
+/// passed: `start..` rises from 0.0 at `start` as `1 - 1 / (1 + (input - start))`, `..end` rises
+/// the same way towards 1.0 at `end`, and `..` crosses 0.5 at zero.
+pub fn normalize<R>(range: &R, input: f32) -> f32
+where
+    R: RangeBounds<f32>,
+{
+    fn value(bound: Bound<&f32>) -> Option<f32> {
+        match bound {
+            Bound::Included(value) | Bound::Excluded(value) => Some(*value),
+            Bound::Unbounded => None,
+        }
+    }
 
-        (input - start) / (end - start)
+    match (value(range.start_bound()), value(range.end_bound())) {
+        (Some(start), Some(end)) => {
+            if (end - start).abs() < std::f32::EPSILON {
+                if input < start {
+                    0.0
+                } else {
+                    1.0
+                }
+            } else {
+                ((input - start) / (end - start)).max(0.0).min(1.0)
+            }
+        }
+        (Some(start), None) => 1.0 - 1.0 / (1.0 + (input - start).max(0.0)),
+        (None, Some(end)) => 1.0 / (1.0 + (end - input).max(0.0)),
+        (None, None) => {
+            let half = 0.5 - 0.5 / (1.0 + input.abs());
+            if input < 0.0 {
+                0.5 - half
+            } else {
+                0.5 + half
+            }
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Linear<R>
 where
     R: RangeBounds<f32> + Debug + Send + Sync,
@@ -52,7 +76,7 @@ where
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Exponential<R>
 where
     R: RangeBounds<f32> + Debug + Send + Sync,
@@ -74,7 +98,7 @@ where
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ExponentialDecay<R>
 where
     R: RangeBounds<f32> + Debug + Send + Sync,
@@ -96,6 +120,230 @@ where
     }
 }
 
+/// The logistic function `1 / (1 + e^(-steepness * (x - midpoint)))`, an S-shaped curve which
+/// rises through 0.5 at `midpoint`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Logistic<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync,
+{
+    pub range: R,
+    pub steepness: f32,
+    pub midpoint: f32,
+}
+impl<R> Logistic<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync,
+{
+    /// A sigmoid centered on the range, close to 0.0 and 1.0 at either end of it.
+    pub fn sigmoid(range: R) -> Self {
+        Self {
+            range,
+            steepness: 10.0,
+            midpoint: 0.5,
+        }
+    }
+}
+impl<R> Curve<R> for Logistic<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync + Clone,
+{
+    #[inline]
+    fn range(&self) -> R { self.range.clone() }
+
+    #[inline]
+    fn transform(&self, input: f32) -> f32 {
+        let input = self.normalize(input);
+        1.0 / (1.0 + (-self.steepness * (input - self.midpoint)).exp())
+    }
+}
+
+/// The inverse of a `Logistic` centered on 0.5: flat through the middle of the range and steep at
+/// either end. The result is clamped to `0.0..=1.0`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Logit<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync,
+{
+    pub range: R,
+    pub steepness: f32,
+}
+impl<R> Curve<R> for Logit<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync + Clone,
+{
+    #[inline]
+    fn range(&self) -> R { self.range.clone() }
+
+    #[inline]
+    fn transform(&self, input: f32) -> f32 {
+        let input = self
+            .normalize(input)
+            .max(std::f32::EPSILON)
+            .min(1.0 - std::f32::EPSILON);
+        (0.5 + (input / (1.0 - input)).ln() / self.steepness)
+            .max(0.0)
+            .min(1.0)
+    }
+}
+
+/// Hermite interpolation `3x^2 - 2x^3`, easing in and out of either end of the range.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SmoothStep<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync,
+{
+    pub range: R,
+}
+impl<R> Curve<R> for SmoothStep<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync + Clone,
+{
+    #[inline]
+    fn range(&self) -> R { self.range.clone() }
+
+    #[inline]
+    fn transform(&self, input: f32) -> f32 {
+        let input = self.normalize(input);
+        input * input * (3.0 - 2.0 * input)
+    }
+}
+
+/// Reciprocal falloff `1 / (1 + magnitude * x)`: 1.0 at the start of the range, dropping quickly
+/// and then tailing off.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Inverse<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync,
+{
+    pub range: R,
+    pub magnitude: f32,
+}
+impl<R> Curve<R> for Inverse<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync + Clone,
+{
+    #[inline]
+    fn range(&self) -> R { self.range.clone() }
+
+    #[inline]
+    fn transform(&self, input: f32) -> f32 {
+        let input = self.normalize(input);
+        1.0 / (1.0 + self.magnitude * input)
+    }
+}
+
+/// `high` once the normalized input reaches `threshold`, otherwise `low`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Step<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync,
+{
+    pub range: R,
+    pub threshold: f32,
+    pub low: f32,
+    pub high: f32,
+}
+impl<R> Step<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync,
+{
+    /// A switch from 0.0 to 1.0 at `threshold`.
+    pub fn threshold(range: R, threshold: f32) -> Self {
+        Self {
+            range,
+            threshold,
+            low: 0.0,
+            high: 1.0,
+        }
+    }
+}
+impl<R> Curve<R> for Step<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync + Clone,
+{
+    #[inline]
+    fn range(&self) -> R { self.range.clone() }
+
+    #[inline]
+    fn transform(&self, input: f32) -> f32 {
+        if self.normalize(input) >= self.threshold {
+            self.high
+        } else {
+            self.low
+        }
+    }
+}
+
+/// Straight lines between control points, given as `(x, y)` over the normalized input and sorted
+/// by `x`. Inputs before the first point or after the last take that point's `y`. Points are
+/// sorted when the curve is made or deserialized.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PiecewiseLinear<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync,
+{
+    pub range: R,
+    #[serde(deserialize_with = "deserialize_points")]
+    pub points: Vec<(f32, f32)>,
+}
+impl<R> PiecewiseLinear<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync,
+{
+    pub fn new(range: R, mut points: Vec<(f32, f32)>) -> Self {
+        sort_points(&mut points);
+        Self { range, points }
+    }
+}
+impl<R> Curve<R> for PiecewiseLinear<R>
+where
+    R: RangeBounds<f32> + Debug + Send + Sync + Clone,
+{
+    #[inline]
+    fn range(&self) -> R { self.range.clone() }
+
+    fn transform(&self, input: f32) -> f32 {
+        let input = self.normalize(input);
+
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return 0.0,
+        };
+        if input <= first.0 {
+            return first.1;
+        }
+        if input >= last.0 {
+            return last.1;
+        }
+
+        self.points
+            .windows(2)
+            .find(|pair| input <= pair[1].0)
+            .map_or(last.1, |pair| {
+                let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+                if (x1 - x0).abs() < std::f32::EPSILON {
+                    y1
+                } else {
+                    y0 + (y1 - y0) * (input - x0) / (x1 - x0)
+                }
+            })
+    }
+}
+
+fn sort_points(points: &mut Vec<(f32, f32)>) {
+    points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+fn deserialize_points<'de, D>(deserializer: D) -> Result<Vec<(f32, f32)>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut points = <Vec<(f32, f32)> as serde::Deserialize>::deserialize(deserializer)?;
+    sort_points(&mut points);
+    Ok(points)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,7 +355,7 @@ mod tests {
         let root = BitMapBackend::new("sigmoid.png", (640, 480)).into_drawing_area();
         root.fill(&WHITE)?;
         let mut chart = ChartBuilder::on(&root)
-            .caption("1 / 1 + e^x", ("Arial", 50).into_font())
+            .caption("1 / (1 + e^-x)", ("Arial", 50).into_font())
             .margin(5)
             .x_label_area_size(30)
             .y_label_area_size(30)
@@ -115,13 +363,10 @@ mod tests {
 
         chart.configure_mesh().draw()?;
 
-        let curve = curves::Exponential {
-            range: std::ops::Range {
-                start: 0.0,
-                end: 256.0,
-            },
-            power: 2.0,
-        };
+        let curve = curves::Logistic::sigmoid(std::ops::Range {
+            start: 0.0,
+            end: 256.0,
+        });
 
         chart
             .draw_series(LineSeries::new(
@@ -141,4 +386,128 @@ mod tests {
 
         Ok(())
     }
+
+    fn approx(a: f32, b: f32) -> bool { (a - b).abs() < 0.001 }
+
+    #[test]
+    fn normalize_ranges() {
+        assert!(approx(normalize(&(0.0..10.0), 5.0), 0.5));
+        assert!(approx(normalize(&(0.0..=10.0), 10.0), 1.0));
+        assert!(approx(normalize(&(0.0..10.0), 20.0), 1.0));
+        assert!(approx(normalize(&(0.0..10.0), -5.0), 0.0));
+        assert!(approx(normalize(&(5.0..5.0), 5.0), 1.0));
+
+        assert!(approx(normalize(&(2.0..), 1.0), 0.0));
+        assert!(approx(normalize(&(2.0..), 3.0), 0.5));
+        assert!(approx(normalize(&(2.0..), 5.0), 0.75));
+        assert!(approx(normalize(&(..2.0), 1.0), 0.5));
+        assert!(approx(normalize(&(..2.0), 8.0), 1.0));
+        assert!(approx(normalize(&(..), 0.0), 0.5));
+        assert!(approx(normalize(&(..), 1.0), 0.75));
+        assert!(approx(normalize(&(..), -1.0), 0.25));
+
+        let bounds = (Bound::Excluded(0.0), Bound::Excluded(4.0));
+        assert!(approx(normalize(&bounds, 1.0), 0.25));
+    }
+
+    #[test]
+    fn shaped_curves() {
+        let sigmoid = Logistic::sigmoid(0.0..=1.0);
+        assert!(approx(sigmoid.transform(0.5), 0.5));
+        assert!(sigmoid.transform(0.0) < 0.01);
+        assert!(sigmoid.transform(1.0) > 0.99);
+
+        let logit = Logit {
+            range: 0.0..=1.0,
+            steepness: 10.0,
+        };
+        assert!(approx(logit.transform(sigmoid.transform(0.3)), 0.3));
+        assert!(approx(logit.transform(0.0), 0.0));
+
+        let smooth = SmoothStep { range: 0.0..=2.0 };
+        assert!(approx(smooth.transform(1.0), 0.5));
+        assert!(approx(smooth.transform(0.5), 0.15625));
+
+        let inverse = Inverse {
+            range: 0.0..=1.0,
+            magnitude: 3.0,
+        };
+        assert!(approx(inverse.transform(0.0), 1.0));
+        assert!(approx(inverse.transform(1.0), 0.25));
+
+        let step = Step::threshold(0.0..100.0, 0.5);
+        assert!(approx(step.transform(49.0), 0.0));
+        assert!(approx(step.transform(50.0), 1.0));
+
+        let piecewise = PiecewiseLinear::new(0.0..=10.0, vec![(1.0, 0.0), (0.0, 1.0), (0.5, 0.8)]);
+        assert!(approx(piecewise.transform(0.0), 1.0));
+        assert!(approx(piecewise.transform(2.5), 0.9));
+        assert!(approx(piecewise.transform(7.5), 0.4));
+        assert!(approx(piecewise.transform(20.0), 0.0));
+    }
+
+    #[test]
+    fn unbounded_curves() {
+        let inputs = [
+            std::f32::MIN,
+            -1_000_000.0,
+            -1.0,
+            0.0,
+            1.0,
+            1_000_000.0,
+            std::f32::MAX,
+        ];
+        let within = |curve: &dyn Fn(f32) -> f32| {
+            inputs
+                .iter()
+                .all(|input| (0.0..=1.0).contains(&curve(*input)))
+        };
+
+        let linear = Linear {
+            range: 0.0..,
+            slope: 1.0,
+            intercept: 0.0,
+        };
+        assert!(approx(linear.transform(1.0), 0.5));
+        assert!(linear.transform(1_000_000.0) > 0.99);
+        assert!(within(&|input| linear.transform(input)));
+
+        let exponential = Exponential {
+            range: ..10.0,
+            power: 2.0,
+        };
+        assert!(approx(exponential.transform(10.0), 1.0));
+        assert!(approx(exponential.transform(9.0), 0.25));
+        assert!(within(&|input| exponential.transform(input)));
+
+        let decay = ExponentialDecay {
+            range: 0.0..,
+            magnitude: 0.1,
+        };
+        assert!(approx(decay.transform(0.0), 1.0));
+        assert!(decay.transform(std::f32::MAX) >= 0.1);
+        assert!(within(&|input| decay.transform(input)));
+
+        let sigmoid = Logistic::sigmoid(..);
+        assert!(approx(sigmoid.transform(0.0), 0.5));
+        assert!(within(&|input| sigmoid.transform(input)));
+
+        let smooth = SmoothStep { range: .. };
+        assert!(approx(smooth.transform(0.0), 0.5));
+        assert!(within(&|input| smooth.transform(input)));
+
+        let piecewise = PiecewiseLinear::new(0.0.., vec![(0.0, 0.0), (1.0, 1.0)]);
+        assert!(approx(piecewise.transform(3.0), 0.75));
+        assert!(within(&|input| piecewise.transform(input)));
+    }
+
+    #[test]
+    fn deserialized_points_are_sorted() {
+        let piecewise: PiecewiseLinear<std::ops::Range<f32>> = ron::de::from_str(
+            "(range: (start: 0.0, end: 10.0), points: [(1.0, 0.0), (0.0, 1.0), (0.5, 0.8)])",
+        )
+        .unwrap();
+        assert_eq!(piecewise.points, vec![(0.0, 1.0), (0.5, 0.8), (1.0, 0.0)]);
+        assert!(approx(piecewise.transform(2.5), 0.9));
+    }
 }
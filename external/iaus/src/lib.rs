@@ -291,13 +291,52 @@ mod tests {
         let state = TestState::default();
 
         let score = decisions[0].score(&state);
-        assert_eq!(score, 0.46754268);
+        assert_eq!(score, 0.46364412);
 
         let bucket = Arc::new(
             Bucket::<TestState, u32>::new("bucket", 1).with_decision(decisions[0].clone()),
         );
 
         let resolver = SimpleResolver::with_buckets(&[bucket.clone()]);
-        println!("res = {:?}", resolver.get_top_decision(&state));
+        let (top_bucket, _, top_score) = resolver.get_top_decision(&state).unwrap();
+        assert_eq!(top_bucket.name(), Some("bucket"));
+        assert_eq!(top_score, score);
+    }
+
+    #[test]
+    fn top_decision_prefers_priority() {
+        let constant = |value: f32| {
+            Arc::new(<ConsiderationFn<TestState>>::new(
+                None,
+                None,
+                None,
+                Box::new(move |_, _: &TestState| value),
+            )) as Arc<dyn Consideration<TestState>>
+        };
+        let decision = |name: &str, value: f32| {
+            Arc::new(SimpleDecision::new(name).with_consideration(constant(value)))
+                as Arc<dyn Decision<TestState>>
+        };
+
+        let low = Arc::new(
+            Bucket::<TestState, u32>::new("low", 1)
+                .with_decision(decision("strong", 0.9))
+                .with_decision(decision("stronger", 1.0)),
+        );
+        let high = Arc::new(
+            Bucket::<TestState, u32>::new("high", 2)
+                .with_decision(decision("weak", 0.2))
+                .with_decision(decision("weaker", 0.1)),
+        );
+
+        let state = TestState::default();
+        let resolver = SimpleResolver::with_buckets(&[low.clone(), high.clone()]);
+        let (bucket, _, score) = resolver.get_top_decision(&state).unwrap();
+        assert_eq!(bucket.name(), Some("high"));
+        assert!((score - 0.2).abs() < std::f32::EPSILON);
+
+        let empty =
+            SimpleResolver::with_buckets(&[Arc::new(Bucket::<TestState, u32>::new("empty", 3))]);
+        assert!(empty.get_top_decision(&state).is_none());
     }
 }
@@ -69,12 +69,20 @@ where
             .collect::<Vec<_>>()
     }
 
-    pub fn get_top_decision(&self, state: &S) -> (Arc<Bucket<S, P>>, Arc<dyn Decision<S>>, f32) {
-        let full_execution = self.get_decisions(state);
-        (
-            full_execution[0].0.clone(),
-            full_execution[0].1[0].0.clone(),
-            full_execution[0].1[0].1,
-        )
+    /// The best decision across every bucket. Buckets are tried from the highest priority down,
+    /// and the first with any decision scoring above zero wins; within it the highest score wins.
+    pub fn get_top_decision(
+        &self,
+        state: &S,
+    ) -> Option<(Arc<Bucket<S, P>>, Arc<dyn Decision<S>>, f32)> {
+        self.get_decisions(state)
+            .into_iter()
+            .max_by(|(a, _), (b, _)| a.priority.cmp(&b.priority))
+            .and_then(|(bucket, decisions)| {
+                decisions
+                    .into_iter()
+                    .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+                    .map(|(decision, score)| (bucket, decision, score))
+            })
     }
 }
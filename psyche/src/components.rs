@@ -1,14 +1,127 @@
 use core::{
     amethyst::ecs::{Component, VecStorage},
-    defs::psyche::{NeedsContainer, PsycheTraitId},
+    defs::{
+        psyche::{NeedsContainer, PsycheTraitDefinition, PsycheTraitEffectKind, PsycheTraitId},
+        race::RaceDefinition,
+        DefinitionStorage, Named,
+    },
     fnv::FnvHashMap,
+    rand::{seq::SliceRandom, Rng},
+    rand_distr::{Distribution, Normal},
     shrinkwraprs::Shrinkwrap,
     smallvec::SmallVec,
 };
 
+pub use crate::{mood::MoodComponent, social::RelationshipsComponent};
+
+/// How far a generated trait's intensity strays from the one in its race's table, and the
+/// weakest it can be.
+const INTENSITY_DEVIATION: f32 = 0.25;
+const MIN_INTENSITY: f32 = 0.1;
+
+/// A trait a creature has. `intensity` scales its effects, 1.0 being an ordinary case of it, and
+/// `accums` holds the time each of its `Decay` effects has built up, by effect index.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PersonalityTrait {
+    pub active: bool,
+    pub id: PsycheTraitId,
+    pub intensity: f32,
+    pub accums: FnvHashMap<usize, u32>,
+}
+impl PersonalityTrait {
+    pub fn new(id: PsycheTraitId, intensity: f32) -> Self {
+        Self {
+            active: true,
+            id,
+            intensity,
+            accums: FnvHashMap::default(),
+        }
+    }
+}
+
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct PersonalityComponent {
-    pub traits: SmallVec<[(bool, PsycheTraitId, FnvHashMap<usize, u32>); 32]>,
+    pub traits: SmallVec<[PersonalityTrait; 32]>,
+}
+impl PersonalityComponent {
+    /// Rolls a personality from the race's psyche table of `((trait, intensity), chance)`.
+    /// Entries are rolled in a random order, and skipped once a trait from the same exclusive
+    /// group has been picked.
+    pub fn generate<R>(
+        rng: &mut R,
+        def: &RaceDefinition,
+        trait_defs: &DefinitionStorage<PsycheTraitDefinition>,
+    ) -> Self
+    where
+        R: Rng,
+    {
+        let mut table = def.psyche.iter().collect::<Vec<_>>();
+        table.shuffle(rng);
+
+        let mut groups = Vec::new();
+        let mut traits = SmallVec::new();
+        for ((name, intensity), chance) in table {
+            let (id, trait_def) = match trait_defs
+                .get_id(name)
+                .and_then(|id| Some((id, trait_defs.get(id)?)))
+            {
+                Some(found) => found,
+                None => {
+                    log::warn!("Race '{}' has unknown psyche trait '{}'", def.name(), name);
+                    continue;
+                }
+            };
+
+            if let Some(group) = &trait_def.group {
+                if groups.contains(&group) {
+                    continue;
+                }
+            }
+            if !rng.gen_bool(f64::from(chance.max(0.0).min(1.0))) {
+                continue;
+            }
+            if let Some(group) = &trait_def.group {
+                groups.push(group);
+            }
+
+            let intensity = Normal::new(*intensity, INTENSITY_DEVIATION)
+                .unwrap()
+                .sample(rng)
+                .max(MIN_INTENSITY);
+            traits.push(PersonalityTrait::new(id, intensity));
+        }
+
+        Self { traits }
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &PersonalityTrait> {
+        self.traits
+            .iter()
+            .filter(|personality_trait| personality_trait.active)
+    }
+
+    /// Product of the scales `effect` picks out of every active trait, each strengthened or
+    /// weakened by how intense the trait is.
+    pub fn scale<F>(&self, trait_defs: &DefinitionStorage<PsycheTraitDefinition>, effect: F) -> f32
+    where
+        F: Fn(&PsycheTraitEffectKind) -> Option<f32>,
+    {
+        let effect = &effect;
+        self.active()
+            .filter_map(|personality_trait| {
+                Some((
+                    personality_trait.intensity,
+                    trait_defs.get(personality_trait.id)?,
+                ))
+            })
+            .flat_map(move |(intensity, def)| {
+                def.effects
+                    .iter()
+                    .filter_map(effect)
+                    .map(move |scale| 1.0 + (scale - 1.0) * intensity)
+            })
+            .product()
+    }
 }
 impl Component for PersonalityComponent {
     type Storage = VecStorage<Self>;
@@ -25,3 +138,60 @@ impl PyscheNeedsComponent {
 impl Component for PyscheNeedsComponent {
     type Storage = VecStorage<Self>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{rand::SeedableRng, rand_xorshift::XorShiftRng};
+
+    #[test]
+    fn generate_personality() -> Result<(), core::failure::Error> {
+        let traits: Vec<PsycheTraitDefinition> = core::ron::de::from_str(
+            r#"[
+                (name: "Introvert", description: "", group: "Sociability", effects: []),
+                (name: "Extrovert", description: "", group: "Sociability", effects: []),
+                (name: "Callous", description: "", effects: []),
+            ]"#,
+        )?;
+        let mut trait_defs = DefinitionStorage::default();
+        traits.into_iter().for_each(|def| {
+            trait_defs.insert(def);
+        });
+        let races: Vec<RaceDefinition> = core::ron::de::from_str(
+            r#"[(
+                name: "Test",
+                body: "humanoid",
+                psyche: [
+                    (("Introvert", 1.0), 0.9),
+                    (("Extrovert", 1.0), 0.9),
+                    (("Callous", 0.5), 0.5),
+                ],
+            )]"#,
+        )?;
+        let mut rng = XorShiftRng::seed_from_u64(0);
+
+        let names = |personality: &PersonalityComponent| {
+            personality
+                .active()
+                .map(|personality_trait| trait_defs.get(personality_trait.id).unwrap().name())
+                .collect::<Vec<_>>()
+        };
+
+        let mut callous = 0;
+        for _ in 0..100 {
+            let personality = PersonalityComponent::generate(&mut rng, &races[0], &trait_defs);
+            let names = names(&personality);
+            assert!(!(names.contains(&"Introvert") && names.contains(&"Extrovert")));
+            assert!(personality
+                .traits
+                .iter()
+                .all(|personality_trait| personality_trait.intensity >= MIN_INTENSITY));
+            if names.contains(&"Callous") {
+                callous += 1;
+            }
+        }
+        assert!(callous > 25 && callous < 75);
+
+        Ok(())
+    }
+}
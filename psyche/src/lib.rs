@@ -1,3 +1,5 @@
 pub mod systems;
 pub use systems::NeedsDecaySystem;
 pub mod components;
+pub mod mood;
+pub mod social;
@@ -0,0 +1,282 @@
+use crate::components::PersonalityComponent;
+use core::{
+    amethyst::ecs::{Component, VecStorage},
+    defs::{
+        psyche::{
+            NeedEffectValue, NeedKind, NeedsContainer, PsycheTraitDefinition,
+            PsycheTraitEffectKind, ThoughtDefinition, ThoughtId,
+        },
+        DefinitionStorage, Named,
+    },
+};
+
+/// Mood is kept between -100 for miserable and 100 for elated.
+pub const MOOD_RANGE: (i16, i16) = (-100, 100);
+
+/// Thoughts the game gives creatures itself, by name.
+pub const SAW_A_CORPSE: &str = "Saw a corpse";
+pub const ATE_A_GOOD_MEAL: &str = "Ate a good meal";
+pub const ATE_RAW_FOOD: &str = "Ate raw food";
+pub const ATE_BURNT_FOOD: &str = "Ate burnt food";
+pub const ATE_ROTTEN_FOOD: &str = "Ate rotten food";
+
+/// Mood below which a creature suffers each kind of breakdown.
+pub const MINOR_BREAKDOWN_MOOD: i16 = -30;
+pub const MAJOR_BREAKDOWN_MOOD: i16 = -60;
+pub const EXTREME_BREAKDOWN_MOOD: i16 = -85;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[repr(u8)]
+pub enum Breakdown {
+    Minor = 1,
+    Major = 2,
+    Extreme = 3,
+}
+impl Breakdown {
+    pub fn from_mood(mood: i16) -> Option<Self> {
+        if mood <= EXTREME_BREAKDOWN_MOOD {
+            Some(Breakdown::Extreme)
+        } else if mood <= MAJOR_BREAKDOWN_MOOD {
+            Some(Breakdown::Major)
+        } else if mood <= MINOR_BREAKDOWN_MOOD {
+            Some(Breakdown::Minor)
+        } else {
+            None
+        }
+    }
+}
+
+/// A thought a creature currently holds, and the mood it adds after the creature's traits.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Thought {
+    pub id: ThoughtId,
+    /// Seconds of game time left before the thought wears off.
+    pub remaining: u32,
+    pub mood: i16,
+}
+
+/// How a creature feels, from how well its needs are met and what it has been through lately.
+/// `mood` is the sum of `needs` and every thought, and `breakdown` is set while it is low enough
+/// for the creature to break.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MoodComponent {
+    pub mood: i16,
+    pub needs: i16,
+    pub thoughts: Vec<Thought>,
+    pub breakdown: Option<Breakdown>,
+}
+impl MoodComponent {
+    /// Remembers a thought. Once a creature holds `stack_limit` of it, the oldest is refreshed
+    /// instead.
+    pub fn think(&mut self, def: &ThoughtDefinition) {
+        let id = def.id().unwrap();
+        let held = self
+            .thoughts
+            .iter()
+            .filter(|thought| thought.id == id)
+            .count();
+        if held < usize::from(def.stack_limit.max(1)) {
+            self.thoughts.push(Thought {
+                id,
+                remaining: def.duration,
+                mood: def.mood,
+            });
+        } else if let Some(oldest) = self
+            .thoughts
+            .iter_mut()
+            .filter(|thought| thought.id == id)
+            .min_by_key(|thought| thought.remaining)
+        {
+            oldest.remaining = def.duration;
+        }
+    }
+
+    pub fn has_thought(&self, id: ThoughtId) -> bool {
+        self.thoughts.iter().any(|thought| thought.id == id)
+    }
+
+    /// Forgets thoughts older than `elapsed` seconds, then works the mood out again from the
+    /// creature's needs and remaining thoughts, as its active traits see them.
+    pub fn update(
+        &mut self,
+        elapsed: u32,
+        needs: &NeedsContainer,
+        personality: &PersonalityComponent,
+        trait_defs: &DefinitionStorage<PsycheTraitDefinition>,
+        thought_defs: &DefinitionStorage<ThoughtDefinition>,
+    ) {
+        let effects = personality
+            .active()
+            .filter_map(|personality_trait| {
+                let def = trait_defs.get(personality_trait.id)?;
+                Some(
+                    def.effects
+                        .iter()
+                        .map(move |effect| (personality_trait.intensity, effect)),
+                )
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        for thought in &mut self.thoughts {
+            thought.remaining = thought.remaining.saturating_sub(elapsed);
+        }
+        self.thoughts.retain(|thought| thought.remaining > 0);
+
+        self.needs = need_mood(needs, &effects);
+        for thought in &mut self.thoughts {
+            if let Some(def) = thought_defs.get(thought.id) {
+                thought.mood = thought_mood(def, &effects);
+            }
+        }
+
+        let thoughts = self
+            .thoughts
+            .iter()
+            .map(|thought| i32::from(thought.mood))
+            .sum::<i32>();
+        self.mood = clamp_mood(i32::from(self.needs) + thoughts);
+        self.breakdown = Breakdown::from_mood(self.mood);
+    }
+}
+impl Component for MoodComponent {
+    type Storage = VecStorage<Self>;
+}
+
+/// Average of every need's value, weighted by how much the creature cares about it. Traits with
+/// a `Static` effect on a need shift its value, as far as their intensity.
+#[allow(clippy::cast_possible_truncation)]
+fn need_mood(needs: &NeedsContainer, effects: &[(f32, &PsycheTraitEffectKind)]) -> i16 {
+    let offset = |kind: NeedKind| {
+        effects
+            .iter()
+            .filter_map(|(intensity, effect)| match effect {
+                PsycheTraitEffectKind::NeedEffect(effect) if effect.kind == kind => {
+                    if let NeedEffectValue::Static(value) = effect.value {
+                        Some((f32::from(value) * intensity).round() as i32)
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+            .sum::<i32>()
+    };
+
+    let (total, weights) = needs
+        .iter()
+        .fold((0, 0), |(total, weights), (kind, state)| {
+            let value = i32::from(state.value) + offset(*kind);
+            let weight = i32::from(state.weight);
+            (total + value * weight, weights + weight.abs())
+        });
+
+    if weights == 0 {
+        0
+    } else {
+        clamp_mood(total / weights)
+    }
+}
+
+/// The mood of a thought, scaled by every trait with an effect on it. Weaker traits scale it
+/// less.
+#[allow(clippy::cast_possible_truncation)]
+fn thought_mood(def: &ThoughtDefinition, effects: &[(f32, &PsycheTraitEffectKind)]) -> i16 {
+    let scale = effects
+        .iter()
+        .filter_map(|(intensity, effect)| match effect {
+            PsycheTraitEffectKind::ThoughtEffect(effect) if effect.thought == def.name() => {
+                Some(1.0 + (effect.scale - 1.0) * intensity)
+            }
+            _ => None,
+        })
+        .product::<f32>();
+
+    clamp_mood((f32::from(def.mood) * scale).round() as i32)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn clamp_mood(mood: i32) -> i16 { mood.max(MOOD_RANGE.0.into()).min(MOOD_RANGE.1.into()) as i16 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::PersonalityTrait;
+
+    #[test]
+    fn needs_and_thoughts() -> Result<(), core::failure::Error> {
+        let traits: Vec<PsycheTraitDefinition> = core::ron::de::from_str(
+            r#"[
+                (
+                    name: "Introvert",
+                    description: "",
+                    effects: [ NeedEffect((kind: Social, value: Static(50))) ],
+                ),
+                (
+                    name: "Callous",
+                    description: "",
+                    effects: [ ThoughtEffect((thought: "Saw a corpse", scale: 0.0)) ],
+                ),
+            ]"#,
+        )?;
+        let thoughts: Vec<ThoughtDefinition> = core::ron::de::from_str(
+            r#"[
+                (name: "Saw a corpse", mood: -40, duration: 3600),
+                (name: "Ate a good meal", mood: 10, duration: 3600, stack_limit: 2),
+            ]"#,
+        )?;
+        let mut trait_defs = DefinitionStorage::default();
+        traits.into_iter().for_each(|def| {
+            trait_defs.insert(def);
+        });
+        let mut thought_defs = DefinitionStorage::default();
+        thoughts.into_iter().for_each(|def| {
+            thought_defs.insert(def);
+        });
+        let corpse = thought_defs.find("Saw a corpse").unwrap();
+        let meal = thought_defs.find("Ate a good meal").unwrap();
+
+        let mut needs = NeedsContainer::default();
+        needs.need_mut(NeedKind::Social).weight = 1;
+        needs.need_mut(NeedKind::Social).value = -20;
+        needs.need_mut(NeedKind::Safety).weight = 1;
+        needs.need_mut(NeedKind::Safety).value = -20;
+
+        let mut personality = PersonalityComponent::default();
+        let mut mood = MoodComponent::default();
+        mood.update(0, &needs, &personality, &trait_defs, &thought_defs);
+        assert_eq!(mood.needs, -20);
+        assert_eq!(mood.breakdown, None);
+
+        // Seeing a corpse twice only counts once, but is enough to break.
+        mood.think(corpse);
+        mood.think(corpse);
+        mood.update(0, &needs, &personality, &trait_defs, &thought_defs);
+        assert_eq!(mood.thoughts.len(), 1);
+        assert_eq!(mood.mood, -60);
+        assert_eq!(mood.breakdown, Some(Breakdown::Major));
+
+        // Introverts don't mind being alone, and callous ones don't mind corpses.
+        let introvert = trait_defs.get_id("Introvert").unwrap();
+        let callous = trait_defs.get_id("Callous").unwrap();
+        personality
+            .traits
+            .push(PersonalityTrait::new(introvert, 1.0));
+        personality.traits.push(PersonalityTrait::new(callous, 1.0));
+        mood.think(meal);
+        mood.think(meal);
+        mood.update(1800, &needs, &personality, &trait_defs, &thought_defs);
+        assert_eq!(mood.needs, 5);
+        assert_eq!(mood.mood, 25);
+        assert_eq!(mood.breakdown, None);
+
+        // Every thought wears off.
+        mood.update(1800, &needs, &personality, &trait_defs, &thought_defs);
+        assert!(mood.thoughts.is_empty());
+        assert_eq!(mood.mood, 5);
+
+        Ok(())
+    }
+}
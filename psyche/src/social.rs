@@ -0,0 +1,330 @@
+use crate::components::PersonalityComponent;
+use core::{
+    amethyst::ecs::{Component, VecStorage},
+    components::PawnId,
+    defs::{
+        psyche::{
+            NeedKind, NeedsContainer, PsycheTraitDefinition, PsycheTraitEffectKind,
+            SocialInteraction,
+        },
+        race::Attributes,
+        DefinitionStorage,
+    },
+    fnv::FnvHashMap,
+};
+use std::convert::TryFrom;
+
+/// Opinions are kept between -100 for hatred and 100 for devotion.
+pub const OPINION_RANGE: (i16, i16) = (-100, 100);
+
+/// Opinion at or above which acquaintances become friends, and at or below which they become
+/// rivals. Friends who both think this well of each other become partners.
+pub const FRIEND_OPINION: i16 = 40;
+pub const RIVAL_OPINION: i16 = -40;
+pub const PARTNER_OPINION: i16 = 80;
+
+/// Seconds for an opinion to drift one point back towards indifference. Family and partners
+/// drift half as fast.
+const OPINION_DECAY_TIME: u32 = 6 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum RelationshipKind {
+    Acquaintance,
+    Friend,
+    Rival,
+    Partner,
+    Family,
+}
+impl Default for RelationshipKind {
+    fn default() -> Self { RelationshipKind::Acquaintance }
+}
+
+/// What one creature thinks of another. `acc` holds the time built up towards the next point of
+/// decay.
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Relationship {
+    pub opinion: i16,
+    pub kind: RelationshipKind,
+    pub acc: u32,
+}
+impl Relationship {
+    /// Family and partners are as close as relationships get.
+    pub fn is_close(&self) -> bool {
+        self.kind == RelationshipKind::Family || self.kind == RelationshipKind::Partner
+    }
+
+    /// Family stays family, and partners stay partners until they become rivals. Everyone else is
+    /// whatever their opinion makes them.
+    fn update_kind(&mut self) {
+        self.kind = match self.kind {
+            RelationshipKind::Family => RelationshipKind::Family,
+            RelationshipKind::Partner if self.opinion > RIVAL_OPINION => RelationshipKind::Partner,
+            _ if self.opinion >= FRIEND_OPINION => RelationshipKind::Friend,
+            _ if self.opinion <= RIVAL_OPINION => RelationshipKind::Rival,
+            _ => RelationshipKind::Acquaintance,
+        };
+    }
+}
+
+/// Every pawn a creature knows, and what it thinks of them.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RelationshipsComponent {
+    pub relationships: FnvHashMap<PawnId, Relationship>,
+}
+impl RelationshipsComponent {
+    pub fn get(&self, other: PawnId) -> Option<&Relationship> { self.relationships.get(&other) }
+
+    pub fn opinion(&self, other: PawnId) -> i16 { self.get(other).map_or(0, |r| r.opinion) }
+
+    pub fn has_partner(&self) -> bool {
+        self.relationships
+            .values()
+            .any(|relationship| relationship.kind == RelationshipKind::Partner)
+    }
+
+    /// Makes `other` family, a partner or anything else, whatever the opinion of them.
+    pub fn set_kind(&mut self, other: PawnId, kind: RelationshipKind) {
+        self.relationships.entry(other).or_default().kind = kind;
+    }
+
+    /// Moves the opinion of `other` by `change`, getting to know them if need be.
+    pub fn change_opinion(&mut self, other: PawnId, change: i16) -> &Relationship {
+        let relationship = self.relationships.entry(other).or_default();
+        relationship.opinion = clamp_opinion(i32::from(relationship.opinion) + i32::from(change));
+        relationship.update_kind();
+        relationship
+    }
+
+    /// Forgets someone, such as a pawn which no longer exists.
+    pub fn forget(&mut self, other: PawnId) { self.relationships.remove(&other); }
+
+    /// Drifts every opinion back towards indifference by `elapsed` seconds' worth.
+    pub fn decay(&mut self, elapsed: u32) {
+        for relationship in self.relationships.values_mut() {
+            let time = if relationship.is_close() {
+                OPINION_DECAY_TIME * 2
+            } else {
+                OPINION_DECAY_TIME
+            };
+
+            relationship.acc = relationship.acc.saturating_add(elapsed);
+            let steps = i16::try_from(relationship.acc / time).unwrap_or(i16::max_value());
+            relationship.acc %= time;
+
+            relationship.opinion = if relationship.opinion > 0 {
+                (relationship.opinion - steps).max(0)
+            } else {
+                (relationship.opinion + steps).min(0)
+            };
+            relationship.update_kind();
+        }
+    }
+}
+impl Component for RelationshipsComponent {
+    type Storage = VecStorage<Self>;
+}
+
+/// One side of an interaction.
+pub struct Participant<'a> {
+    pub id: PawnId,
+    pub attributes: &'a Attributes,
+    pub personality: &'a PersonalityComponent,
+    pub relationships: &'a mut RelationshipsComponent,
+    pub needs: &'a mut NeedsContainer,
+}
+impl Participant<'_> {
+    fn satisfy(&mut self, other: PawnId, social: i16, love: i16) {
+        satisfy(self.needs, NeedKind::Social, social);
+        if self
+            .relationships
+            .get(other)
+            .map_or(false, Relationship::is_close)
+        {
+            satisfy(self.needs, NeedKind::Love, love);
+        }
+    }
+}
+
+/// `source` chats with, insults or comforts `target`. Whoever is on the receiving end changes
+/// their opinion of the other, and both get to know each other. Each side's `Social` need is met
+/// by friendly interactions, and their `Love` need too if they are close. Friends who come to
+/// think well enough of each other become partners, if neither has one already.
+pub fn socialize(
+    interaction: SocialInteraction,
+    source: &mut Participant<'_>,
+    target: &mut Participant<'_>,
+    trait_defs: &DefinitionStorage<PsycheTraitDefinition>,
+) {
+    let received = opinion_change(interaction, source, target, trait_defs);
+    let given = match interaction {
+        SocialInteraction::Chat => opinion_change(interaction, target, source, trait_defs),
+        _ => 0,
+    };
+    target.relationships.change_opinion(source.id, received);
+    source.relationships.change_opinion(target.id, given);
+
+    let mutual = |a: &Participant<'_>, b: &Participant<'_>| {
+        a.relationships.get(b.id).map_or(false, |relationship| {
+            relationship.kind == RelationshipKind::Friend && relationship.opinion >= PARTNER_OPINION
+        }) && !a.relationships.has_partner()
+    };
+    if mutual(source, target) && mutual(target, source) {
+        source
+            .relationships
+            .set_kind(target.id, RelationshipKind::Partner);
+        target
+            .relationships
+            .set_kind(source.id, RelationshipKind::Partner);
+    }
+
+    let (social, love) = match interaction {
+        SocialInteraction::Chat => (10, 5),
+        SocialInteraction::Comfort => (15, 15),
+        SocialInteraction::Insult => (0, 0),
+    };
+    source.satisfy(target.id, social, love);
+    target.satisfy(source.id, social, love);
+}
+
+/// How much an interaction moves the opinion `to` has of `from`. Sociable creatures enjoy a chat
+/// more, strong-willed ones shrug insults off and empathetic ones are better at comforting. The
+/// receiver's traits scale the result.
+#[allow(clippy::cast_possible_truncation)]
+fn opinion_change(
+    interaction: SocialInteraction,
+    from: &Participant<'_>,
+    to: &Participant<'_>,
+    trait_defs: &DefinitionStorage<PsycheTraitDefinition>,
+) -> i16 {
+    let attribute = |value: u16| f32::from(value) / 1000.0;
+    let base = match interaction {
+        SocialInteraction::Chat => 5.0 * attribute(to.attributes.social),
+        SocialInteraction::Insult => -15.0 * (2.0 - attribute(to.attributes.willpower)).max(0.25),
+        SocialInteraction::Comfort => 10.0 * attribute(from.attributes.empathy),
+    };
+    let scale = to.personality.scale(trait_defs, |effect| match effect {
+        PsycheTraitEffectKind::SocialEffect(effect) if effect.interaction == interaction => {
+            Some(effect.scale)
+        }
+        _ => None,
+    });
+
+    clamp_opinion((base * scale).round() as i32)
+}
+
+fn satisfy(needs: &mut NeedsContainer, kind: NeedKind, amount: i16) {
+    let need = needs.need_mut(kind);
+    need.value = need
+        .value
+        .saturating_add(amount)
+        .max(need.decay.minmax.0)
+        .min(need.decay.minmax.1);
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn clamp_opinion(opinion: i32) -> i16 {
+    opinion
+        .max(OPINION_RANGE.0.into())
+        .min(OPINION_RANGE.1.into()) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relationships_grow_and_fade() -> Result<(), core::failure::Error> {
+        let traits: Vec<PsycheTraitDefinition> = core::ron::de::from_str(
+            r#"[(
+                name: "Thick-skinned",
+                description: "",
+                effects: [ SocialEffect((interaction: Insult, scale: 0.0)) ],
+            )]"#,
+        )?;
+        let mut trait_defs = DefinitionStorage::default();
+        traits.into_iter().for_each(|def| {
+            trait_defs.insert(def);
+        });
+
+        let (a, b) = (PawnId(1), PawnId(2));
+        let attributes = Attributes::default();
+        let a_personality = PersonalityComponent::default();
+        let mut b_personality = PersonalityComponent::default();
+        let mut a_relationships = RelationshipsComponent::default();
+        let mut b_relationships = RelationshipsComponent::default();
+        let (mut a_needs, mut b_needs) = (NeedsContainer::default(), NeedsContainer::default());
+
+        macro_rules! interact {
+            ($interaction:expr) => {
+                socialize(
+                    $interaction,
+                    &mut Participant {
+                        id: a,
+                        attributes: &attributes,
+                        personality: &a_personality,
+                        relationships: &mut a_relationships,
+                        needs: &mut a_needs,
+                    },
+                    &mut Participant {
+                        id: b,
+                        attributes: &attributes,
+                        personality: &b_personality,
+                        relationships: &mut b_relationships,
+                        needs: &mut b_needs,
+                    },
+                    &trait_defs,
+                )
+            };
+        }
+
+        // Chatting makes friends of both, and keeps them from getting lonely.
+        for _ in 0..8 {
+            interact!(SocialInteraction::Chat);
+        }
+        assert_eq!(
+            a_relationships.get(b).unwrap().kind,
+            RelationshipKind::Friend
+        );
+        assert_eq!(b_relationships.opinion(a), 40);
+        assert_eq!(a_needs.need(NeedKind::Social).value, 80);
+        assert_eq!(a_needs.need(NeedKind::Love).value, 0);
+
+        // ...until they keep chatting their way into being partners.
+        for _ in 0..8 {
+            interact!(SocialInteraction::Chat);
+        }
+        assert_eq!(
+            b_relationships.get(a).unwrap().kind,
+            RelationshipKind::Partner
+        );
+        interact!(SocialInteraction::Comfort);
+        assert_eq!(b_needs.need(NeedKind::Love).value, 20);
+
+        // Insults only hurt those who take them to heart.
+        interact!(SocialInteraction::Insult);
+        assert_eq!(b_relationships.opinion(a), 75);
+        let id = trait_defs.get_id("Thick-skinned").unwrap();
+        b_personality
+            .traits
+            .push(crate::components::PersonalityTrait::new(id, 1.0));
+        interact!(SocialInteraction::Insult);
+        assert_eq!(b_relationships.opinion(a), 75);
+
+        // Left alone, everyone drifts apart, but partners slower than anyone else.
+        b_relationships.decay(60 * 2 * OPINION_DECAY_TIME);
+        assert_eq!(b_relationships.opinion(a), 15);
+        assert_eq!(
+            b_relationships.get(a).unwrap().kind,
+            RelationshipKind::Partner
+        );
+        a_relationships.set_kind(b, RelationshipKind::Friend);
+        a_relationships.decay(60 * OPINION_DECAY_TIME);
+        assert_eq!(a_relationships.opinion(b), 20);
+        assert_eq!(
+            a_relationships.get(b).unwrap().kind,
+            RelationshipKind::Acquaintance
+        );
+
+        Ok(())
+    }
+}
@@ -14,6 +14,11 @@ use core::{
     rayon::prelude::*,
 };
 
+pub mod mood;
+pub use mood::{MoodSystem, WitnessSystem};
+pub mod social;
+pub use social::RelationshipDecaySystem;
+
 #[derive(Default, SystemDesc)]
 pub struct NeedsDecaySystem {
     pub last: Instant,
@@ -79,28 +84,33 @@ impl NeedsDecaySystem {
 
         use core::defs::psyche::{NeedEffectValue, PsycheTraitEffectKind};
 
-        // Iterate through the traits, if any are a Decay type, we apply them
+        // Iterate through the traits, if any are a Decay type, we apply them. Stronger traits
+        // decay their needs faster.
         personality
             .traits
             .iter_mut()
-            .filter(|(active, _, _)| *active)
-            .for_each(|(_, trait_id, accums)| {
-                let def = trait_defs.get(*trait_id).unwrap();
+            .filter(|personality_trait| personality_trait.active)
+            .for_each(|personality_trait| {
+                let def = trait_defs.get(personality_trait.id).unwrap();
+                let intensity = personality_trait.intensity;
+                let accums = &mut personality_trait.accums;
                 def.effects.iter().enumerate().for_each(|(i, effect)| {
                     if let PsycheTraitEffectKind::NeedEffect(effect) = effect {
                         if let NeedEffectValue::Decay(decay) = effect.value {
-                            if decay.value == 0 || decay.time == 0 {
+                            if decay.value == 0 || decay.time == 0 || intensity <= 0.0 {
                                 return;
                             }
 
+                            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                            let time = ((decay.time as f32 / intensity) as u32).max(1);
                             let mut accum = accums.get(&i).unwrap_or(&0) + time_elapsed as u32;
-                            while accum >= decay.time {
+                            while accum >= time {
                                 let new_value = needs.need(effect.kind).value + decay.value;
                                 if decay.minmax.0 < new_value && decay.minmax.1 > new_value {
                                     needs.need_mut(effect.kind).value = new_value;
                                 }
 
-                                accum -= decay.time;
+                                accum -= time;
                             }
 
                             accums.insert(i, accum);
@@ -120,6 +130,17 @@ impl<'a, 'b> SystemBundle<'a, 'b> for PsycheBundle {
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), core::amethyst::Error> {
         builder.add(NeedsDecaySystem::default(), "NeedsDecaySystem", &[]);
+        builder.add(WitnessSystem::default(), "WitnessSystem", &[]);
+        builder.add(
+            RelationshipDecaySystem::default(),
+            "RelationshipDecaySystem",
+            &[],
+        );
+        builder.add(
+            MoodSystem::default(),
+            "MoodSystem",
+            &["NeedsDecaySystem", "WitnessSystem"],
+        );
 
         Ok(())
     }
@@ -156,4 +177,51 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn race_needs_after_a_day() -> Result<(), core::failure::Error> {
+        use crate::components::{MoodComponent, PersonalityTrait};
+        use core::defs::{psyche::ThoughtDefinition, race::RaceDefinition};
+
+        let races = DefinitionStorage::<RaceDefinition>::from_folder("../resources/defs/races")?;
+        let traits =
+            DefinitionStorage::<PsycheTraitDefinition>::from_folder("../resources/defs/psyche")?;
+        let thoughts =
+            DefinitionStorage::<ThoughtDefinition>::from_folder("../resources/defs/thoughts")?;
+        let race = races.find("Human").unwrap();
+
+        // Nothing here goes hungry, thirsty or gets hurt, but nobody keeps an extrovert company.
+        let time = WorldTime::default();
+        let mut sys = NeedsDecaySystem::default();
+        sys.reset(time.now());
+        let attributes = AttributesComponent::default();
+        let mut personality = PersonalityComponent::default();
+        let extrovert = traits.get_id("Extrovert").unwrap();
+        personality
+            .traits
+            .push(PersonalityTrait::new(extrovert, 1.0));
+        let mut needs = PyscheNeedsComponent::new(race.needs.clone());
+
+        for _ in 0..24 * 60 {
+            time.elapse_raw(60.0);
+            sys.tick(&time, &traits, &attributes, &mut personality, &mut needs);
+            sys.reset(time.now());
+        }
+
+        for kind in &[
+            NeedKind::HungerTolerance,
+            NeedKind::ThirstTolerance,
+            NeedKind::PainTolerance,
+        ] {
+            assert_eq!(needs.need(*kind).value, 0);
+        }
+        assert!(needs.need(NeedKind::Social).value < 0);
+
+        let mut mood = MoodComponent::default();
+        mood.update(0, &needs, &personality, &traits, &thoughts);
+        assert!(mood.mood < 0);
+        assert_eq!(mood.breakdown, None);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,122 @@
+use crate::{
+    components::{MoodComponent, PersonalityComponent, PyscheNeedsComponent},
+    mood::SAW_A_CORPSE,
+};
+use core::{
+    amethyst::{
+        core::{math::Point3, SystemDesc},
+        derive::SystemDesc,
+        ecs::{
+            Entities, Join, ParJoin, Read, ReadStorage, System, SystemData, World, WriteStorage,
+        },
+    },
+    clock::{Instant, WorldTime},
+    components::{PropertiesComponent, TilePosition},
+    defs::{
+        property::PropertyKind,
+        psyche::{PsycheTraitDefinition, ThoughtDefinition},
+        DefinitionStorage,
+    },
+    rayon::prelude::*,
+};
+
+/// Tiles within which a creature notices a corpse.
+const SIGHT_RANGE: u32 = 8;
+
+/// Ages every creature's thoughts and works out its mood and any breakdown again.
+#[derive(Default, SystemDesc)]
+pub struct MoodSystem {
+    pub last: Instant,
+}
+impl<'s> System<'s> for MoodSystem {
+    type SystemData = (
+        Read<'s, WorldTime>,
+        Read<'s, DefinitionStorage<PsycheTraitDefinition>>,
+        Read<'s, DefinitionStorage<ThoughtDefinition>>,
+        ReadStorage<'s, PersonalityComponent>,
+        ReadStorage<'s, PyscheNeedsComponent>,
+        WriteStorage<'s, MoodComponent>,
+    );
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn run(
+        &mut self,
+        (time, trait_defs, thought_defs, personalities, needs, mut moods): Self::SystemData,
+    ) {
+        // Skip execution if the game time hasn't progressed
+        if self.last == time.now() {
+            return;
+        }
+        let elapsed = (time.now() - self.last).value() as u32;
+        self.last = time.now();
+
+        (&personalities, &needs, &mut moods)
+            .par_join()
+            .for_each(|(personality, needs, mood)| {
+                mood.update(elapsed, needs, personality, &trait_defs, &thought_defs);
+            });
+    }
+}
+
+/// Gives creatures thoughts about what they can see around them, such as corpses.
+#[derive(Default, SystemDesc)]
+pub struct WitnessSystem {
+    pub last: Instant,
+}
+impl<'s> System<'s> for WitnessSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, WorldTime>,
+        Read<'s, DefinitionStorage<ThoughtDefinition>>,
+        ReadStorage<'s, TilePosition>,
+        ReadStorage<'s, PropertiesComponent>,
+        WriteStorage<'s, MoodComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, thought_defs, positions, properties, mut moods): Self::SystemData,
+    ) {
+        if self.last == time.now() {
+            return;
+        }
+        self.last = time.now();
+
+        let saw_corpse = match thought_defs.find(SAW_A_CORPSE) {
+            Some(def) => def,
+            None => return,
+        };
+
+        let dead = |properties: Option<&PropertiesComponent>| {
+            properties.map_or(false, |properties| properties.contains(PropertyKind::Dead))
+        };
+        let corpses = (&entities, &positions, &properties)
+            .join()
+            .filter(|(_, _, properties)| dead(Some(properties)))
+            .map(|(entity, position, _)| (entity, position.0))
+            .collect::<Vec<_>>();
+        if corpses.is_empty() {
+            return;
+        }
+
+        for (entity, position, mood, properties) in
+            (&entities, &positions, &mut moods, properties.maybe()).join()
+        {
+            if dead(properties) {
+                continue;
+            }
+
+            if corpses
+                .iter()
+                .any(|(corpse, at)| *corpse != entity && in_sight(position.0, *at))
+            {
+                mood.think(saw_corpse);
+            }
+        }
+    }
+}
+
+fn in_sight(a: Point3<u32>, b: Point3<u32>) -> bool {
+    let distance = |a: u32, b: u32| if a > b { a - b } else { b - a };
+    a.z == b.z && distance(a.x, b.x) <= SIGHT_RANGE && distance(a.y, b.y) <= SIGHT_RANGE
+}
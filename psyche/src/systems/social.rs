@@ -0,0 +1,47 @@
+use crate::components::RelationshipsComponent;
+use core::{
+    amethyst::{
+        core::SystemDesc,
+        derive::SystemDesc,
+        ecs::{Join, ParJoin, Read, ReadStorage, System, SystemData, World, WriteStorage},
+    },
+    clock::{Instant, WorldTime},
+    components::PawnComponent,
+    fnv::FnvHashSet,
+    rayon::prelude::*,
+};
+
+/// Drifts every creature's opinions back towards indifference, and forgets pawns which no longer
+/// exist.
+#[derive(Default, SystemDesc)]
+pub struct RelationshipDecaySystem {
+    pub last: Instant,
+}
+impl<'s> System<'s> for RelationshipDecaySystem {
+    type SystemData = (
+        Read<'s, WorldTime>,
+        ReadStorage<'s, PawnComponent>,
+        WriteStorage<'s, RelationshipsComponent>,
+    );
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn run(&mut self, (time, pawns, mut relationships): Self::SystemData) {
+        // Skip execution if the game time hasn't progressed
+        if self.last == time.now() {
+            return;
+        }
+        let elapsed = (time.now() - self.last).value() as u32;
+        self.last = time.now();
+
+        let existing = (&pawns)
+            .join()
+            .map(|pawn| pawn.id)
+            .collect::<FnvHashSet<_>>();
+        (&mut relationships).par_join().for_each(|relationships| {
+            relationships
+                .relationships
+                .retain(|id, _| existing.contains(id));
+            relationships.decay(elapsed);
+        });
+    }
+}
@@ -10,12 +10,14 @@ use amethyst::{
 
 use crate::components::{
     AttributesComponent, CurrentActionComponent, IdleComponent, ItemComponent, ItemParentComponent,
-    ItemParentRelationship, PawnComponent, PyscheNeedsComponent, RaceComponent,
+    ItemParentRelationship, MoodComponent, PawnComponent, PyscheNeedsComponent, RaceComponent,
+    RelationshipsComponent,
 };
 use amethyst_imgui::imgui::{self, im_str, Condition, ImString, Ui};
 use core::{
     defs::{
-        action::ActionDefinition, item::ItemDefinition, DefinitionLookup, DefinitionStorage, Named,
+        action::ActionDefinition, item::ItemDefinition, psyche::ThoughtDefinition,
+        DefinitionLookup, DefinitionStorage, Named,
     },
     fsm::{ActionEvent, Event, MovementEvent},
     rand::{thread_rng, Rng},
@@ -35,6 +37,8 @@ type PawnData<'a> = (
     ReadStorage<'a, TileMap<core::tiles::region::RegionTile>>,
     ReadStorage<'a, RaceComponent>,
     ReadStorage<'a, PyscheNeedsComponent>,
+    ReadStorage<'a, MoodComponent>,
+    ReadStorage<'a, RelationshipsComponent>,
     ReadStorage<'a, AttributesComponent>,
     Write<'a, EventChannel<ActionEvent>>,
 );
@@ -72,6 +76,8 @@ pub fn draw(ui: &Ui, world: &mut World) {
                 tilemap_storage,
                 _race_storage,
                 needs_storage,
+                mood_storage,
+                relationships_storage,
                 _attributes_storage,
                 mut action_channel,
             ) = PawnData::fetch(&world);
@@ -108,6 +114,37 @@ pub fn draw(ui: &Ui, world: &mut World) {
                             }
                         }
                     });
+                    ui.group(|| {
+                        if let Some(mood) = mood_storage.get(entity) {
+                            ui.text(&format!(
+                                "mood = {} (needs {}), breakdown = {:?}",
+                                mood.mood, mood.needs, mood.breakdown
+                            ));
+
+                            let thought_defs =
+                                world.fetch::<DefinitionStorage<ThoughtDefinition>>();
+                            for thought in &mood.thoughts {
+                                if let Some(def) = thought_defs.get(thought.id) {
+                                    ui.text(&format!(
+                                        "  {} = {} ({}s)",
+                                        def.name(),
+                                        thought.mood,
+                                        thought.remaining
+                                    ));
+                                }
+                            }
+                        }
+                    });
+                    ui.group(|| {
+                        if let Some(relationships) = relationships_storage.get(entity) {
+                            for (id, relationship) in &relationships.relationships {
+                                ui.text(&format!(
+                                    "pawn #{} = {} ({:?})",
+                                    id.0, relationship.opinion, relationship.kind
+                                ));
+                            }
+                        }
+                    });
                     ui.group(|| {
                         let items = world
                             .fetch::<DefinitionStorage<ItemDefinition>>()
@@ -95,6 +95,17 @@ fn main() -> amethyst::Result<()> {
             "PawnPickupItemSystem",
             &[],
         )
+        .with_system_desc(
+            systems::PawnIngestSystem::default(),
+            "PawnIngestSystem",
+            &[],
+        )
+        .with_system_desc(
+            systems::PawnSocialSystem::default(),
+            "PawnSocialSystem",
+            &[],
+        )
+        .with_system_desc(systems::SpoilageSystem::default(), "SpoilageSystem", &[])
         .with_system_desc(
             systems::PathingWorkSystemDesc::default(),
             "PathingWorkSystem",
@@ -108,6 +119,36 @@ fn main() -> amethyst::Result<()> {
         .with_bundle(systems::world_view::WorldViewBundle::default())?
         .with_bundle(psyche::systems::PsycheBundle::default())?
         .with_bundle(body::bundle::BodyBundle::default())?
+        .with_system_desc(
+            systems::behavior::PlanningWorldSystemDesc::default(),
+            "PlanningWorldSystem",
+            &[],
+        )
+        .with_system_desc(
+            systems::behavior::UtilitySystemDesc::default(),
+            "UtilitySystem",
+            &[
+                "PlanningWorldSystem",
+                "NeedsDecaySystem",
+                "MoodSystem",
+                "digestion_system",
+            ],
+        )
+        .with_system_desc(
+            systems::behavior::DecisionGoalSystemDesc::default(),
+            "DecisionGoalSystem",
+            &["UtilitySystem"],
+        )
+        .with_system_desc(
+            systems::behavior::GoapPlannerSystemDesc::default(),
+            "GoapPlannerSystem",
+            &["DecisionGoalSystem"],
+        )
+        .with_system_desc(
+            systems::behavior::PlanExecutionSystemDesc::default(),
+            "PlanExecutionSystem",
+            &["GoapPlannerSystem"],
+        )
         .with_bundle(UiBundle::<core::input::BindingTypes>::new())?
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
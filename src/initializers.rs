@@ -1,4 +1,5 @@
 use crate::components::*;
+use ai::behavior::planner::{ConditionCache, GoapPlannerComponent};
 use amethyst::{
     assets::{AssetStorage, Handle, Loader, ProgressCounter},
     core::{
@@ -8,76 +9,129 @@ use amethyst::{
     },
     ecs::{world::Builder, Entity, Join, ReadStorage, SystemData, World, WorldExt, WriteStorage},
     renderer::{Camera, ImageFormat, SpriteSheet, SpriteSheetFormat, Texture, Transparent},
+    shrev::EventChannel,
     tiles::{Map, MapStorage, TileMap},
     window::ScreenDimensions,
 };
 use core::{
     components::TilePosition,
     defs::{
+        behavior::BehaviorDefinition,
         body::BodyDefinition,
         building::BuildingDefinition,
+        creature::CreatureDefinition,
         digestion::DigestionDefinition,
         item::ItemDefinition,
-        material::{MaterialRef, MaterialState},
+        material::{MaterialDefinition, MaterialRef, MaterialState},
+        psyche::PsycheTraitDefinition,
         sprites::SpriteOntoFlags,
         DefinitionStorage, HasProperties, Named,
     },
+    rng::WorldRng,
     settings::GraphicsSettings,
-    tiles::region::{RegionTile, RegionTileFlags},
+    tiles::region::{RegionTile, RegionTileChangedEvent},
 };
 
 pub use core::initializers::{self, tile_to_transform};
 
-#[allow(unused_variables)]
-pub fn spawn_creature(name: &str, position: &Point3<u32>, world: &mut World) -> Entity {
-    unimplemented!()
+/// Plans for the goals a pawn or creature picks, starting from the shared condition cache.
+fn goap_planner(world: &World) -> GoapPlannerComponent {
+    GoapPlannerComponent::new(
+        world
+            .try_fetch::<ConditionCache>()
+            .map(|cache| cache.clone())
+            .unwrap_or_default(),
+    )
 }
 
-pub fn spawn_pawn(race_name: &str, position: &Point3<u32>, world: &mut World) -> Entity {
+pub fn spawn_creature(name: &str, position: &Point3<u32>, world: &mut World) -> Entity {
     let transform = tile_to_transform(position, world);
 
     log::trace!(
-        "Spawning pawn: @ tile={:?}, world={:?}",
+        "Spawning creature: '{}' @ tile={:?}, world={:?}",
+        name,
         position,
         transform.translation()
     );
 
-    let (race, body, properties, spatial) = {
-        let races = world.fetch::<DefinitionStorage<RaceDefinition>>();
+    let (body, digestion, properties, spatial, utility, sprite_ref) = {
+        let creatures = world.fetch::<DefinitionStorage<CreatureDefinition>>();
         let bodies = world.fetch::<DefinitionStorage<BodyDefinition>>();
-        let _digestions = world.fetch::<DefinitionStorage<DigestionDefinition>>();
-
-        let race = races.find(race_name).unwrap();
-        let body = bodies.find(&race.body).unwrap();
-
-        // TODO: just copy the body dimensions for now
+        let digestions = world.fetch::<DefinitionStorage<DigestionDefinition>>();
+        let behaviors = world.fetch::<DefinitionStorage<BehaviorDefinition>>();
+
+        let creature = creatures.find(name).unwrap();
+        let body = creature
+            .body
+            .as_ref()
+            .map(|name| bodies.find(name).unwrap());
+        let digestion = body
+            .and_then(|body| body.digestion.as_ref())
+            .and_then(|name| digestions.get_id(name))
+            .map(|id| DigestionComponent::new(id, &digestions));
+
+        let mut properties = creature.default_properties();
+        if let Some(body) = body {
+            properties =
+                properties.merge(PropertiesMergeResolution::Error, &body.default_properties());
+        }
 
         (
-            RaceComponent::new(race.id().unwrap()),
-            BodyComponent::new(body.id().unwrap(), &bodies),
-            race.default_properties()
-                .merge(PropertiesMergeResolution::Error, &body.default_properties()),
-            SpatialComponent::new(body.dimensions.unwrap().mean, body.mass.unwrap().mean),
+            body.map(|body| BodyComponent::new(body.id().unwrap(), &bodies)),
+            digestion,
+            properties,
+            body.map(|body| {
+                SpatialComponent::new(body.dimensions.unwrap().mean, body.mass.unwrap().mean)
+            }),
+            creature
+                .behavior_definition(&behaviors)
+                .map(UtilityStateComponent::from_definition),
+            creature
+                .sprite
+                .clone()
+                .or_else(|| body.and_then(|body| body.sprite.clone())),
         )
     };
     let idle = IdleComponent::new(&world.fetch::<Time>());
+    let planner = goap_planner(world);
 
-    let entity = world
+    let mut builder = world
         .create_entity()
-        .with(PawnComponent::default())
         .with(idle)
-        .with(spatial)
         .with(properties)
-        .with(AttributesComponent::default())
-        .with(PersonalityComponent::default())
-        .with(PyscheNeedsComponent::default())
-        .with(TypeTagComponent::Pawn(PawnType::Player))
+        .with(planner)
+        .with(TypeTagComponent::Pawn(PawnType::AI))
         .with(Transparent)
-        .with(body)
-        .with(race)
-        .with(TilePosition::default())
-        .with(transform)
-        .build();
+        .with(TilePosition(*position))
+        .with(transform);
+    if let Some(body) = body {
+        builder = builder.with(body);
+    }
+    if let Some(spatial) = spatial {
+        builder = builder.with(spatial);
+    }
+    if let Some(digestion) = digestion {
+        builder = builder.with(digestion);
+    }
+    if let Some(utility) = utility {
+        builder = builder.with(utility);
+    }
+    let entity = builder.build();
+
+    if let Some(sprite_ref) = sprite_ref {
+        sprite_ref.onto_entity(
+            entity,
+            world,
+            core::z_level_modifiers::PAWN,
+            SpriteOntoFlags::All,
+        );
+    }
+
+    entity
+}
+
+pub fn spawn_pawn(race_name: &str, position: &Point3<u32>, world: &mut World) -> Entity {
+    let entity = create_pawn(race_name, position, world);
 
     let sprite_ref = {
         world
@@ -104,6 +158,87 @@ pub fn spawn_pawn(race_name: &str, position: &Point3<u32>, world: &mut World) ->
     entity
 }
 
+/// Builds every component of a pawn but its sprite.
+fn create_pawn(race_name: &str, position: &Point3<u32>, world: &mut World) -> Entity {
+    let transform = tile_to_transform(position, world);
+
+    log::trace!(
+        "Spawning pawn: @ tile={:?}, world={:?}",
+        position,
+        transform.translation()
+    );
+
+    world.entry::<WorldRng>().or_insert_with(WorldRng::default);
+    let id = world
+        .entry::<PawnIds>()
+        .or_insert_with(PawnIds::default)
+        .allocate();
+
+    let (race, body, digestion, properties, spatial, attributes, personality, needs, utility) = {
+        let races = world.fetch::<DefinitionStorage<RaceDefinition>>();
+        let bodies = world.fetch::<DefinitionStorage<BodyDefinition>>();
+        let digestions = world.fetch::<DefinitionStorage<DigestionDefinition>>();
+        let traits = world.fetch::<DefinitionStorage<PsycheTraitDefinition>>();
+        let behaviors = world.fetch::<DefinitionStorage<BehaviorDefinition>>();
+        let mut rng = world.fetch_mut::<WorldRng>();
+
+        let race = races.find(race_name).unwrap();
+        let body = bodies.find(&race.body).unwrap();
+        let digestion = body
+            .digestion
+            .as_ref()
+            .and_then(|name| digestions.get_id(name))
+            .map(|id| DigestionComponent::new(id, &digestions));
+
+        // TODO: just copy the body dimensions for now
+
+        (
+            RaceComponent::new(race.id().unwrap()),
+            BodyComponent::new(body.id().unwrap(), &bodies),
+            digestion,
+            race.default_properties()
+                .merge(PropertiesMergeResolution::Error, &body.default_properties()),
+            SpatialComponent::new(body.dimensions.unwrap().mean, body.mass.unwrap().mean),
+            AttributesComponent::new(Attributes::generate(&mut **rng, race)),
+            PersonalityComponent::generate(&mut **rng, race, &traits),
+            PyscheNeedsComponent::new(race.needs.clone()),
+            race.behavior_definition(&behaviors)
+                .map(UtilityStateComponent::from_definition),
+        )
+    };
+    let idle = IdleComponent::new(&world.fetch::<Time>());
+    let planner = goap_planner(world);
+
+    let mut builder = world
+        .create_entity()
+        .with(PawnComponent {
+            id,
+            ..PawnComponent::default()
+        })
+        .with(idle)
+        .with(spatial)
+        .with(properties)
+        .with(attributes)
+        .with(personality)
+        .with(needs)
+        .with(MoodComponent::default())
+        .with(RelationshipsComponent::default())
+        .with(TypeTagComponent::Pawn(PawnType::Player))
+        .with(Transparent)
+        .with(body)
+        .with(race)
+        .with(planner)
+        .with(TilePosition(*position))
+        .with(transform);
+    if let Some(digestion) = digestion {
+        builder = builder.with(digestion);
+    }
+    if let Some(utility) = utility {
+        builder = builder.with(utility);
+    }
+    builder.build()
+}
+
 #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
 pub fn spawn_building(name: &str, position: &Point3<u32>, world: &mut World) -> Entity {
     let transform = tile_to_transform(position, world);
@@ -135,10 +270,17 @@ pub fn spawn_building(name: &str, position: &Point3<u32>, world: &mut World) ->
         spatial.occupies_tiles(position).iter().for_each(|coord| {
             map.get_mut(&coord)
                 .unwrap()
-                .flags
-                .insert(RegionTileFlags::HasBuilding);
+                .set_building(Some(building_component.def));
         });
     }
+    if let Some(mut channel) = world.try_fetch_mut::<EventChannel<RegionTileChangedEvent>>() {
+        channel.iter_write(
+            spatial
+                .occupies_tiles(position)
+                .iter()
+                .map(RegionTileChangedEvent),
+        );
+    }
 
     let entity = world
         .create_entity()
@@ -204,10 +346,12 @@ pub fn spawn_item_world(
         transform.translation()
     );
 
-    let (item, properties, spatial) = {
+    let (item, properties, spatial, edible) = {
         let def_storage = world.fetch::<DefinitionStorage<ItemDefinition>>();
         let def_id = def_storage.get_id(name).unwrap();
         let def = def_storage.get(def_id).unwrap();
+        // Validation makes sure every item has its dimensions
+        let dimensions = def.dimensions.unwrap();
 
         let mut return_properties = def.default_properties();
 
@@ -217,14 +361,36 @@ pub fn spawn_item_world(
 
         let material = if let Some(material) = material {
             material
+        } else if let Some(material) = &def.material {
+            material.clone()
         } else {
             MaterialRef::new(&"oak", MaterialState::Solid)
         };
 
+        // Food gets its nutrition from what it is made of
+        let edible = if let Some(Property::Edible(kind, state)) =
+            return_properties.get(PropertyKind::Edible)
+        {
+            let materials = world.fetch::<DefinitionStorage<MaterialDefinition>>();
+            let mut edible = EdibleComponent::new(*kind).with_state(*state);
+            if let Some(state_def) = materials
+                .find(&material.name)
+                .and_then(|material_def| material_def.states.get(&material.state))
+            {
+                #[allow(clippy::cast_possible_truncation)]
+                let volume = (dimensions.volume() / 1000) as u32;
+                edible = edible.with_material(state_def, volume);
+            }
+            Some(edible)
+        } else {
+            None
+        };
+
         (
             ItemComponent::new(def_id, &material, &def_storage),
             return_properties,
-            SpatialComponent::new(def.dimensions.unwrap(), 100),
+            SpatialComponent::new(dimensions, 100),
+            edible,
         )
     };
 
@@ -241,6 +407,9 @@ pub fn spawn_item_world(
     if let Some(parent) = parent {
         builder = builder.with(parent);
     }
+    if let Some(edible) = edible {
+        builder = builder.with(edible);
+    }
 
     builder.build()
 }
@@ -324,3 +493,86 @@ pub fn camera(world: &mut World, _: &mut ProgressCounter) -> Entity {
         .named("camera")
         .build()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::behavior::{
+        DecisionGoalSystemDesc, GoapPlannerSystemDesc, PlanExecutionSystemDesc,
+        PlanningWorldSystemDesc, UtilitySystemDesc,
+    };
+    use amethyst::ecs::RunNow;
+    use core::{
+        defs::{action::ActionDefinition, foliage::FoliageCategory},
+        fsm::ActionTarget,
+    };
+
+    #[test]
+    fn spawned_pawn_plans_its_goal() -> Result<(), failure::Error> {
+        let mut world = World::new();
+        crate::loaders::assets(&mut world)?;
+        world.insert(Time::default());
+
+        let mut snapshot = PlanningWorldSystemDesc::default().build(&mut world);
+        let mut utility = UtilitySystemDesc::default().build(&mut world);
+        let mut goals = DecisionGoalSystemDesc::default().build(&mut world);
+        let mut planner = GoapPlannerSystemDesc::default().build(&mut world);
+        let mut executor = PlanExecutionSystemDesc::default().build(&mut world);
+
+        world.register::<TileMap<RegionTile>>();
+        world.register::<Transform>();
+        world.register::<Transparent>();
+        world.register::<PawnComponent>();
+        world.register::<SpatialComponent>();
+        world.register::<AttributesComponent>();
+        world.register::<PersonalityComponent>();
+        world.register::<RelationshipsComponent>();
+        world.register::<TypeTagComponent>();
+        world.register::<BodyComponent>();
+        world.register::<RaceComponent>();
+        world
+            .create_entity()
+            .with(TileMap::<RegionTile>::new(
+                Vector3::new(8, 8, 1),
+                Vector3::new(1, 1, 1),
+                None,
+            ))
+            .build();
+
+        let pawn = create_pawn("Human", &Point3::new(0, 0, 0), &mut world);
+        let axe = world
+            .create_entity()
+            .with(PropertiesComponent::from_iter_ref(
+                [Property::CanPickup, Property::Chopping(1)].iter(),
+            ))
+            .with(TilePosition(Point3::new(5, 0, 0)))
+            .build();
+        world
+            .create_entity()
+            .with(PropertiesComponent::from_iter_ref(
+                [Property::Foliage(FoliageCategory::Tree)].iter(),
+            ))
+            .with(TilePosition(Point3::new(0, 5, 0)))
+            .build();
+
+        // The pawn decides to chop wood, plans to fetch the axe first, and sets off for it
+        snapshot.run_now(&world);
+        utility.run_now(&world);
+        goals.run_now(&world);
+        planner.run_now(&world);
+        executor.run_now(&world);
+
+        let move_to = world
+            .fetch::<DefinitionStorage<ActionDefinition>>()
+            .find("Move To")
+            .unwrap()
+            .event
+            .clone();
+        let actions = world.read_storage::<CurrentActionComponent>();
+        let action = &actions.get(pawn).unwrap().inner;
+        assert_eq!(action.event, move_to);
+        assert_eq!(action.targets, vec![ActionTarget::Entity(axe)]);
+
+        Ok(())
+    }
+}
@@ -1,107 +1,399 @@
-use core::amethyst::ecs::{World, WorldExt};
-use core::defs::DefinitionStorage;
+use crate::components::{
+    BodyComponent, BuildingComponent, FoliageComponent, ItemComponent, PropertiesComponent,
+    RaceComponent, UtilityStateComponent,
+};
+use core::amethyst::ecs::{
+    Component, Entities, Join, ReadExpect, ReadStorage, SystemData, World, WorldExt, WriteStorage,
+};
 use core::defs::{
-    action::ActionDefinition, body::BodyDefinition, building::BuildingDefinition,
-    creature::CreatureDefinition, digestion::DigestionDefinition, foliage::FoliageDefinition,
-    item::ItemDefinition, material::MaterialDefinition, race::RaceDefinition,
-    reaction::ReactionDefinition, InheritDefinitionStorage, Named,
+    action::ActionDefinition,
+    behavior::BehaviorDefinition,
+    body::BodyDefinition,
+    building::BuildingDefinition,
+    bundle::{load_definitions, DefinitionBundle},
+    creature::CreatureDefinition,
+    digestion::DigestionDefinition,
+    foliage::FoliageDefinition,
+    item::ItemDefinition,
+    material::MaterialDefinition,
+    mods::mod_list,
+    property::PropertyKind,
+    psyche::{PsycheTraitDefinition, ThoughtDefinition},
+    race::RaceDefinition,
+    reaction::ReactionDefinition,
+    sprites::{SpriteOntoFlags, SpriteRef},
+    watcher::DefinitionWatcher,
+    Definition, DefinitionComponent, DefinitionDiff, DefinitionStorage, HasProperties,
+    InheritDefinition, InheritDefinitionStorage, Named,
 };
+use core::settings::GraphicsSettings;
+use core::tiles::MovementCostTable;
+use std::time::Duration;
+
+/// Root folder of every definition storage, watched for hot reloading.
+pub const DEFINITIONS_ROOT: &str = "resources/defs";
+
+/// Folder of mods layered on top of `DEFINITIONS_ROOT`, one directory each.
+pub const MODS_ROOT: &str = "resources/mods";
+
+/// Compiled definitions, used instead of the RON files while they are up to date with them.
+pub const DEFINITIONS_BUNDLE: &str = core::defs::bundle::DEFAULT_BUNDLE_PATH;
 
 pub fn assets(world: &mut World) -> Result<(), failure::Error> {
     log::info!("Loading definitions...");
 
     world.register::<core::components::SpatialComponent>();
 
-    let mut storage =
-        DefinitionStorage::<MaterialDefinition>::from_folder("resources/defs/materials")?;
-    storage.apply_inherits()?;
-    world.insert(storage);
+    let mods = mod_list(DEFINITIONS_ROOT, MODS_ROOT)?;
+    for m in &mods {
+        log::info!("Loading mod {} {}", m.name(), m.manifest.version);
+    }
 
-    let mut storage = DefinitionStorage::<BodyDefinition>::from_folder("resources/defs/bodies")?;
-    storage.apply_inherits()?;
-    world.insert(storage);
+    match DefinitionBundle::load_current(DEFINITIONS_BUNDLE, &mods) {
+        Some(bundle) => {
+            log::info!("Using definitions bundle {}", DEFINITIONS_BUNDLE);
+            bundle.insert_into(world)?;
+        }
+        None => load_definitions(world, &mods)?,
+    }
 
-    let mut storage =
-        DefinitionStorage::<DigestionDefinition>::from_folder("resources/defs/digestion")?;
-    storage.apply_inherits()?;
-    world.insert(storage);
+    let movement_costs = MovementCostTable::new(
+        &world.fetch::<DefinitionStorage<MaterialDefinition>>(),
+        &world.fetch::<DefinitionStorage<BuildingDefinition>>(),
+    );
+    world.insert(movement_costs);
 
-    world.insert(DefinitionStorage::<RaceDefinition>::from_folder(
-        "resources/defs/races",
-    )?);
+    world.insert(DefinitionWatcher::new(DEFINITIONS_ROOT, Duration::from_secs(1)).watch(MODS_ROOT));
 
-    world.insert(DefinitionStorage::<CreatureDefinition>::from_folder(
-        "resources/defs/creatures",
-    )?);
+    validate_defs(world)
+}
 
-    world.insert(DefinitionStorage::<ActionDefinition>::from_folder(
-        "resources/defs/actions",
-    )?);
+/// What a definitions reload changed, per storage.
+#[derive(Debug, Default)]
+pub struct DefinitionReloadReport {
+    pub diffs: Vec<(&'static str, DefinitionDiff)>,
+}
+impl DefinitionReloadReport {
+    pub fn diff(&self, kind: &str) -> Option<&DefinitionDiff> {
+        self.diffs
+            .iter()
+            .find(|(name, _)| *name == kind)
+            .map(|(_, diff)| diff)
+    }
 
-    world.insert(DefinitionStorage::<ReactionDefinition>::from_folder(
-        "resources/defs/reactions",
-    )?);
+    pub fn is_empty(&self) -> bool { self.diffs.iter().all(|(_, diff)| diff.is_empty()) }
+}
+impl std::fmt::Display for DefinitionReloadReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no definitions changed");
+        }
 
-    world.insert(DefinitionStorage::<BuildingDefinition>::from_folder(
-        "resources/defs/buildings",
-    )?);
+        for (kind, diff) in self.diffs.iter().filter(|(_, diff)| !diff.is_empty()) {
+            writeln!(f, "{}: {}", kind, diff)?;
+        }
+        Ok(())
+    }
+}
 
-    world.insert(DefinitionStorage::<FoliageDefinition>::from_folder(
-        "resources/defs/foliage",
-    )?);
+fn stage<T>(world: &World, staging: &mut World) -> Result<(), failure::Error>
+where
+    T: std::fmt::Debug
+        + Clone
+        + Definition
+        + for<'a> serde::Deserialize<'a>
+        + serde::Serialize
+        + Send
+        + Sync
+        + 'static,
+{
+    let fresh = world.fetch::<DefinitionStorage<T>>().load_fresh()?;
+    staging.insert(fresh);
+    Ok(())
+}
 
-    world.insert(DefinitionStorage::<ItemDefinition>::from_folder(
-        "resources/defs/items",
-    )?);
+fn stage_inherited<T>(world: &World, staging: &mut World) -> Result<(), failure::Error>
+where
+    T: std::fmt::Debug
+        + Clone
+        + Definition
+        + InheritDefinition
+        + for<'a> serde::Deserialize<'a>
+        + serde::Serialize
+        + Send
+        + Sync
+        + 'static,
+{
+    let mut fresh = world.fetch::<DefinitionStorage<T>>().load_fresh()?;
+    fresh.apply_inherits()?;
+    staging.insert(fresh);
+    Ok(())
+}
 
-    validate_defs(world)
+/// Merges a staged storage into the live one, returning the live storage as it was before.
+fn commit<T>(
+    world: &mut World,
+    staging: &mut World,
+    report: &mut DefinitionReloadReport,
+    kind: &'static str,
+) -> DefinitionStorage<T>
+where
+    T: std::fmt::Debug
+        + Clone
+        + Definition
+        + for<'a> serde::Deserialize<'a>
+        + serde::Serialize
+        + Send
+        + Sync
+        + 'static,
+{
+    let fresh = staging.remove::<DefinitionStorage<T>>().unwrap();
+    let mut storage = world.fetch_mut::<DefinitionStorage<T>>();
+    let previous = storage.clone();
+    report.diffs.push((kind, storage.update_from(fresh)));
+    previous
 }
 
-pub fn reload_defs(world: &mut World) -> Result<(), failure::Error> {
-    log::info!("Reloading definitions...");
+/// Swaps the default properties of each entity's old definition for those of its new one,
+/// keeping any properties the entity gained on its own.
+fn refresh_properties<C>(
+    world: &mut World,
+    previous: &DefinitionStorage<C::DefinitionType>,
+    diff: Option<&DefinitionDiff>,
+) where
+    C: Component + DefinitionComponent,
+    C::Storage: Default,
+    C::DefinitionType: HasProperties + Send + Sync + 'static,
+{
+    let changed = match diff {
+        Some(diff) if !diff.changed.is_empty() => &diff.changed,
+        _ => return,
+    };
+
+    world.register::<C>();
+    world.register::<PropertiesComponent>();
+
+    let (components, mut properties, storage) = <(
+        ReadStorage<'_, C>,
+        WriteStorage<'_, PropertiesComponent>,
+        ReadExpect<'_, DefinitionStorage<C::DefinitionType>>,
+    )>::fetch(world);
 
-    /*
-    world
-        .fetch_mut::<DefinitionStorage<MaterialDefinition>>()
-        .reload()?;
-    world
-        .fetch_mut::<DefinitionStorage<BodyDefinition>>()
-        .reload()?;
-    world
-        .fetch_mut::<DefinitionStorage<ActionDefinition>>()
-        .reload()?;
-    world
-        .fetch_mut::<DefinitionStorage<BuildingDefinition>>()
-        .reload()?;
-        */
-    assets(world)
+    for (component, properties) in (&components, &mut properties).join() {
+        let (before, after) = match (component.fetch_def(previous), component.fetch_def(&storage)) {
+            (Some(before), Some(after)) => (before, after),
+            _ => continue,
+        };
+        if !changed.iter().any(|name| name == after.name()) {
+            continue;
+        }
+
+        for property in before.default_properties().iter() {
+            properties.remove(PropertyKind::from(property));
+        }
+        properties.extend(after.default_properties().iter());
+    }
 }
 
-pub fn validate_foliage(world: &World) -> Result<(), failure::Error> {
-    // Validate that all materials actually exist
-    let foliage = world.fetch::<DefinitionStorage<FoliageDefinition>>();
-    let materials = world.fetch::<DefinitionStorage<MaterialDefinition>>();
-
-    println!("Materials: {:?}", *materials);
-    println!("find = {:?}", materials.find("oak"));
-
-    for foliage in foliage.iter() {
-        for layer in &foliage.material_layers {
-            materials.find(&layer.material.name()).ok_or_else(|| {
-                failure::format_err!(
-                    "Invalid material specified on foliage! name={}, material={}",
-                    foliage.name(),
-                    layer.material.name()
-                )
-            })?;
+/// Lines the part states of every body whose definition changed back up with its new part
+/// graph, keeping the wounds on parts which are still there.
+fn refresh_bodies(
+    world: &mut World,
+    previous: &DefinitionStorage<BodyDefinition>,
+    diff: Option<&DefinitionDiff>,
+) {
+    let changed = match diff {
+        Some(diff) if !diff.changed.is_empty() => &diff.changed,
+        _ => return,
+    };
+
+    world.register::<BodyComponent>();
+
+    let (mut bodies, storage) = <(
+        WriteStorage<'_, BodyComponent>,
+        ReadExpect<'_, DefinitionStorage<BodyDefinition>>,
+    )>::fetch(world);
+
+    for body in (&mut bodies).join() {
+        let (before, after) = match (body.fetch_def(previous), body.fetch_def(&storage)) {
+            (Some(before), Some(after)) => (before, after),
+            _ => continue,
+        };
+        if !changed.iter().any(|name| name == after.name()) {
+            continue;
         }
+
+        body.remap(before, after);
     }
+}
 
-    Ok(())
+/// Rebuilds the decisions of every entity whose behavior definition changed. Scores, cooldowns
+/// and the current choice are all tied to the old decisions, so they start over.
+fn refresh_behaviors(world: &mut World, diff: Option<&DefinitionDiff>) {
+    let changed = match diff {
+        Some(diff) if !diff.changed.is_empty() => &diff.changed,
+        _ => return,
+    };
+
+    world.register::<UtilityStateComponent>();
+
+    let (mut states, storage) = <(
+        WriteStorage<'_, UtilityStateComponent>,
+        ReadExpect<'_, DefinitionStorage<BehaviorDefinition>>,
+    )>::fetch(world);
+
+    for state in (&mut states).join() {
+        if !changed.iter().any(|name| name == state.behavior()) {
+            continue;
+        }
+        if let Some(def) = storage.find(state.behavior()) {
+            *state = UtilityStateComponent::from_definition(def);
+        }
+    }
+}
+
+/// Re-applies the sprite of every entity whose definition changed.
+fn refresh_sprites<C, F>(
+    world: &mut World,
+    diff: Option<&DefinitionDiff>,
+    z_modifier: f32,
+    sprite: F,
+) where
+    C: Component + DefinitionComponent,
+    C::Storage: Default,
+    C::DefinitionType: Send + Sync + 'static,
+    F: Fn(&C::DefinitionType) -> Option<SpriteRef>,
+{
+    let changed = match diff {
+        Some(diff) if !diff.changed.is_empty() => &diff.changed,
+        _ => return,
+    };
+
+    world.register::<C>();
+
+    let updates = {
+        let (entities, components, storage) = <(
+            Entities<'_>,
+            ReadStorage<'_, C>,
+            ReadExpect<'_, DefinitionStorage<C::DefinitionType>>,
+        )>::fetch(world);
+
+        (&entities, &components)
+            .join()
+            .filter_map(|(entity, component)| {
+                let def = component.fetch_def(&storage)?;
+                if changed.iter().any(|name| name == def.name()) {
+                    sprite(def).map(|sprite| (entity, sprite))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for (entity, sprite) in updates {
+        sprite.onto_entity(entity, world, z_modifier, SpriteOntoFlags::All);
+    }
+}
+
+/// Reloads every definition storage from disk. Ids are kept stable by name, so components keep
+/// pointing at the same definitions. Everything is loaded, inherited and validated before any
+/// live storage is touched, so a broken file leaves the running game as it was. Entities whose
+/// definitions changed have their properties, behaviors and sprites refreshed.
+pub fn reload_defs(world: &mut World) -> Result<DefinitionReloadReport, failure::Error> {
+    log::info!("Reloading definitions...");
+
+    let mut staging = World::new();
+    stage_inherited::<MaterialDefinition>(world, &mut staging)?;
+    stage_inherited::<BodyDefinition>(world, &mut staging)?;
+    stage_inherited::<DigestionDefinition>(world, &mut staging)?;
+    stage::<RaceDefinition>(world, &mut staging)?;
+    stage::<BehaviorDefinition>(world, &mut staging)?;
+    stage::<CreatureDefinition>(world, &mut staging)?;
+    stage::<ActionDefinition>(world, &mut staging)?;
+    stage::<ReactionDefinition>(world, &mut staging)?;
+    stage::<BuildingDefinition>(world, &mut staging)?;
+    stage::<FoliageDefinition>(world, &mut staging)?;
+    stage::<ItemDefinition>(world, &mut staging)?;
+    stage::<PsycheTraitDefinition>(world, &mut staging)?;
+    stage::<ThoughtDefinition>(world, &mut staging)?;
+    if let Some(graphics) = world.try_fetch::<GraphicsSettings>() {
+        staging.insert(graphics.clone());
+    }
+    validate_defs(&staging)?;
+
+    let mut report = DefinitionReloadReport::default();
+    commit::<MaterialDefinition>(world, &mut staging, &mut report, "materials");
+    let bodies = commit::<BodyDefinition>(world, &mut staging, &mut report, "bodies");
+    commit::<DigestionDefinition>(world, &mut staging, &mut report, "digestion");
+    let races = commit::<RaceDefinition>(world, &mut staging, &mut report, "races");
+    commit::<BehaviorDefinition>(world, &mut staging, &mut report, "behaviors");
+    commit::<CreatureDefinition>(world, &mut staging, &mut report, "creatures");
+    commit::<ActionDefinition>(world, &mut staging, &mut report, "actions");
+    commit::<ReactionDefinition>(world, &mut staging, &mut report, "reactions");
+    let buildings = commit::<BuildingDefinition>(world, &mut staging, &mut report, "buildings");
+    let foliage = commit::<FoliageDefinition>(world, &mut staging, &mut report, "foliage");
+    let items = commit::<ItemDefinition>(world, &mut staging, &mut report, "items");
+    commit::<PsycheTraitDefinition>(world, &mut staging, &mut report, "psyche");
+    commit::<ThoughtDefinition>(world, &mut staging, &mut report, "thoughts");
+
+    refresh_properties::<RaceComponent>(world, &races, report.diff("races"));
+    refresh_properties::<BodyComponent>(world, &bodies, report.diff("bodies"));
+    refresh_bodies(world, &bodies, report.diff("bodies"));
+    refresh_properties::<BuildingComponent>(world, &buildings, report.diff("buildings"));
+    refresh_properties::<FoliageComponent>(world, &foliage, report.diff("foliage"));
+    refresh_properties::<ItemComponent>(world, &items, report.diff("items"));
+    refresh_behaviors(world, report.diff("behaviors"));
+
+    refresh_sprites::<BodyComponent, _>(
+        world,
+        report.diff("bodies"),
+        core::z_level_modifiers::PAWN,
+        |def| def.sprite.clone(),
+    );
+    refresh_sprites::<BuildingComponent, _>(
+        world,
+        report.diff("buildings"),
+        core::z_level_modifiers::BUILDING,
+        |def| Some(def.sprite.clone()),
+    );
+    refresh_sprites::<FoliageComponent, _>(
+        world,
+        report.diff("foliage"),
+        core::z_level_modifiers::FOLIAGE,
+        |def| Some(def.sprite.clone()),
+    );
+
+    let movement_costs = MovementCostTable::new(
+        &world.fetch::<DefinitionStorage<MaterialDefinition>>(),
+        &world.fetch::<DefinitionStorage<BuildingDefinition>>(),
+    );
+    world.insert(movement_costs);
+
+    log::info!("Reloaded definitions: {}", report);
+    Ok(report)
 }
 
+/// Reloads definitions whenever a file under `DEFINITIONS_ROOT` changes on disk. Polling is
+/// throttled by the `DefinitionWatcher`, so this can be called every frame.
+pub fn hot_reload(world: &mut World) {
+    let changed = match world.try_fetch_mut::<DefinitionWatcher>() {
+        Some(mut watcher) => watcher.poll(),
+        None => return,
+    };
+    if changed.is_empty() {
+        return;
+    }
+
+    log::info!("Definition files changed: {:?}", changed);
+    if let Err(e) = reload_defs(world) {
+        log::error!("Reloading of definitions FAILED!: {:?}", e);
+    }
+}
+
+/// Checks every cross-reference between definitions, reporting all broken references at once.
 pub fn validate_defs(world: &World) -> Result<(), failure::Error> {
-    validate_foliage(world)?;
+    core::defs::validation::validate(world)?;
 
     Ok(())
 }
@@ -116,4 +408,150 @@ pub mod tests {
         super::assets(&mut world)?;
         super::validate_defs(&world)
     }
+
+    #[test]
+    fn reload_unchanged_definitions() -> Result<(), failure::Error> {
+        use core::defs::{item::ItemDefinition, DefinitionStorage, Named};
+
+        let mut world = World::new();
+        super::assets(&mut world)?;
+        let ids = world
+            .fetch::<DefinitionStorage<ItemDefinition>>()
+            .iter()
+            .map(|def| (def.name().to_string(), def.id()))
+            .collect::<Vec<_>>();
+
+        let report = super::reload_defs(&mut world)?;
+        assert!(report.is_empty(), "{}", report);
+
+        let items = world.fetch::<DefinitionStorage<ItemDefinition>>();
+        for (name, id) in ids {
+            assert_eq!(items.find(&name).unwrap().id(), id);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reload_body_with_new_part() -> Result<(), failure::Error> {
+        use crate::components::BodyComponent;
+        use amethyst::ecs::Builder;
+        use body::damage::{AttackKind, Hit};
+        use core::{
+            defs::{
+                body::{BodyDefinition, Joint, Part},
+                material::MaterialDefinition,
+                DefinitionDiff, DefinitionStorage, Named,
+            },
+            petgraph::{graph::NodeIndex, Graph},
+            rand::SeedableRng,
+            rand_xorshift::XorShiftRng,
+        };
+
+        // Names of the parts with wounds
+        fn wounded<'a>(body: &BodyComponent, graph: &'a Graph<Part, Joint>) -> Vec<&'a str> {
+            body.part_states
+                .iter()
+                .zip(graph.raw_nodes())
+                .filter(|(state, _)| {
+                    state
+                        .layer_states
+                        .iter()
+                        .any(|layer| !layer.wounds.is_empty())
+                })
+                .map(|(_, node)| node.weight.name())
+                .collect()
+        }
+
+        let before = DefinitionStorage::<BodyDefinition>::from_folder("resources/defs/bodies")?;
+        let mut after = DefinitionStorage::<BodyDefinition>::from_folder("resources/defs/bodies")?;
+        let materials =
+            DefinitionStorage::<MaterialDefinition>::from_folder("resources/defs/materials")?;
+
+        // The new version of the body has a tail ahead of every other part.
+        let def = after.find_mut("Humanoid").unwrap();
+        let parts = def.part_graph.take().unwrap();
+        let mut graph = Graph::new();
+        graph.add_node(Part::new("Tail", 5, None, &[]));
+        for node in parts.raw_nodes() {
+            graph.add_node(node.weight.clone());
+        }
+        for edge in parts.raw_edges() {
+            graph.add_edge(
+                NodeIndex::new(edge.source().index() + 1),
+                NodeIndex::new(edge.target().index() + 1),
+                edge.weight.clone(),
+            );
+        }
+        def.part_graph = Some(graph);
+
+        let id = before.get_id("Humanoid").unwrap();
+        let mut body = BodyComponent::new(id, &before);
+        let mut rng = XorShiftRng::seed_from_u64(0);
+        body.hit_part(
+            "Right Upper Arm",
+            Hit::new(AttackKind::Cutting, 200.0),
+            before.get(id).unwrap(),
+            &materials,
+            &mut rng,
+        )
+        .unwrap();
+
+        let before_wounds = wounded(&body, before.get(id).unwrap().part_graph.as_ref().unwrap());
+
+        let mut world = World::new();
+        world.register::<BodyComponent>();
+        world.insert(after);
+        let entity = world.create_entity().with(body).build();
+
+        let diff = DefinitionDiff {
+            changed: vec!["Humanoid".to_string()],
+            ..DefinitionDiff::default()
+        };
+        super::refresh_bodies(&mut world, &before, Some(&diff));
+
+        let after = world.fetch::<DefinitionStorage<BodyDefinition>>();
+        let graph = after.get(id).unwrap().part_graph.as_ref().unwrap();
+        let bodies = world.read_storage::<BodyComponent>();
+        let body = bodies.get(entity).unwrap();
+        assert_eq!(body.part_states.len(), graph.node_count());
+        assert_eq!(body.joint_states.len(), graph.edge_count());
+        assert_eq!(wounded(body, graph), before_wounds);
+        assert!(before_wounds.contains(&"Right Upper Arm"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reload_changed_behavior() -> Result<(), failure::Error> {
+        use crate::components::UtilityStateComponent;
+        use amethyst::ecs::Builder;
+        use core::defs::{behavior::BehaviorDefinition, DefinitionDiff, DefinitionStorage};
+
+        let mut behaviors =
+            DefinitionStorage::<BehaviorDefinition>::from_folder("resources/defs/behaviors")?;
+        let state = UtilityStateComponent::from_definition(behaviors.find("Cow Root").unwrap());
+        let decisions = state.available_decisions.len();
+
+        // The new version of the behavior forgets how to rest.
+        for bucket in &mut behaviors.find_mut("Cow Root").unwrap().buckets {
+            bucket.decisions.retain(|decision| decision.name != "Rest");
+        }
+
+        let mut world = World::new();
+        world.register::<UtilityStateComponent>();
+        world.insert(behaviors);
+        let entity = world.create_entity().with(state).build();
+
+        let diff = DefinitionDiff {
+            changed: vec!["Cow Root".to_string()],
+            ..DefinitionDiff::default()
+        };
+        super::refresh_behaviors(&mut world, Some(&diff));
+
+        let states = world.read_storage::<UtilityStateComponent>();
+        let state = states.get(entity).unwrap();
+        assert_eq!(state.behavior(), "Cow Root");
+        assert_eq!(state.available_decisions.len(), decisions - 1);
+        Ok(())
+    }
 }
@@ -23,6 +23,7 @@ use core::{
     num_traits::FromPrimitive,
     rand::SeedableRng,
     rand_xorshift::XorShiftRng,
+    rng::WorldRng,
     settings::GraphicsSettings,
     tiles::region::RegionTile,
 };
@@ -35,7 +36,9 @@ pub fn create_test_trees<R>(world: &mut World, rng: &mut R)
 where
     R: core::rand::Rng,
 {
-    use core::{amethyst::tiles::MapStorage, defs::property::MovementFlags};
+    use core::{
+        amethyst::tiles::MapStorage, defs::property::MovementFlags, tiles::MovementCostTable,
+    };
 
     for i in 0..5000 {
         let tile_position = {
@@ -49,7 +52,7 @@ where
             );
 
             if let Some(tile) = map.get(&coord) {
-                if !tile.passable(MovementFlags::Walk) {
+                if !tile.passable(MovementFlags::Walk, &world.fetch::<MovementCostTable>()) {
                     continue;
                 }
             }
@@ -146,6 +149,7 @@ impl TestRegion {
         let seed = map::utils::seed_from_str(self.seed.to_str());
         let mut rng = XorShiftRng::from_seed(*arrayref::array_ref![&seed, 0, 16]);
         gen.execute(&mut map, world, &mut rng)?;
+        world.insert(WorldRng::from_rng(&mut rng));
 
         world
             .create_entity()
@@ -210,6 +214,8 @@ impl<'a, 'b> amethyst::State<GameData<'a, 'b>, StateEvent> for TestRegion {
         let StateData { world, .. } = data;
         data.data.update(world);
 
+        crate::loaders::hot_reload(world);
+
         amethyst_imgui::with(|ui| {
             self.ui_manager.as_mut().unwrap().draw(ui, world);
 
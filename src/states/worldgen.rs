@@ -101,6 +101,8 @@ impl<'a, 'b> amethyst::State<GameData<'a, 'b>, StateEvent> for WorldGeneration {
         let StateData { world, .. } = data;
         data.data.update(world);
 
+        crate::loaders::hot_reload(world);
+
         amethyst_imgui::with(|ui| {
             self.ui_manager.as_mut().unwrap().draw(ui, world);
 
@@ -1 +1,7 @@
-
+pub use ai::behavior::{
+    execution::{PlanExecutionSystem, PlanExecutionSystemDesc},
+    planner::{
+        GoapPlannerSystem, GoapPlannerSystemDesc, PlanningWorldSystem, PlanningWorldSystemDesc,
+    },
+    utility::{DecisionGoalSystem, DecisionGoalSystemDesc, UtilitySystem, UtilitySystemDesc},
+};
@@ -20,6 +20,15 @@ pub use movement::{PathingMovementSystem, PathingMovementSystemDesc};
 pub mod pawn_pickup;
 pub use pawn_pickup::PawnPickupItemSystem;
 
+pub mod pawn_ingest;
+pub use pawn_ingest::PawnIngestSystem;
+
+pub mod pawn_social;
+pub use pawn_social::PawnSocialSystem;
+
+pub mod spoilage;
+pub use spoilage::SpoilageSystem;
+
 pub mod input;
 
 pub use ai::pathing::{PathingWorkSystem, PathingWorkSystemDesc};
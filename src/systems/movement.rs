@@ -19,6 +19,7 @@ use core::{
     fsm::{ActionEvent, ActionStatus, ActionTarget, Event, MovementEvent},
     tiles::region::RegionTile,
 };
+use std::sync::atomic::Ordering;
 
 #[derive(Default)]
 pub struct MovementTrackComponent {
@@ -173,6 +174,22 @@ impl<'s> System<'s> for PathingMovementSystem {
             if let Some(path_result) = &path.current_path {
                 if let Ok(path_result) = path_result {
                     let (request, path) = path_result;
+
+                    // The map changed underneath this path, so request a new one from where we are
+                    if !path.valid.load(Ordering::Relaxed) {
+                        log::trace!("Path invalidated, re-requesting a path...");
+                        let source_tile_pos = map
+                            .to_tile(transform_storage.get(entity).unwrap().translation())
+                            .unwrap();
+                        path_request_channel.single_write(PathingRequestEvent::new(
+                            entity,
+                            source_tile_pos,
+                            request.destination,
+                            request.kind,
+                        ));
+                        track.current_path_index = None;
+                        continue;
+                    }
                     log::trace!("Valid path, result and track");
 
                     // Lets make sure the current action entity hasn't moved from our pathing destination
@@ -257,7 +274,7 @@ impl<'s> System<'s> for PathingMovementSystem {
         for (status, entity) in &self.done {
             log::trace!("Movement complete, clearing...");
             if let Some(active_action) = active_action_storage.get_mut(*entity) {
-                active_action.status = Ok(*status);
+                active_action.status = *status;
             }
             //if let Some(path) = current_pathing_storage.get_mut(*entity) {
             //    path.complete();
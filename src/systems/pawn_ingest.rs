@@ -0,0 +1,145 @@
+use crate::components::{
+    BodyComponent, CurrentActionComponent, ItemParentComponent, MoodComponent,
+};
+use body::physiology::FOOD_POISONING_TIME;
+use core::{
+    amethyst::{
+        core::{SystemDesc, Transform},
+        ecs::{Entities, Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage},
+        shrev::{EventChannel, ReaderId},
+        tiles::{Map, TileMap},
+    },
+    components::{DigestionComponent, EdibleComponent},
+    defs::{
+        digestion::{DigestionDefinition, EdibleState},
+        psyche::ThoughtDefinition,
+        DefinitionComponent, DefinitionStorage,
+    },
+    fsm::{ActionEvent, ActionStatus, ActionTarget, Event},
+    tiles::region::RegionTile,
+};
+use psyche::mood::{ATE_A_GOOD_MEAL, ATE_BURNT_FOOD, ATE_RAW_FOOD, ATE_ROTTEN_FOOD};
+
+/// Eats or drinks the target of an `Ingest` action, if the pawn can eat it and has room for it.
+#[derive(Default)]
+pub struct PawnIngestSystem {
+    reader: Option<ReaderId<ActionEvent>>,
+}
+impl<'s> System<'s> for PawnIngestSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, EventChannel<ActionEvent>>,
+        Read<'s, DefinitionStorage<DigestionDefinition>>,
+        Read<'s, DefinitionStorage<ThoughtDefinition>>,
+        ReadStorage<'s, TileMap<RegionTile>>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, ItemParentComponent>,
+        ReadStorage<'s, EdibleComponent>,
+        WriteStorage<'s, DigestionComponent>,
+        WriteStorage<'s, BodyComponent>,
+        WriteStorage<'s, MoodComponent>,
+        WriteStorage<'s, CurrentActionComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            events,
+            digestion_defs,
+            thought_defs,
+            map_storage,
+            transform_storage,
+            item_parents_storage,
+            edible_storage,
+            mut digestion_storage,
+            mut body_storage,
+            mut mood_storage,
+            mut active_action_storage,
+        ): Self::SystemData,
+    ) {
+        for action in events.read(self.reader.as_mut().unwrap()) {
+            if let Event::Ingest = &action.event {
+                log::trace!("ingest action received");
+
+                let source_entity = action.source.unwrap();
+                let target_entity =
+                    if let ActionTarget::Entity(target) = action.targets.as_ref().unwrap()[0] {
+                        target
+                    } else {
+                        panic!()
+                    };
+
+                let active = active_action_storage.get_mut(source_entity).unwrap();
+
+                // Food being carried by the pawn is always in reach, otherwise it must be nearby
+                let carried = item_parents_storage
+                    .get(target_entity)
+                    .map_or(false, |parent| parent.parent == source_entity);
+                let in_reach = carried || {
+                    let map = (&map_storage).join().next().unwrap();
+                    let source_transform = transform_storage.get(source_entity).unwrap();
+                    let target_transform = transform_storage.get(target_entity).unwrap();
+
+                    core::tiles::distance(
+                        map.to_tile(source_transform.translation()).unwrap(),
+                        map.to_tile(target_transform.translation()).unwrap(),
+                    ) <= 1
+                };
+
+                let eaten = in_reach
+                    && match (
+                        edible_storage.get(target_entity),
+                        digestion_storage.get_mut(source_entity),
+                    ) {
+                        (Some(edible), Some(digestion)) => {
+                            let def = digestion.fetch_def(&digestion_defs).unwrap();
+                            digestion.ingest(def, edible)
+                        }
+                        _ => false,
+                    };
+
+                if eaten {
+                    // Spoiled food makes whoever eats it sick
+                    let state = edible_storage
+                        .get(target_entity)
+                        .map(EdibleComponent::state);
+                    if state == Some(EdibleState::Rotten) {
+                        if let Some(body) = body_storage.get_mut(source_entity) {
+                            body.physiology.sicken(FOOD_POISONING_TIME);
+                        }
+                    }
+
+                    // ...and everyone remembers how their meal was
+                    let thought = match state {
+                        Some(EdibleState::Cooked) => Some(ATE_A_GOOD_MEAL),
+                        Some(EdibleState::Raw) => Some(ATE_RAW_FOOD),
+                        Some(EdibleState::Burnt) => Some(ATE_BURNT_FOOD),
+                        Some(EdibleState::Rotten) => Some(ATE_ROTTEN_FOOD),
+                        _ => None,
+                    };
+                    if let (Some(def), Some(mood)) = (
+                        thought.and_then(|thought| thought_defs.find(thought)),
+                        mood_storage.get_mut(source_entity),
+                    ) {
+                        mood.think(def);
+                    }
+
+                    entities.delete(target_entity).unwrap();
+                    active.status = ActionStatus::Success;
+                } else {
+                    active.status = ActionStatus::Failure;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, 'b> SystemDesc<'a, 'b, PawnIngestSystem> for PawnIngestSystem {
+    fn build(self, world: &mut World) -> Self {
+        <Self as System<'_>>::SystemData::setup(world);
+        let reader = Some(Write::<EventChannel<ActionEvent>>::fetch(world).register_reader());
+
+        Self { reader }
+    }
+}
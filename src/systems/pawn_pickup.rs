@@ -69,13 +69,13 @@ impl<'s> System<'s> for PawnPickupItemSystem {
                     map.to_tile(target_transform.translation()).unwrap(),
                 ) > 1
                 {
-                    active.status = Ok(ActionStatus::Failure);
+                    active.status = ActionStatus::Failure;
                     return;
                 }
 
                 // Confirm we still are near the source entity, and it hasnt been picked up. If so, fail
                 if let Some(parent) = item_parents_storage.get(source_entity) {
-                    active.status = Ok(ActionStatus::Failure);
+                    active.status = ActionStatus::Failure;
                     return;
                 }
 
@@ -87,7 +87,7 @@ impl<'s> System<'s> for PawnPickupItemSystem {
                     )
                     .unwrap();
 
-                active.status = Ok(ActionStatus::Success);
+                active.status = ActionStatus::Success;
             }
         }
     }
@@ -0,0 +1,142 @@
+use crate::components::{
+    AttributesComponent, CurrentActionComponent, PawnComponent, PersonalityComponent,
+    PyscheNeedsComponent, RelationshipsComponent,
+};
+use core::{
+    amethyst::{
+        core::{SystemDesc, Transform},
+        ecs::{Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage},
+        shrev::{EventChannel, ReaderId},
+        tiles::{Map, TileMap},
+    },
+    defs::{psyche::PsycheTraitDefinition, DefinitionStorage},
+    fsm::{ActionEvent, ActionStatus, ActionTarget, Event},
+    tiles::region::RegionTile,
+};
+use psyche::social::{socialize, Participant};
+
+/// Has a pawn chat with, insult or comfort the target of a `Socialize` action, if it is standing
+/// next to them.
+#[derive(Default)]
+pub struct PawnSocialSystem {
+    reader: Option<ReaderId<ActionEvent>>,
+}
+impl<'s> System<'s> for PawnSocialSystem {
+    type SystemData = (
+        Read<'s, EventChannel<ActionEvent>>,
+        Read<'s, DefinitionStorage<PsycheTraitDefinition>>,
+        ReadStorage<'s, TileMap<RegionTile>>,
+        ReadStorage<'s, Transform>,
+        ReadStorage<'s, PawnComponent>,
+        ReadStorage<'s, AttributesComponent>,
+        ReadStorage<'s, PersonalityComponent>,
+        WriteStorage<'s, RelationshipsComponent>,
+        WriteStorage<'s, PyscheNeedsComponent>,
+        WriteStorage<'s, CurrentActionComponent>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            events,
+            trait_defs,
+            map_storage,
+            transform_storage,
+            pawn_storage,
+            attributes_storage,
+            personality_storage,
+            mut relationships_storage,
+            mut needs_storage,
+            mut active_action_storage,
+        ): Self::SystemData,
+    ) {
+        for action in events.read(self.reader.as_mut().unwrap()) {
+            if let Event::Socialize(interaction) = &action.event {
+                log::trace!("socialize action received");
+
+                let source_entity = action.source.unwrap();
+                let target_entity =
+                    if let ActionTarget::Entity(target) = action.targets.as_ref().unwrap()[0] {
+                        target
+                    } else {
+                        panic!()
+                    };
+
+                let in_reach = source_entity != target_entity && {
+                    let map = (&map_storage).join().next().unwrap();
+                    let source_transform = transform_storage.get(source_entity).unwrap();
+                    let target_transform = transform_storage.get(target_entity).unwrap();
+
+                    core::tiles::distance(
+                        map.to_tile(source_transform.translation()).unwrap(),
+                        map.to_tile(target_transform.translation()).unwrap(),
+                    ) <= 1
+                };
+
+                let socialized = in_reach
+                    && [source_entity, target_entity].iter().all(|entity| {
+                        pawn_storage.contains(*entity)
+                            && attributes_storage.contains(*entity)
+                            && personality_storage.contains(*entity)
+                            && relationships_storage.contains(*entity)
+                            && needs_storage.contains(*entity)
+                    });
+
+                if socialized {
+                    // Both sides are taken out of their storages while they talk, as specs can't
+                    // lend out two components from the same storage at once.
+                    let mut source_relationships =
+                        relationships_storage.remove(source_entity).unwrap();
+                    let mut source_needs = needs_storage.remove(source_entity).unwrap();
+                    let mut target_relationships =
+                        relationships_storage.remove(target_entity).unwrap();
+                    let mut target_needs = needs_storage.remove(target_entity).unwrap();
+
+                    socialize(
+                        *interaction,
+                        &mut Participant {
+                            id: pawn_storage.get(source_entity).unwrap().id,
+                            attributes: attributes_storage.get(source_entity).unwrap(),
+                            personality: personality_storage.get(source_entity).unwrap(),
+                            relationships: &mut source_relationships,
+                            needs: &mut source_needs,
+                        },
+                        &mut Participant {
+                            id: pawn_storage.get(target_entity).unwrap().id,
+                            attributes: attributes_storage.get(target_entity).unwrap(),
+                            personality: personality_storage.get(target_entity).unwrap(),
+                            relationships: &mut target_relationships,
+                            needs: &mut target_needs,
+                        },
+                        &trait_defs,
+                    );
+
+                    relationships_storage
+                        .insert(source_entity, source_relationships)
+                        .unwrap();
+                    needs_storage.insert(source_entity, source_needs).unwrap();
+                    relationships_storage
+                        .insert(target_entity, target_relationships)
+                        .unwrap();
+                    needs_storage.insert(target_entity, target_needs).unwrap();
+                }
+
+                let active = active_action_storage.get_mut(source_entity).unwrap();
+                active.status = if socialized {
+                    ActionStatus::Success
+                } else {
+                    ActionStatus::Failure
+                };
+            }
+        }
+    }
+}
+
+impl<'a, 'b> SystemDesc<'a, 'b, PawnSocialSystem> for PawnSocialSystem {
+    fn build(self, world: &mut World) -> Self {
+        <Self as System<'_>>::SystemData::setup(world);
+        let reader = Some(Write::<EventChannel<ActionEvent>>::fetch(world).register_reader());
+
+        Self { reader }
+    }
+}
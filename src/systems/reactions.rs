@@ -1,4 +1,6 @@
-use crate::components::{CurrentActionComponent, ItemComponent, PropertiesComponent};
+use crate::components::{
+    CurrentActionComponent, EdibleComponent, ItemComponent, PropertiesComponent,
+};
 use core::{
     amethyst::{
         core::{SystemDesc, Transform},
@@ -9,11 +11,15 @@ use core::{
         shrev::{EventChannel, ReaderId},
     },
     defs::{
+        digestion::EdibleState,
+        property::{Property, PropertyKind},
         reaction::{Kind, ReactionDefinition},
         DefinitionStorage,
     },
     fsm::{ActionEvent, ActionStatus, ActionTarget, Event},
     hibitset::BitSetLike,
+    rand::Rng,
+    rng::WorldRng,
 };
 #[derive(Default)]
 pub struct ExecuteRactionSystem {
@@ -24,11 +30,13 @@ impl<'s> System<'s> for ExecuteRactionSystem {
     type SystemData = (
         Entities<'s>,
         Read<'s, LazyUpdate>,
+        Write<'s, WorldRng>,
         Read<'s, EventChannel<ActionEvent>>,
         Read<'s, DefinitionStorage<ReactionDefinition>>,
         ReadStorage<'s, Transform>,
         ReadStorage<'s, ItemComponent>,
-        ReadStorage<'s, PropertiesComponent>,
+        WriteStorage<'s, PropertiesComponent>,
+        WriteStorage<'s, EdibleComponent>,
         WriteStorage<'s, CurrentActionComponent>,
     );
 
@@ -37,11 +45,13 @@ impl<'s> System<'s> for ExecuteRactionSystem {
         (
             entities,
             lazy,
+            mut rng,
             events,
             reaction_storage,
             transform_storage,
             _item_storage,
-            props_storage,
+            mut props_storage,
+            mut edible_storage,
             mut current_action_storage,
         ): Self::SystemData,
     ) {
@@ -102,6 +112,53 @@ impl<'s> System<'s> for ExecuteRactionSystem {
 
                 log::trace!("Creates: {:?}", def.product);
 
+                // Cooking and the like change the consumed reagents, rather than using them up
+                if let Kind::Edible(state) = &def.product.kind {
+                    let targets = reagent_entities_map
+                        .iter()
+                        .flat_map(|(_, matches)| matches.iter())
+                        .filter_map(|target| {
+                            if let ActionTarget::Entity(entity) = target {
+                                Some(*entity)
+                            } else {
+                                None
+                            }
+                        })
+                        .collect::<Vec<_>>();
+
+                    let burn_chance = burn_chance(action, &props_storage);
+                    let mut success = !targets.is_empty();
+                    for entity in targets {
+                        let state =
+                            if *state == EdibleState::Cooked && rng.gen::<f32>() < burn_chance {
+                                EdibleState::Burnt
+                            } else {
+                                *state
+                            };
+
+                        let cooked = edible_storage
+                            .get_mut(entity)
+                            .map_or(false, |edible| edible.cook(state));
+                        if cooked {
+                            let edible = edible_storage.get(entity).unwrap();
+                            if let Some(properties) = props_storage.get_mut(entity) {
+                                properties.insert(Property::Edible(edible.kind(), state));
+                            }
+                        }
+                        success &= cooked;
+                    }
+
+                    current_action_storage
+                        .get_mut(action.source.unwrap())
+                        .unwrap()
+                        .status = if success {
+                        ActionStatus::Success
+                    } else {
+                        ActionStatus::Failure
+                    };
+                    continue;
+                }
+
                 // Was this conducted at a "thing", or just out in the wild?
                 if action.subjects.is_none() {
                     match &def.product.kind {
@@ -151,7 +208,7 @@ impl<'s> System<'s> for ExecuteRactionSystem {
                 current_action_storage
                     .get_mut(action.source.unwrap())
                     .unwrap()
-                    .status = Ok(ActionStatus::Success);
+                    .status = ActionStatus::Success;
             }
 
             if !self.delete.is_empty() {
@@ -174,6 +231,34 @@ impl<'s> System<'s> for ExecuteRactionSystem {
     }
 }
 
+/// Chance of burning food while cooking it, falling with the best `Cooking` property among the
+/// cook and whatever they are cooking with.
+fn burn_chance(action: &ActionEvent, props_storage: &WriteStorage<'_, PropertiesComponent>) -> f32 {
+    let level = action
+        .source
+        .iter()
+        .copied()
+        .chain(action.targets.iter().flatten().filter_map(|target| {
+            if let ActionTarget::Entity(entity) = target {
+                Some(*entity)
+            } else {
+                None
+            }
+        }))
+        .filter_map(|entity| props_storage.get(entity))
+        .filter_map(|properties| {
+            if let Some(Property::Cooking(level)) = properties.get(PropertyKind::Cooking) {
+                Some(*level)
+            } else {
+                None
+            }
+        })
+        .max()
+        .unwrap_or(0);
+
+    0.5 / (1.0 + f32::from(level))
+}
+
 impl<'a, 'b> SystemDesc<'a, 'b, ExecuteRactionSystem> for ExecuteRactionSystem {
     fn build(self, world: &mut World) -> Self {
         <Self as System<'_>>::SystemData::setup(world);
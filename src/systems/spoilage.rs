@@ -0,0 +1,91 @@
+use crate::components::{
+    EdibleComponent, ItemParentComponent, ItemParentRelationship, PropertiesComponent,
+};
+use core::{
+    amethyst::{
+        derive::SystemDesc,
+        ecs::{Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, WriteStorage},
+    },
+    clock::{Instant, WorldTime},
+    defs::{
+        digestion::EdibleState,
+        property::{Property, PropertyKind},
+    },
+};
+
+/// How much slower food spoils in cold storage, and inside a container.
+const COLD_RATE: f32 = 0.1;
+const CONTAINER_RATE: f32 = 0.5;
+
+/// Ages food as world time passes, turning it rotten once it has been kept too long.
+#[derive(Default, SystemDesc)]
+pub struct SpoilageSystem {
+    pub last: Instant,
+}
+impl<'s> System<'s> for SpoilageSystem {
+    type SystemData = (
+        Entities<'s>,
+        Read<'s, WorldTime>,
+        ReadStorage<'s, ItemParentComponent>,
+        WriteStorage<'s, EdibleComponent>,
+        WriteStorage<'s, PropertiesComponent>,
+    );
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn run(&mut self, (entities, time, parents, mut edibles, mut properties): Self::SystemData) {
+        // Skip execution if the game time hasn't progressed
+        if self.last == time.now() {
+            return;
+        }
+        let elapsed = (time.now() - self.last).value() as u32;
+        self.last = time.now();
+
+        let mut rotten = Vec::new();
+        for (entity, edible) in (&entities, &mut edibles).join() {
+            let rate = spoil_rate(entity, &parents, &properties);
+            if edible.spoil(elapsed, rate) {
+                rotten.push((entity, edible.kind()));
+            }
+        }
+
+        for (entity, kind) in rotten {
+            if let Some(properties) = properties.get_mut(entity) {
+                properties.insert(Property::Edible(kind, EdibleState::Rotten));
+            }
+        }
+    }
+}
+
+/// Food in or on cold storage spoils slowest, and food kept inside a container slower than food
+/// left out.
+fn spoil_rate(
+    entity: Entity,
+    parents: &ReadStorage<'_, ItemParentComponent>,
+    properties: &WriteStorage<'_, PropertiesComponent>,
+) -> f32 {
+    let cold = |entity| {
+        properties
+            .get(entity)
+            .map_or(false, |properties| properties.contains(PropertyKind::Cold))
+    };
+
+    let mut rate = if cold(entity) { COLD_RATE } else { 1.0 };
+    if let Some(parent) = parents.get(entity) {
+        if cold(parent.parent) {
+            rate = rate.min(COLD_RATE);
+        }
+        let inside = if let ItemParentRelationship::Inside = parent.relationship {
+            true
+        } else {
+            false
+        };
+        if inside
+            && properties.get(parent.parent).map_or(false, |properties| {
+                properties.contains(PropertyKind::Container)
+            })
+        {
+            rate *= CONTAINER_RATE;
+        }
+    }
+    rate
+}
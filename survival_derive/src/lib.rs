@@ -99,3 +99,142 @@ pub fn definition_component_derive(input: TokenStream) -> TokenStream {
     // Build the trait implementation
     impl_definition_component(&ast)
 }
+
+/// How a single field is resolved against the parent definition.
+enum InheritMode {
+    /// Left as the child declared it.
+    Skip,
+    /// The field holding the parent's name.
+    Parent,
+    /// `Option`: the parent's value is used if the child has none.
+    Fallback,
+    /// List: the parent's entries the child doesn't already contain are appended.
+    Append,
+    /// List: the parent's entries are used if the child has none.
+    Replace,
+    /// Map: the parent's entries are added for keys the child doesn't define.
+    Merge,
+    /// A `fn(&mut Self, &Self)` which resolves the field itself.
+    With(Path),
+}
+
+fn is_option(ty: &syn::Type) -> bool {
+    if let syn::Type::Path(ty) = ty {
+        ty.path
+            .segments
+            .last()
+            .map_or(false, |segment| segment.into_value().ident == "Option")
+    } else {
+        false
+    }
+}
+
+fn inherit_mode(field: &syn::Field) -> InheritMode {
+    for attr in &field.attrs {
+        if attr.path.segments[0].ident != "inherit" {
+            continue;
+        }
+
+        let nested = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list.nested.into_iter().next(),
+            _ => None,
+        };
+
+        return match nested {
+            Some(syn::NestedMeta::Meta(syn::Meta::Word(word))) => match word.to_string().as_str() {
+                "skip" => InheritMode::Skip,
+                "parent" => InheritMode::Parent,
+                "fallback" => InheritMode::Fallback,
+                "append" => InheritMode::Append,
+                "replace" => InheritMode::Replace,
+                "merge" => InheritMode::Merge,
+                other => panic!("Unknown inherit mode `{}`", other),
+            },
+            Some(syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ident,
+                lit: syn::Lit::Str(lit),
+                ..
+            }))) if ident == "with" => InheritMode::With(lit.parse().unwrap()),
+            _ => panic!("Expected #[inherit(<mode>)] or #[inherit(with = \"path\")]"),
+        };
+    }
+
+    // `name` and `id` belong to the definition itself, like in `NamedDefinition`.
+    let ident = field.ident.as_ref().unwrap();
+    if ident != "name" && ident != "id" && is_option(&field.ty) {
+        InheritMode::Fallback
+    } else {
+        InheritMode::Skip
+    }
+}
+
+fn impl_inherit_definition(ast: &syn::DeriveInput) -> TokenStream {
+    let name = &ast.ident;
+
+    let fields = match &ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => panic!("InheritDefinition can only be derived for structs with named fields"),
+    };
+
+    let mut parent = None;
+    let mut inherits = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        match inherit_mode(field) {
+            InheritMode::Skip => {}
+            InheritMode::Parent => parent = Some(ident),
+            InheritMode::Fallback => inherits.push(quote! {
+                if self.#ident.is_none() {
+                    self.#ident = parent.#ident.clone();
+                }
+            }),
+            InheritMode::Append => inherits.push(quote! {
+                let inherited = parent
+                    .#ident
+                    .iter()
+                    .filter(|v| !self.#ident.contains(v))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                self.#ident.extend(inherited);
+            }),
+            InheritMode::Replace => inherits.push(quote! {
+                if self.#ident.is_empty() {
+                    self.#ident = parent.#ident.clone();
+                }
+            }),
+            InheritMode::Merge => inherits.push(quote! {
+                for (k, v) in parent.#ident.iter() {
+                    self.#ident.entry(k.clone()).or_insert_with(|| v.clone());
+                }
+            }),
+            InheritMode::With(path) => inherits.push(quote! {
+                #path(self, parent);
+            }),
+        }
+    }
+    let parent = parent.expect("InheritDefinition requires a field marked #[inherit(parent)]");
+
+    let gen = quote! {
+        impl InheritDefinition for #name {
+            fn inherit_from(&mut self, parent: &Self) {
+                #({ #inherits })*
+            }
+
+            fn parent(&self) -> Option<&str> {
+                self.#parent.as_ref().map(String::as_str)
+            }
+        }
+    };
+
+    gen.into()
+}
+
+#[proc_macro_derive(InheritDefinition, attributes(inherit))]
+pub fn inherit_definition_derive(input: TokenStream) -> TokenStream {
+    let ast = syn::parse(input).unwrap();
+
+    impl_inherit_definition(&ast)
+}